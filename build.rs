@@ -1,12 +1,18 @@
 fn main() {
-    build_fathom();
+    #[cfg(feature = "bindgen")]
     generate_bindings();
+    #[cfg(feature = "syzygy")]
+    build_fathom();
 }
 
+#[cfg(feature = "syzygy")]
 fn build_fathom() {
     let cc = &mut cc::Build::new();
     cc.file("./imports/fathom/src/tbprobe.c");
+    // non-inline wrappers of the `static inline` probing functions (tb_probe_wdl, tb_probe_root), generated by bindgen
+    cc.file("./src/syzygy/extern.c");
     cc.include("./imports/fathom/src/");
+    cc.include(".");
     cc.define("_CRT_SECURE_NO_WARNINGS", None);
 
 
@@ -18,9 +24,12 @@ fn build_fathom() {
 }
 
 
+/// Only needed when Fathom (tbprobe.h) changes, the bindings (and the wrappers in extern.c) are checked in
+#[cfg(feature = "bindgen")]
 fn generate_bindings() {
     let bindings = bindgen::Builder::default()
         .wrap_static_fns(true)
+        .wrap_static_fns_path("./src/syzygy/extern")
         .header("./imports/fathom/src/tbprobe.h")
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .layout_tests(false)
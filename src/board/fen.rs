@@ -119,6 +119,7 @@ impl TryFrom<&str> for Board {
         board.set_castling(rights);
         let zobrist_key = board.hash_key();
         board.set_zobrist(zobrist_key);
+        board.pawn_key = board.pawn_key();
 
         
         Ok(board)
@@ -126,6 +127,47 @@ impl TryFrom<&str> for Board {
 }
 
 
+impl Board {
+    /// The halfmove clock is the total of both sides' fifty move counters,
+    /// and the fullmove number is derived from the plies played on this board
+    pub(crate) fn to_fen(self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                // piece_at is not used here, because it reports the enpassant victim on the enpassant square
+                let sq = rank * 8 + file;
+                match (0..12).find(|&p| self.board[p].get_bit(sq) != 0) {
+                    Some(piece) => {
+                        let piece = Piece::from(piece as u8);
+                        if empty != 0 { fen.push_str(&empty.to_string()); empty = 0; }
+                        fen.push_str(&piece.to_string());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty != 0 { fen.push_str(&empty.to_string()); }
+            if rank != 0 { fen.push('/'); }
+        }
+
+        fen.push_str(if self.turn == Color::White { " w " } else { " b " });
+
+        let rights = [(Castling::WHITE_KING, 'K'), (Castling::WHITE_QUEEN, 'Q'), (Castling::BLACK_KING, 'k'), (Castling::BLACK_QUEEN, 'q')];
+        let castling = rights.iter().filter(|(right, _)| self.castling_rights.contains(*right)).map(|(_, c)| *c).collect::<String>();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        match self.enpassant {
+            Some(sq) => fen.push_str(&format!(" {sq}")),
+            None => fen.push_str(" -"),
+        }
+
+        let halfmove = self.fifty.iter().map(|&f| f as usize).sum::<usize>();
+        fen.push_str(&format!(" {halfmove} {}", (self.ply / 2) + 1));
+        fen
+    }
+}
+
 
 
 #[cfg(test)]
@@ -202,4 +244,18 @@ mod fen_tests {
         assert_eq!(board.hash_key, 0xE0AC430339C6FB3E);
     }
 
+    #[test]
+    fn should_convert_a_board_back_to_fen() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqk1nr/p2p3p/4p3/8/Pp1P1B2/6Pp/1PP2P1P/R2QKB1R b Kq a3 0 1",
+            "8/8/4k3/8/8/8/4K3/8 b - - 0 1",
+        ];
+
+        for fen in fens {
+            assert_eq!(Board::try_from(fen).unwrap().to_fen(), fen);
+        }
+    }
+
 }
\ No newline at end of file
@@ -5,9 +5,15 @@ use history::History;
 use crate::bitboard::Bitboard;
 use crate::move_logic::bitmove::MoveType;
 use crate::constants::PIECE_ATTACKS;
+use crate::endgame;
+use crate::constants::CUCKOO;
+use crate::cuckoo::Cuckoo;
+use crate::search::evaluation::Evaluation;
 use crate::nnue::accumulator::Feature;
 use crate::{move_logic::bitmove::Move, move_scope::MoveScope, squares::Square};
 use crate::nnue::network::NNUEState;
+use crate::nnue::dirty_pieces::DirtyPieces;
+use crate::nnue::EMBEDDED_NET;
 use crate::color::Color::{self, *};
 use crate::nnue::constants::custom_kp::*;
 use super::{piece::{Piece, Piece::*}, state::board::Board};
@@ -28,6 +34,8 @@ pub(crate) struct Position {
     pub(crate) board: Board,
     nnue_state: NNUEState<Feature, ACCUMULATOR_SIZE>,
     history: Vec<Option<History>>,
+    /// Evaluates with the handcrafted evaluation when false (the UseNNUE option, always false without the `nnue` feature)
+    pub(crate) use_nnue: bool,
 }
 
 
@@ -35,7 +43,7 @@ impl Position {
     pub(crate) fn new() -> Self {
         let board = Board::new();
         let nnue_state = NNUEState::from(&board);
-        Self { history: Vec::new(), board, nnue_state }
+        Self { history: Vec::new(), board, nnue_state, use_nnue: EMBEDDED_NET }
     }
 
     /// NOT YET IMPLEMENTED, PLEASE IMPLEMENT ME!!!
@@ -70,9 +78,15 @@ impl Position {
         self.history.pop(); 
         self.nnue_state.pop(); }
 
+    /// Recomputes the accumulator from the current board, and empties the accumulator stack.
+    /// The stack can only hold MAX_DEPTH accumulators, so this must be called between the moves of a long game (e.g. self-play)
+    pub(crate) fn refresh_nnue(&mut self) {
+        self.nnue_state.refresh(&self.board);
+    }
+
     pub(crate) fn with(board: Board) -> Self {
         let nnue_state = NNUEState::from(&board);
-        Self { board, nnue_state, history: Vec::new() }
+        Self { board, nnue_state, history: Vec::new(), use_nnue: EMBEDDED_NET }
     }
 
     pub(crate) fn set_enpassant(&mut self, enpass: Option<Square>) {
@@ -96,11 +110,11 @@ impl Position {
         // Castling cannot have a bad SEE, since all squares the king passes through are not under attack
         if mt == MoveType::Castling { return true }
 
-        // Ony captures are evaluated with SEE
-        let piece_at_tgt = self.piece_at(tgt).unwrap();
+        // Only captures win material up front (the pawn captured en-passant is not on the target square)
+        let victim = if mv.get_enpassant() { Some(Piece::pawn(!self.turn)) } else { self.piece_at(tgt) };
         let piece_at_src = self.piece_at(src).unwrap();
 
-        let mut move_value = if mv.get_capture() { piece_at_tgt.piece_value() } else { 0};
+        let mut move_value = victim.map_or(0, |piece| piece.piece_value());
 
         // Piece being removed later on is the promoted piece
         let next_victim = if let Some(piece) = mv.get_promotion() {Piece::from((piece, self.turn))} else { self.piece_at(src).unwrap()};
@@ -117,7 +131,9 @@ impl Position {
         let mut see_board = self.board.clone();
         // Update the positions on the board: 1. Remove the moved piece, and place it at the target, 2. Remove the captured piece
         see_board.remove_piece(piece_at_src, src);
-        see_board.remove_piece(piece_at_tgt, if mv.get_enpassant() {Board::enpass_tgt(tgt, see_board.turn).into()} else {tgt});
+        if let Some(victim) = victim {
+            see_board.remove_piece(victim, if mv.get_enpassant() {Board::enpass_tgt(tgt, see_board.turn).into()} else {tgt});
+        }
         // Add the moved piece to the new position
         see_board.add_piece(next_victim, tgt);
         
@@ -157,7 +173,7 @@ impl Position {
             if balance >= 0 {
                 // If the recapturing piece is a king, and the opponent has another attacker,
                 // a positrive balance should not translate to an exchange win.
-                if attacker == Piece::king(!stm) && ((attackers & *see_board[stm]) != 0) {
+                if attacker == Piece::king(!stm) && ((attackers & see_board.occupancies[stm]) != 0) {
                     return see_board.turn == stm
                 }
                 break;
@@ -173,7 +189,7 @@ impl Position {
         let all = self.occupancies[Color::Black] | self.occupancies[Color::White];
         if all.count_ones() == 2 { return true }
 
-        let knights = *self.board[Piece::WK] | *self.board[Piece::BK];
+        let knights = *self.board[Piece::WN] | *self.board[Piece::BN];
         let bishops = *self.board[Piece::WB] | *self.board[Piece::BB];
 
         let white_sqs = Bitboard::WHITE_SQUARES.count_ones();
@@ -200,17 +216,41 @@ impl Position {
         }
     }
 
+    /// The number of plies (at the end of the history) that can be undone by a reversible move: the halfmove clock,
+    /// cut short by a null move (positions before a null move can never be reached again with legal moves)
+    fn reversible_plies(&self) -> usize {
+        let halfmove = self.fifty.iter().map(|&f| f as usize).sum::<usize>();
+        self.history.iter().rev().take(halfmove).take_while(|history| history.is_some()).count()
+    }
+
+    /// If the position with this key has already happened since the last irreversible move
     pub(crate) fn is_repetition(&self, key: u64) -> bool {
         let len = self.history_len();
-        if len == 0 { return false }
 
-        // subtracting 1 from len because we don't care about the opponent's (the person who played last's) game
-        // stepping by 2 because we don't care about the opponent's key positional history in this case
-        for index in (0..len-1).rev().step_by(2) {
-            if let Some(history) = self.history_at(index) { 
-                if history.board().hash_key == key { return true }
-             } else { continue }
-        } 
+        // Only the positions with the same side to move, and at least 4 plies ago (a position can't be repeated with a move from each side)
+        (4..=self.reversible_plies()).step_by(2)
+            .any(|plies| self.history[len - plies].as_ref().is_some_and(|history| history.board.hash_key == key))
+    }
+
+    /// Upcoming repetition: if the side to move can repeat a position from the search (i.e. after the root, `ply` plies ago at most)
+    /// with a reversible move, so that it can at least draw.
+    /// The key of the current position differs from the key of a position i (odd) plies ago by the key of a single reversible move,
+    /// which the cuckoo tables find in O(1). The move must also not be blocked on the board.
+    /// https://marcelk.net/2013-04-06/paper/upcoming-rep-v2.pdf
+    pub(crate) fn has_game_cycle(&self, ply: usize) -> bool {
+        let len = self.history_len();
+        let end = self.reversible_plies();
+        let occupancy = self.occupancies[Color::Both];
+
+        for plies in (3..=end).step_by(2) {
+            let Some(history) = &self.history[len - plies] else { return false };
+            let Some(mv) = CUCKOO.find(self.hash_key ^ history.board.hash_key) else { continue };
+
+            // Repetitions of positions before the root would need one more repetition to be draws, those are left to is_draw
+            if ply > plies && Cuckoo::attacks(mv.piece, mv.src, occupancy) & (1 << mv.tgt) != 0 {
+                return true;
+            }
+        }
 
         false
     }
@@ -241,34 +281,34 @@ impl Position {
             let old_board = std::mem::replace(&mut self.board, new_board);
             self.history.push(Some(History::new(old_board, mv)));
 
-            let mut remove = vec![]; let mut add = vec![];
+            let mut dirty = DirtyPieces::default();
             
             if mv.get_enpassant() {
                 // let enpass_target = match board.turn {Color::Black => to as u64 + 8, _ => to as u64 -  8};
                 let enpass_tgt = Square::from(match turn {White => tgt as u64 -  8, _ => tgt as u64 + 8});
                 // self.nnue_state.manual_update::<OFF>(Piece::pawn(!turn), enpass_tgt);
-                remove.push((Piece::pawn(!turn), enpass_tgt));
+                dirty.remove(Piece::pawn(!turn), enpass_tgt);
             } else if mv.get_capture() {
                 // self.nnue_state.manual_update::<OFF>(victim.unwrap(), tgt_sq);
-                remove.push((victim.unwrap(), tgt_sq));
+                dirty.remove(victim.unwrap(), tgt_sq);
             } else if mv.get_castling() {
                 let (rook_src, rook_tgt) = rook_mvs.unwrap();
                 let rook = Piece::rook(turn);
                 // self.nnue_state.move_update(Piece::rook(turn), rook_src, rook_tgt);
-                remove.push((rook, rook_src));
-                add.push((rook, rook_tgt));
+                dirty.remove(rook, rook_src);
+                dirty.add(rook, rook_tgt);
             }
             
             if let Some(promoted) =  mv.get_promotion() {
-                remove.push((piece, src));
-                add.push((Piece::from((promoted, turn)), tgt));
+                dirty.remove(piece, src);
+                dirty.add(Piece::from((promoted, turn)), tgt);
             } else {
-                remove.push((piece, src));
-                add.push((piece, tgt));
+                dirty.remove(piece, src);
+                dirty.add(piece, tgt);
             }
             
             
-            self.nnue_state.update(remove, add);
+            self.nnue_state.update(dirty);
             
             return true;
         }
@@ -289,8 +329,12 @@ impl Position {
         }
     }
 
-    pub(crate) fn evaluate(&self) -> i32 {
-        let eval = self.nnue_state.evaluate(self.board.turn);
+    pub(crate) fn evaluate(&mut self) -> i32 {
+        if !self.use_nnue {
+            return Evaluation::evaluate(&self.board);
+        }
+
+        let eval = self.nnue_state.evaluate(&self.board);
   
         let total_material = 
             (self.board[WN].count_ones() + self.board[BN].count_ones()) as i32 * Piece::PIECE_VALUES[WN] +
@@ -298,7 +342,7 @@ impl Position {
             (self.board[WR].count_ones() + self.board[BR].count_ones()) as i32 * Piece::PIECE_VALUES[WR] + 
             (self.board[WQ].count_ones() + self.board[BQ].count_ones()) as i32 * Piece::PIECE_VALUES[WQ];
 
-        (eval * (700 + total_material / 32)) / 1024
+        endgame::evaluate(&self.board, (eval * (700 + total_material / 32)) / 1024)
     }
 }
 
@@ -314,6 +358,6 @@ impl Deref for Position {
 impl From<Board> for Position {
     fn from(board: Board) -> Self {
         let nnue_state = NNUEState::from(&board);
-        Self { board, nnue_state, history: Vec::new() }
+        Self { board, nnue_state, history: Vec::new(), use_nnue: EMBEDDED_NET }
     }
 }
\ No newline at end of file
@@ -189,3 +189,172 @@ mod do_and_undo_a_move {
     }
 }
 
+
+
+#[cfg(test)]
+mod lazy_nnue_updates {
+    use crate::board::position::Position;
+    use crate::board::state::board::Board;
+    use crate::constants::TRICKY_POSITION;
+    use crate::move_logic::bitmove::{Move, MoveType::*};
+    use crate::move_scope::MoveScope::*;
+    use crate::squares::Square::*;
+
+    #[test]
+    fn lazily_updated_evaluation_should_match_a_fresh_position() {
+        let mut position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+        let mvs = [
+            Move::new(E1 as u8, G1 as u8, Castling),
+            Move::new(B4 as u8, C3 as u8, Capture),
+            Move::new(D2 as u8, C3 as u8, Capture),
+            Move::new(E8 as u8, C8 as u8, Castling),
+        ];
+
+        for mv in mvs {
+            assert!(position.make_move(mv, AllMoves));
+        }
+
+        let lazy_eval = position.evaluate();
+        let fresh_eval = Position::with(position.board).evaluate();
+        assert_eq!(lazy_eval, fresh_eval);
+    }
+
+    #[test]
+    fn should_restore_the_evaluation_after_undoing_moves() {
+        let mut position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+        let eval_before = position.evaluate();
+
+        assert!(position.make_move(Move::new(E2 as u8, A6 as u8, Capture), AllMoves));
+        position.nnue_push();
+        position.evaluate();
+        position.nnue_pop();
+        assert!(position.make_move(Move::new(B6 as u8, C4 as u8, Quiet), AllMoves));
+        position.evaluate();
+
+        position.undo_move(true);
+        position.undo_move(true);
+        assert_eq!(position.evaluate(), eval_before);
+    }
+}
+
+
+#[cfg(test)]
+mod static_exchange_evaluation {
+    use crate::board::{position::Position, state::board::Board};
+    use crate::constants::START_POSITION;
+    use crate::move_logic::{bitmove::Move, move_stack::MoveStack};
+    use crate::move_scope::MoveScope;
+    use crate::squares::Square::{self, *};
+
+    /// The pseudo-legal move from src to tgt (queen promotions only)
+    fn find(position: &Position, src: Square, tgt: Square) -> Move {
+        let mut mvs = MoveStack::<Move>::new();
+        position.gen_movement::<{ MoveScope::ALL }, Move>(&mut mvs);
+        (0..mvs.count_mvs()).map(|i| *mvs.at(i).unwrap())
+            .find(|mv| mv.get_src() == src && mv.get_target() == tgt && mv.get_promotion().is_none_or(|p| p as u8 % 6 == 4))
+            .unwrap_or_else(|| panic!("{src} -> {tgt} is not a move"))
+    }
+
+    /// see(mv, threshold) is true if the exchange gains at least `threshold`, so the exact gain is the largest threshold that passes
+    fn assert_see(fen: &str, src: Square, tgt: Square, expected: i32) {
+        let position = Position::with(Board::try_from(fen).unwrap());
+        let mv = find(&position, src, tgt);
+
+        assert!(position.see(&mv, expected), "{fen}: {src}{tgt} should gain at least {expected}");
+        assert!(!position.see(&mv, expected + 1), "{fen}: {src}{tgt} should gain at most {expected}");
+    }
+
+    // piece values: P=161, N=446, B=464, R=705, Q=1322
+
+    #[test]
+    fn undefended_and_defended_captures() {
+        assert_see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", E1, E5, 161);
+        assert_see("4k3/8/2p5/3q4/8/8/3R4/4K3 w - - 0 1", D2, D5, 1322 - 705);
+        assert_see("4k3/3p4/4p3/8/8/8/8/3QK3 w - - 0 1", D1, D7, 161 - 1322);
+    }
+
+    #[test]
+    fn exchanges_with_x_rays() {
+        // NxP NxN RxN BxR QxB QxQ: black stops after winning the knight
+        assert_see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", D3, E5, 161 - 446);
+        assert_see("4R3/2r3p1/5bk1/1p1r3p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1", H5, G4, 0);
+        assert_see("4R3/2r3p1/5bk1/1p1r1p1p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1", H5, G4, 0);
+        assert_see("8/4kp2/2npp3/1Nn5/1p2P1P1/7q/1PP1B3/4KR1r b - - 0 1", H1, F1, 0);
+    }
+
+    #[test]
+    fn the_king_cannot_recapture_a_defended_piece() {
+        assert_see("4k3/3p4/8/8/8/8/3R4/4K3 w - - 0 1", D2, D7, 161 - 705);
+        // the rook on d1 x-rays through d2
+        assert_see("4k3/3p4/8/8/8/8/3R4/3RK3 w - - 0 1", D2, D7, 161);
+    }
+
+    #[test]
+    fn quiet_moves_and_en_passant_should_not_panic() {
+        assert_see(START_POSITION, E2, E4, 0);
+        // the knight is left hanging to the c3 pawn
+        assert_see("4k3/8/8/8/8/2p5/8/1N5K w - - 0 1", B1, D2, -446);
+        assert_see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", E5, D6, 161);
+    }
+
+    #[test]
+    fn promotions_should_count_the_promoted_piece() {
+        // the new queen is lost to the king
+        assert_see("2k5/1P6/8/8/8/8/8/4K3 w - - 0 1", B7, B8, -161);
+        assert_see("8/1P1k4/8/8/8/8/8/4K3 w - - 0 1", B7, B8, 1322 - 161);
+    }
+}
+
+
+#[cfg(test)]
+mod repetitions {
+    use crate::board::{position::Position, state::board::Board};
+    use crate::constants::START_POSITION;
+    use crate::move_logic::bitmove::{Move, MoveType::Quiet};
+    use crate::move_scope::MoveScope::AllMoves;
+    use crate::squares::Square::{self, *};
+
+    fn play(fen: &str, mvs: &[(Square, Square)]) -> Position {
+        let mut position = Position::with(Board::try_from(fen).unwrap());
+        for &(src, tgt) in mvs {
+            assert!(position.make_move(Move::new(src as u8, tgt as u8, Quiet), AllMoves));
+        }
+        position
+    }
+
+    #[test]
+    fn the_side_to_move_should_see_that_it_can_repeat_a_position() {
+        // 1. Nf3 Nf6 2. Ng1: Ng8 repeats the start position
+        let position = play(START_POSITION, &[(G1, F3), (G8, F6), (F3, G1)]);
+        assert!(position.has_game_cycle(4));
+        // the repeated position is before the root
+        assert!(!position.has_game_cycle(3));
+
+        // 1. Nf3 Nf6 2. Nc3: no single move goes back to an earlier position
+        let position = play(START_POSITION, &[(G1, F3), (G8, F6), (F3, C3)]);
+        assert!(!position.has_game_cycle(4));
+    }
+
+    #[test]
+    fn blocked_moves_should_not_repeat_a_position() {
+        // 1... Kg8 2. Rb1 Kf8 3. Rb5 Kg8 4. Ra5 Kh8: only Ra5-a1 goes back to the start
+        let mvs = [(H8, G8), (A1, B1), (G8, F8), (B1, B5), (F8, G8), (B5, A5), (G8, H8)];
+
+        assert!(play("7k/8/8/8/8/8/8/R6K b - - 0 1", &mvs).has_game_cycle(8));
+        // the knight on a3 is in the rook's way
+        assert!(!play("7k/8/8/8/8/N7/8/R6K b - - 0 1", &mvs).has_game_cycle(8));
+    }
+
+    #[test]
+    fn repetitions_should_be_limited_to_the_halfmove_clock() {
+        // 1. Nf3 Nf6 2. Ng1 Ng8
+        let mut position = play(START_POSITION, &[(G1, F3), (G8, F6), (F3, G1), (F6, G8)]);
+        assert!(position.is_repetition(position.hash_key));
+        assert!(position.is_draw());
+
+        // as if the last 4 moves could not be undone
+        position.board.fifty = [0, 0];
+        assert!(!position.is_repetition(position.hash_key));
+        assert!(!position.is_draw());
+    }
+}
@@ -43,6 +43,8 @@ pub struct Board {
     pub(crate) occupancies: [u64; OCCUPANCIES], // 0-white, 1-black, 2-both
     // castling_table: [u8; TOTAL_SQUARES],
     pub(crate) hash_key: u64,
+    /// Zobrist key of the pawns only (both colours), it is updated alongside hash_key
+    pub(crate) pawn_key: u64,
     // fifty move rule counter
     pub(crate) fifty: [u8; 2],
     pub(crate) ply: usize,
@@ -57,6 +59,7 @@ impl Board {
             castling_rights: Castling::all(),
             occupancies: [0; OCCUPANCIES],
             hash_key: START_POSITION_ZOBRIST,
+            pawn_key: 0,
             fifty: [0, 0],
             ply: 0,
             // prev: None, //  castling_table: CASTLING_TABLE,
@@ -676,6 +679,9 @@ impl Board {
                 board[piece].set_bit(to.into());
                 board.hash_key ^= ZOBRIST.piece_keys[piece][from];
                 board.hash_key ^= ZOBRIST.piece_keys[piece][to];
+                if piece == Piece::pawn(turn) {
+                    board.pawn_key ^= ZOBRIST.piece_keys[piece][from] ^ ZOBRIST.piece_keys[piece][to];
+                }
 
                 if Piece::WP == piece || Piece::BP == piece || bit_move.get_capture() {
                     board.fifty = [0, 0];
@@ -690,6 +696,7 @@ impl Board {
                         if board[p].get_bit(to.into()) != 0 {
                             board[p].pop_bit(to.into());
                             board.hash_key ^= ZOBRIST.piece_keys[p][to];
+                            if p == Piece::pawn(!turn) { board.pawn_key ^= ZOBRIST.piece_keys[p][to]; }
                             // found = true;
                             break;
                         }
@@ -705,6 +712,7 @@ impl Board {
                     let promoted_to = Piece::from((promoted_to, turn));
                     board[piece].pop_bit(to.into());
                     board.hash_key ^= ZOBRIST.piece_keys[piece][to];
+                    board.pawn_key ^= ZOBRIST.piece_keys[piece][to];
                     board[promoted_to].set_bit(to.into());
                     board.hash_key ^= ZOBRIST.piece_keys[promoted_to][to];
                 }
@@ -715,6 +723,7 @@ impl Board {
                     board[Piece::pawn(!turn)].pop_bit(enpass_target);
                     board.hash_key ^=
                         ZOBRIST.piece_keys[Piece::pawn(!turn)][enpass_target as usize];
+                    board.pawn_key ^= ZOBRIST.piece_keys[Piece::pawn(!turn)][enpass_target as usize];
                 }
 
                 if let Some(enpass) = board.enpassant {
@@ -872,6 +881,21 @@ impl Board {
         self.hash_key = key;
     }
 
+    /// Generates the zobrist hash of the pawns (both colours) on this board
+    pub(crate) fn pawn_key(&self) -> u64 {
+        let mut key = 0u64;
+
+        for piece in [Piece::WP, Piece::BP] {
+            let mut bitboard = *self[piece];
+            while bitboard != 0 {
+                key ^= ZOBRIST.piece_keys[piece][bitboard.trailing_zeros() as usize];
+                bitboard &= bitboard - 1;
+            }
+        }
+
+        key
+    }
+
     /// Generates the zobrist hash for this board
     pub(crate) fn hash_key(&self) -> u64 {
         let mut final_key = 0u64;
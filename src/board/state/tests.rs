@@ -1138,4 +1138,25 @@ mod board_tests {
         assert_eq!(board.get_piece_at(C1, White).unwrap(), WR);
     }
 
+
+    #[test]
+    fn pawn_key_should_be_updated_incrementally() {
+        let fens = [
+            crate::constants::TRICKY_POSITION,
+            // en-passant on d6, and captures/promotions on the 8th rank
+            "r3k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::try_from(fen).unwrap();
+            assert_eq!(board.pawn_key, board.pawn_key());
+
+            let mut mvs = MoveStack::<Move>::new();
+            board.gen_movement::<{ MoveScope::ALL }, Move>(&mut mvs);
+            for i in 0..mvs.count_mvs() {
+                let Some(result) = board.make_move(*mvs.at(i).unwrap(), AllMoves) else { continue };
+                assert_eq!(result.pawn_key, result.pawn_key(), "{fen}: {}", mvs.at(i).unwrap());
+            }
+        }
+    }
 }
\ No newline at end of file
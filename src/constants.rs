@@ -1,7 +1,7 @@
 use lazy_static::lazy_static;
 use params::MAX_DEPTH;
 
-use crate::{masks::EvaluationMasks, piece_attacks::PieceAttacks, shift::ShiftData, squares::Square, utils::lmr::{FutilityMoveCounts, LmrTable}, zobrist::Zobrist};
+use crate::{cuckoo::Cuckoo, masks::EvaluationMasks, piece_attacks::PieceAttacks, shift::ShiftData, squares::Square, utils::lmr::{FutilityMoveCounts, LmrTable}, zobrist::Zobrist};
 
 ///  ----NOT_A_FILE----
 /// 8   0  1  1  1  1  1  1  1 \
@@ -124,11 +124,27 @@ pub(crate) const MATE_VALUE: i32 = 32000;
 pub const MATE_IN_MAX_PLY: i32 = MATE_VALUE - 2 * 128;
 pub const MATED_IN_MAX_PLY: i32 = -MATE_VALUE + 2 * 128;
 pub const LONGEST_TB_MATE: i32 = MATE_VALUE - 127;
+/// Tablebase wins are scored just below the longest mate, so that a TB win is never mistaken for (or preferred over) an actual mate.
+/// A TB win at ply p is TB_WIN - p, which is always within [TB_WIN_IN_MAX_PLY, TB_WIN]
+pub const TB_WIN: i32 = LONGEST_TB_MATE - 1;
+pub const TB_WIN_IN_MAX_PLY: i32 = TB_WIN - MAX_DEPTH as i32;
+/// Score of a won endgame that is known without searching (e.g. KBNK, or a won KPK), above any material balance and below the TB wins
+pub const KNOWN_WIN: i32 = 10_000;
 pub const NONE: i32 = 32002;
 /// Defines a margin to decide how "bad" a position must be to be considered for razoring. The margin can depend on the search depth and should be empirically tuned.
 /// For instance, at depth 1, the margin might be a small value (like half a pawn), whereas at depth 2, you might use a larger margin.
 /// Should still be further tuned
 pub(crate) const RAZOR_MARGIN: [i32; 3] = [0, 293, 512];
+/// SEE pruning (negamax): quiet moves and captures that lose too much material in the exchange are skipped up to this depth.
+/// The margins are tunable (search::tunables)
+pub(crate) const SEE_PRUNING_DEPTH: u8 = 8;
+/// ProbCut (`probcut` feature): captures are searched at depth - PROBCUT_REDUCTION against beta plus a (tunable) margin, from PROBCUT_DEPTH onwards
+pub(crate) const PROBCUT_DEPTH: u8 = 5;
+pub(crate) const PROBCUT_REDUCTION: u8 = 4;
+/// Internal iterative reduction (`iir` feature): nodes without a TT move are searched 1 ply shallower, from IIR_DEPTH onwards
+pub(crate) const IIR_DEPTH: u8 = 4;
+/// Quiet checks are generated in the first QS_CHECK_PLIES plies of the quiescence search (0 disables them)
+pub(crate) const QS_CHECK_PLIES: u8 = 1;
 
 
 pub const SE_LOWER_LIMIT: u8 = 8;
@@ -232,6 +248,8 @@ lazy_static! {
     pub static ref PIECE_ATTACKS: PieceAttacks = PieceAttacks::new();
     #[derive(Debug)]
     pub static ref ZOBRIST: Zobrist = Zobrist::init_zobrist();
+    /// Zobrist keys of the reversible moves (upcoming repetition detection)
+    pub(crate) static ref CUCKOO: Cuckoo = Cuckoo::init();
     // evaluation masks
     pub static ref EVAL_MASKS: EvaluationMasks = EvaluationMasks::init();
 
@@ -242,32 +260,7 @@ lazy_static! {
 }
 
 
-/// double pawns penalty (from CMK (BBC engine))
-pub(crate) const DOUBLE_PAWN_PENALTY_OPENING: i32 = -5;
-pub(crate) const DOUBLE_PAWN_PENALTY_ENDGAME: i32 = -10;
-/// isolated pawns penalty
-pub(crate) const ISOLATED_PAWN_PENALTY_OPENING: i32 = -5;
-pub(crate) const ISOLATED_PAWN_PENALTY_ENDGAME: i32 = -10;
-/// passed pawn bonus 
-pub(crate) const PASSED_PAWN_BONUS: [u8; 8] = [0, 5, 10, 20, 35, 60, 100, 200];
-/// Semi-open-file: a file on which we do not have a pawn, but the opponent has at least one. It can be used to increase the vertical mobility of the major pieces, to attack weak pawns and to pressure enemy position or to create an outpost.
-pub(crate) const SEMI_OPEN_FILE_SCORE: i32 = 10;
-/// An Open File is a vertical column with no pawns of either color on it
-pub(crate) const OPEN_FILE_SCORE: i32 = 15;
-pub(crate) const KING_SHIELD_BONUS: i32 = 5;
-
-
-// mobility units (values from fruit reloaded)
-pub(crate) const BISHOP_UNIT: i32 = 4;
-pub(crate) const QUEEN_UNIT: i32 = 9;
-
-// mobility bonuses (values from engine Fruit reloaded)
-pub(crate) const BISHOP_MOBILITY_OPENING: i32 = 5;
-pub(crate) const BISHOP_MOBILITY_ENDGAME: i32 = 5;
-pub(crate) const QUEEN_MOBILITY_OPENING: i32 = 1;
-pub(crate) const QUEEN_MOBILITY_ENDGAME: i32 = 2;
-
-
+/// Material values of the game phase (the evaluation's own material weights are in search::hce_weights)
 pub(crate) const MATERIAL_SCORE: [[i32; 12]; 2] = [
     [82, 337, 365, 477, 1025,  12000, -82, -337, -365, -477, -1025,  -12000], // opening material game score
     [94, 281, 297, 512,  936,  12000, -94, -281, -297, -512,  -936,  -12000], // end-game material game scoresss
@@ -279,142 +272,10 @@ pub(crate) const OPENING_PHASE_SCORE: i32 = 6192; // mg
 /// Scores lower than this score results in the game's end
 pub(crate) const END_PHASE_SCORE: i32 = 518;      // eg
 
-// opening positional pieces scores
-pub(crate) const POSITIONAL_SCORES: [[[i32; 64]; 6]; 2] = [
-    // [0.OPENING_POSITIONAL_SCORES 1. END_GAME_POSITIONAL_SCORES
-    //  [PIECES: PAWN, KNIGHT, BISHOP, ROOK, QUEEN, KING
-    //      [SQUARES]
-    //  ]
-    // ]
-    // 
-    [
-        [   // PAWN TABLE
-              0,   0,   0,   0,   0,   0,  0,   0,
-             98, 134,  61,  95,  68, 126, 34, -11,
-             -6,   7,  26,  31,  65,  56, 25, -20,
-            -14,  13,   6,  21,  23,  12, 17, -23,
-            -27,  -2,  -5,  12,  17,   6, 10, -25,
-            -26,  -4,  -4, -10,   3,   3, 33, -12,
-            -35,  -1, -20, -23, -15,  24, 38, -22,
-              0,   0,   0,   0,   0,   0,  0,   0,
-        ],
-        [   // KNIGHT TABLE
-           -167, -89, -34, -49,  61, -97, -15, -107,
-            -73, -41,  72,  36,  23,  62,   7,  -17,
-            -47,  60,  37,  65,  84, 129,  73,   44,
-             -9,  17,  19,  53,  37,  69,  18,   22,
-            -13,   4,  16,  13,  28,  19,  21,   -8,
-            -23,  -9,  12,  10,  19,  17,  25,  -16,
-            -29, -53, -12,  -3,  -1,  18, -14,  -19,
-           -105, -21, -58, -33, -17, -28, -19,  -23,
-        ],
-        [   // BISHOP TABLE
-            -29,   4, -82, -37, -25, -42,   7,  -8,
-            -26,  16, -18, -13,  30,  59,  18, -47,
-            -16,  37,  43,  40,  35,  50,  37,  -2,
-             -4,   5,  19,  50,  37,  37,   7,  -2,
-             -6,  13,  13,  26,  34,  12,  10,   4,
-              0,  15,  15,  15,  14,  27,  18,  10,
-              4,  15,  16,   0,   7,  21,  33,   1,
-            -33,  -3, -14, -21, -13, -12, -39, -21,
-        ],
-        [   // ROOK TABLE
-            32,  42,  32,  51, 63,  9,  31,  43,
-            27,  32,  58,  62, 80, 67,  26,  44,
-            -5,  19,  26,  36, 17, 45,  61,  16,
-            -24, -11,   7,  26, 24, 35,  -8, -20,
-            -36, -26, -12,  -1,  9, -7,   6, -23,
-            -45, -25, -16, -17,  3,  0,  -5, -33,
-            -44, -16, -20,  -9, -1, 11,  -6, -71,
-            -19, -13,   1,  17, 16,  7, -37, -26,
-        ],
-        [   // QUEEN TABLE
-            -28,   0,  29,  12,  59,  44,  43,  45,
-            -24, -39,  -5,   1, -16,  57,  28,  54,
-            -13, -17,   7,   8,  29,  56,  47,  57,
-            -27, -27, -16, -16,  -1,  17,  -2,   1,
-             -9, -26,  -9, -10,  -2,  -4,   3,  -3,
-            -14,   2, -11,  -2,  -5,   2,  14,   5,
-            -35,  -8,  11,   2,   8,  15,  -3,   1,
-             -1, -18,  -9,  10, -15, -25, -31, -50,
-        ],
-        [   // KING TABLE
-            -65,  23,  16, -15, -56, -34,   2,  13,
-             29,  -1, -20,  -7,  -8,  -4, -38, -29,
-             -9,  24,   2, -16, -20,   6,  22, -22,
-            -17, -20, -12, -27, -30, -25, -14, -36,
-            -49,  -1, -27, -39, -46, -44, -33, -51,
-            -14, -14, -22, -46, -44, -30, -15, -27,
-              1,   7,  -8, -64, -43, -16,   9,   8,
-            -15,  36,  12, -54,   8, -28,  24,  14,
-        ]
-    ],
-    [
-    [   // PAWN SCORES
-        0,   0,   0,   0,   0,   0,   0,   0,
-        178, 173, 158, 134, 147, 132, 165, 187,
-        94, 100,  85,  67,  56,  53,  82,  84,
-        32,  24,  13,   5,  -2,   4,  17,  17,
-        13,   9,  -3,  -7,  -7,  -8,   3,  -1,
-        4,   7,  -6,   1,   0,  -5,  -1,  -8,
-        13,   8,   8,  10,  13,   0,   2,  -7,
-        0,   0,   0,   0,   0,   0,   0,   0,
-    ],
-    [   // KNIGHT TABLE
-        -58, -38, -13, -28, -31, -27, -63, -99,
-        -25,  -8, -25,  -2,  -9, -25, -24, -52,
-        -24, -20,  10,   9,  -1,  -9, -19, -41,
-        -17,   3,  22,  22,  22,  11,   8, -18,
-        -18,  -6,  16,  25,  16,  17,   4, -18,
-        -23,  -3,  -1,  15,  10,  -3, -20, -22,
-        -42, -20, -10,  -5,  -2, -20, -23, -44,
-        -29, -51, -23, -15, -22, -18, -50, -64,
-    ],
-    [ // BISHOP TABLE
-        -14, -21, -11,  -8, -7,  -9, -17, -24,
-         -8,  -4,   7, -12, -3, -13,  -4, -14,
-          2,  -8,   0,  -1, -2,   6,   0,   4,
-         -3,   9,  12,   9, 14,  10,   3,   2,
-         -6,   3,  13,  19,  7,  10,  -3,  -9,
-        -12,  -3,   8,  10, 13,   3,  -7, -15,
-        -14, -18,  -7,  -1,  4,  -9, -15, -27,
-        -23,  -9, -23,  -5, -9, -16,  -5, -17,
-    ],
-    [   // ROOK TABLE
-        13, 10, 18, 15, 12,  12,   8,   5,
-        11, 13, 13, 11, -3,   3,   8,   3,
-         7,  7,  7,  5,  4,  -3,  -5,  -3,
-         4,  3, 13,  1,  2,   1,  -1,   2,
-         3,  5,  8,  4, -5,  -6,  -8, -11,
-        -4,  0, -5, -1, -7, -12,  -8, -16,
-        -6, -6,  0,  2, -9,  -9, -11,  -3,
-        -9,  2,  3, -1, -5, -13,   4, -20,
-    ],
-    [   // QUEEN TABLE
-         -9,  22,  22,  27,  27,  19,  10,  20,
-        -17,  20,  32,  41,  58,  25,  30,   0,
-        -20,   6,   9,  49,  47,  35,  19,   9,
-          3,  22,  24,  45,  57,  40,  57,  36,
-        -18,  28,  19,  47,  31,  34,  39,  23,
-        -16, -27,  15,   6,   9,  17,  10,   5,
-        -22, -23, -30, -16, -16, -23, -36, -32,
-        -33, -28, -22, -43,  -5, -32, -20, -41,
-    ],
-    [   // KING TABLE
-        -74, -35, -18, -18, -11,  15,   4, -17,
-        -12,  17,  14,  17,  17,  38,  23,  11,
-         10,  17,  23,  15,  20,  45,  44,  13,
-         -8,  22,  24,  27,  26,  33,  26,   3,
-        -18,  -4,  21,  24,  27,  23,   9, -11,
-        -19,  -3,  11,  21,  23,  16,   7,  -9,
-        -27, -11,   4,  13,  14,   4,  -5, -17,
-        -53, -34, -21, -11, -28, -14, -24, -43
-    ]
-]
-];
 
 
 
+/// ```text
 ///                             castling    move     in      in
 ///                              right     update  binary  decimal
 /// king & rooks didn't move:    1111   &   1111 =  1111    15
@@ -426,6 +287,7 @@ pub(crate) const POSITIONAL_SCORES: [[[i32; 64]; 6]; 2] = [
 ///         black king moved:    1111   &   0011 =  0011    3
 ///  black king's rook moved:    1111   &   1011 =  1011    11
 /// black queen's rook moved:    1111   &   0111 =  0111    7
+/// ```
 pub(crate) const CASTLING_TABLE: [u8; 64] = [
     13, 15, 15, 15, 12, 15, 15, 14,
     15, 15, 15, 15, 15, 15, 15, 15,
@@ -0,0 +1,109 @@
+use crate::{board::piece::Piece, constants::{PIECE_ATTACKS, TOTAL_SQUARES, ZOBRIST}};
+
+
+/// The number of slots of each cuckoo table (both hash functions index into the same table)
+const SIZE: usize = 8192;
+
+/// A reversible move (a non-pawn piece going from a square to another, in either direction)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CuckooMove {
+    pub(crate) piece: Piece,
+    pub(crate) src: usize,
+    pub(crate) tgt: usize,
+}
+
+/// Cuckoo tables of the zobrist keys of every reversible move (piece on src ^ piece on tgt ^ side), used to detect that the side to move
+/// can repeat an earlier position with its next move (an upcoming repetition), without generating any move.
+/// A move and its reverse have the same key, so only one of them is stored.
+/// https://marcelk.net/2013-04-06/paper/upcoming-rep-v2.pdf
+/// https://github.com/official-stockfish/Stockfish/blob/master/src/position.cpp (Position::init)
+#[derive(Debug)]
+pub(crate) struct Cuckoo {
+    keys: Vec<u64>,
+    moves: Vec<Option<CuckooMove>>,
+}
+
+impl Cuckoo {
+    #[cold]
+    pub(crate) fn init() -> Self {
+        let mut cuckoo = Self { keys: vec![0; SIZE], moves: vec![None; SIZE] };
+
+        for piece in Piece::ascii_pieces().into_iter().filter(|&p| p != Piece::WP && p != Piece::BP) {
+            for src in 0..TOTAL_SQUARES {
+                for tgt in (src + 1)..TOTAL_SQUARES {
+                    if Self::attacks(piece, src, 0) & (1 << tgt) == 0 { continue }
+
+                    let key = ZOBRIST.piece_keys[piece][src] ^ ZOBRIST.piece_keys[piece][tgt] ^ ZOBRIST.side_key;
+                    cuckoo.insert(key, CuckooMove { piece, src, tgt });
+                }
+            }
+        }
+
+        cuckoo
+    }
+
+    #[inline(always)]
+    fn h1(key: u64) -> usize {
+        (key & 0x1FFF) as usize
+    }
+
+    #[inline(always)]
+    fn h2(key: u64) -> usize {
+        ((key >> 16) & 0x1FFF) as usize
+    }
+
+    /// Every entry lives in one of its 2 slots, an insertion evicts the previous occupant to its other slot, until an empty slot is found
+    fn insert(&mut self, mut key: u64, mv: CuckooMove) {
+        let mut mv = Some(mv);
+        let mut i = Self::h1(key);
+
+        loop {
+            std::mem::swap(&mut self.keys[i], &mut key);
+            std::mem::swap(&mut self.moves[i], &mut mv);
+            if mv.is_none() { break }
+            i = if i == Self::h1(key) { Self::h2(key) } else { Self::h1(key) };
+        }
+    }
+
+    /// The reversible move that changes a position's key by `key` (if any)
+    pub(crate) fn find(&self, key: u64) -> Option<CuckooMove> {
+        [Self::h1(key), Self::h2(key)].into_iter().find(|&i| self.keys[i] == key).and_then(|i| self.moves[i])
+    }
+
+    /// The squares attacked by the piece from sq
+    pub(crate) fn attacks(piece: Piece, sq: usize, occupancy: u64) -> u64 {
+        match piece {
+            Piece::WN | Piece::BN => PIECE_ATTACKS.knight_attacks[sq],
+            Piece::WB | Piece::BB => PIECE_ATTACKS.nnbishop_attacks(1 << sq, occupancy),
+            Piece::WR | Piece::BR => PIECE_ATTACKS.nnrook_attacks(1 << sq, occupancy),
+            Piece::WQ | Piece::BQ => PIECE_ATTACKS.nnbishop_attacks(1 << sq, occupancy) | PIECE_ATTACKS.nnrook_attacks(1 << sq, occupancy),
+            Piece::WK | Piece::BK => PIECE_ATTACKS.king_attacks[sq],
+            _ => 0,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod cuckoo_tests {
+    use crate::constants::CUCKOO;
+
+    use super::*;
+
+    #[test]
+    fn should_store_every_reversible_move() {
+        // 2 colours * (knights 168 + bishops 280 + rooks 448 + queens 728 + kings 210)
+        assert_eq!(CUCKOO.moves.iter().filter(|mv| mv.is_some()).count(), 3668);
+    }
+
+    #[test]
+    fn moves_should_be_found_by_their_key() {
+        let (g1, f3) = (6, 21);
+        let key = ZOBRIST.piece_keys[Piece::WN][g1] ^ ZOBRIST.piece_keys[Piece::WN][f3] ^ ZOBRIST.side_key;
+        assert_eq!(CUCKOO.find(key), Some(CuckooMove { piece: Piece::WN, src: g1, tgt: f3 }));
+
+        // not a knight move
+        let key = ZOBRIST.piece_keys[Piece::WN][g1] ^ ZOBRIST.piece_keys[Piece::WN][g1 + 8] ^ ZOBRIST.side_key;
+        assert_eq!(CUCKOO.find(key), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::board::castling::Castling;
+use crate::board::state::board::Board;
+use crate::color::Color;
+use crate::squares::Square::*;
+
+
+/// Game result from white's perspective, as written to the training data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GameResult {
+    BlackWin = 0,
+    Draw = 1,
+    WhiteWin = 2,
+}
+
+impl GameResult {
+    /// The text format uses 0.0, 0.5, and 1.0
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::BlackWin => "0.0",
+            Self::Draw => "0.5",
+            Self::WhiteWin => "1.0",
+        }
+    }
+}
+
+
+/// A 32 bytes marlinformat (bulletformat compatible) `ChessBoard` record
+/// | occupancy: u64 | pieces: [u8; 16] | stm_enpassant: u8 | halfmove: u8 | fullmove: u16 | eval: i16 | wdl: u8 | extra: u8 |
+///
+/// pieces: one nibble per occupied square (in the order of the occupancy bits, low nibble first),
+/// 0..=5 for P, N, B, R, Q, K, 6 for a rook that can still castle, and +8 for black pieces.
+/// stm_enpassant: the enpassant square (64 if none), with the top bit set when black is to move.
+/// eval and wdl are both from white's perspective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PackedBoard {
+    occupancy: u64,
+    pieces: [u8; 16],
+    stm_enpassant: u8,
+    halfmove: u8,
+    fullmove: u16,
+    eval: i16,
+    wdl: u8,
+    extra: u8,
+}
+
+impl PackedBoard {
+    pub(crate) const SIZE: usize = 32;
+    const UNMOVED_ROOK: u8 = 6;
+
+    pub(crate) fn pack(board: &Board, eval: i16, result: GameResult) -> Self {
+        let occupancy = board.occupancies[Color::Both];
+        let mut pieces = [0u8; 16];
+
+        let castling_rooks = [(Castling::WHITE_KING, H1), (Castling::WHITE_QUEEN, A1), (Castling::BLACK_KING, H8), (Castling::BLACK_QUEEN, A8)];
+        let mut occ = occupancy;
+        let mut i = 0;
+
+        while occ != 0 {
+            let sq = occ.trailing_zeros() as u64;
+            let piece = board.piece_at(sq.into()).unwrap();
+
+            let mut code = piece as u8 % 6;
+            if castling_rooks.iter().any(|&(right, rook_sq)| board.castling_rights.contains(right) && rook_sq as u64 == sq) {
+                code = Self::UNMOVED_ROOK;
+            }
+            code |= (piece.color() as u8) << 3;
+
+            pieces[i / 2] |= code << ((i % 2) * 4);
+            occ &= occ - 1;
+            i += 1;
+        }
+
+        let ep = board.enpassant.map(|sq| sq as u8).unwrap_or(64);
+        let stm_enpassant = ep | ((board.turn as u8) << 7);
+        let halfmove = board.fifty.iter().map(|&f| f as u16).sum::<u16>().min(u8::MAX as u16) as u8;
+        let fullmove = (board.ply / 2 + 1) as u16;
+
+        Self { occupancy, pieces, stm_enpassant, halfmove, fullmove, eval, wdl: result as u8, extra: 0 }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.occupancy.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.pieces);
+        bytes[24] = self.stm_enpassant;
+        bytes[25] = self.halfmove;
+        bytes[26..28].copy_from_slice(&self.fullmove.to_le_bytes());
+        bytes[28..30].copy_from_slice(&self.eval.to_le_bytes());
+        bytes[30] = self.wdl;
+        bytes[31] = self.extra;
+        bytes
+    }
+}
+
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+    use crate::constants::START_POSITION;
+
+    #[test]
+    fn should_pack_the_start_position() {
+        let board = Board::try_from(START_POSITION).unwrap();
+        let bytes = PackedBoard::pack(&board, -25, GameResult::Draw).to_bytes();
+
+        assert_eq!(u64::from_le_bytes(bytes[0..8].try_into().unwrap()), 0xFFFF00000000FFFF);
+        // a1: rook with castling rights(6), b1: knight(1), c1: bishop(2), d1: queen(4), e1: king(5), ...
+        assert_eq!(&bytes[8..12], &[0x16, 0x42, 0x25, 0x61]);
+        // white pawns, followed by black pawns
+        assert_eq!(&bytes[12..16], &[0x00; 4]);
+        assert_eq!(&bytes[16..20], &[0x88; 4]);
+        // a8: black rook with castling rights(6 | 8), b8: black knight (1 | 8), ...
+        assert_eq!(&bytes[20..24], &[0x9E, 0xCA, 0xAD, 0xE9]);
+        assert_eq!(bytes[24], 64);
+        assert_eq!(i16::from_le_bytes([bytes[28], bytes[29]]), -25);
+        assert_eq!(bytes[30], 1);
+    }
+
+    #[test]
+    fn should_record_black_to_move_and_the_enpassant_square() {
+        let board = Board::try_from("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b - e3 0 1").unwrap();
+        let packed = PackedBoard::pack(&board, 0, GameResult::WhiteWin).to_bytes();
+
+        assert_eq!(packed[24], (1 << 7) | E3 as u8);
+        // no castling rights, so a1 is just a rook
+        assert_eq!(packed[8] & 0xF, 3);
+        assert_eq!(packed[30], 2);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+use std::thread;
+
+use format::{GameResult, PackedBoard};
+
+use crate::board::{position::Position, state::board::Board};
+use crate::color::Color;
+use crate::constants::{params::MAX_DEPTH, MATE_IN_MAX_PLY, START_POSITION};
+use crate::move_logic::{bitmove::Move, move_stack::MoveStack};
+use crate::move_scope::MoveScope;
+use crate::search::{search::SearchWorker, signals::SearchSignals};
+use crate::syzygy::probe::SyzygyConfig;
+use crate::tt::table::TTable;
+use crate::uci::{clock::Clock, counter::Counter};
+use crate::utils::prng::PRNG;
+
+pub(crate) mod format;
+
+
+/// Self-play training data generator
+/// usage: datagen [threads N] [games N] [nodes N] [plies N] [seed N] [out PATH]
+///
+/// Every worker writes its own `{out}_{id}.bin` (marlinformat records), and `{out}_{id}.txt` (FEN | score | result) files
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataGen {
+    threads: usize,
+    /// The number of games played by each worker
+    games: usize,
+    /// Fixed node limit for every search
+    nodes: u64,
+    /// The number of random plies played from the start position, before recording
+    random_plies: usize,
+    seed: u32,
+    out: String,
+}
+
+impl Default for DataGen {
+    fn default() -> Self {
+        Self { threads: 1, games: 100, nodes: 5000, random_plies: 8, seed: 0x5EED, out: String::from("data") }
+    }
+}
+
+impl DataGen {
+    /// Games are adjudicated as draws after this many plies
+    const MAX_GAME_PLIES: usize = 400;
+
+    pub(crate) fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut datagen = Self::default();
+
+        while let Some(key) = args.next() {
+            let Some(value) = args.next() else { return Err(format!("No value provided for {key}")) };
+            let number = || value.parse::<u64>().map_err(|_| format!("Invalid value for {key}: {value}"));
+
+            match key {
+                "threads" => datagen.threads = number()?.max(1) as usize,
+                "games" => datagen.games = number()? as usize,
+                "nodes" => datagen.nodes = number()?,
+                "plies" => datagen.random_plies = number()? as usize,
+                "seed" => datagen.seed = number()? as u32,
+                "out" => datagen.out = value.to_string(),
+                _ => return Err(format!("Unrecognized datagen option {key}")),
+            }
+        }
+
+        Ok(datagen)
+    }
+
+    pub(crate) fn run(&self) {
+        let positions = AtomicUsize::new(0);
+        let signals = Arc::new(SearchSignals::default());
+
+        thread::scope(|s| {
+            for id in 0..self.threads {
+                let (positions, signals) = (&positions, &signals);
+                s.spawn(move || {
+                    if let Err(e) = self.worker(id, positions, signals) {
+                        eprintln!("datagen worker {id} failed: {e}");
+                    }
+                });
+            }
+        });
+
+        println!("datagen: {} positions written", positions.load(Ordering::Relaxed));
+    }
+
+    fn worker(&self, id: usize, positions: &AtomicUsize, signals: &Arc<SearchSignals>) -> std::io::Result<()> {
+        let mut bin = BufWriter::new(File::create(format!("{}_{id}.bin", self.out))?);
+        let mut txt = BufWriter::new(File::create(format!("{}_{id}.txt", self.out))?);
+        // xorshift must never be seeded with 0
+        let mut prng = PRNG::new((self.seed ^ (id as u32).wrapping_mul(0x9E37_79B9)).max(1));
+
+        for _ in 0..self.games {
+            let (records, result) = self.play_game(&mut prng, signals);
+
+            for (board, score) in &records {
+                bin.write_all(&PackedBoard::pack(board, *score as i16, result).to_bytes())?;
+                writeln!(txt, "{} | {} | {}", board.to_fen(), score, result.as_str())?;
+            }
+            positions.fetch_add(records.len(), Ordering::Relaxed);
+        }
+
+        bin.flush()?;
+        txt.flush()
+    }
+
+    /// Plays a single game, and returns the quiet positions (with their white relative scores), and the game result
+    fn play_game(&self, prng: &mut PRNG, signals: &Arc<SearchSignals>) -> (Vec<(Board, i32)>, GameResult) {
+        let mut position = self.random_opening(prng);
+        let tt = TTable::default();
+        let mut records = Vec::new();
+
+        let mut clock = Clock::new(Arc::clone(signals));
+        clock.set_limit(Counter::Nodes(self.nodes), position.turn);
+        // the same worker plays the whole game, its histories carry over from one move to the next
+        let mut search = SearchWorker::new(tt.get(), clock.clone(), SyzygyConfig::default());
+
+        for _ in 0..Self::MAX_GAME_PLIES {
+            if legal_moves(&position.board).is_empty() {
+                if !position.stm_in_check() { return (records, GameResult::Draw) }
+                return (records, if position.turn == Color::White { GameResult::BlackWin } else { GameResult::WhiteWin });
+            }
+            if position.is_draw() { return (records, GameResult::Draw) }
+
+            search.new_search(tt.get(), clock.clone(), SyzygyConfig::default());
+            search.iterative_deepening_with(MAX_DEPTH, &mut position, &mut |_| {});
+
+            let Some(best_move) = search.best_move() else { return (records, GameResult::Draw) };
+            let score = search.eval();
+            let white_score = if position.turn == Color::White { score } else { -score };
+
+            // The game is decided, mate scores are not useful training targets
+            if score.abs() >= MATE_IN_MAX_PLY {
+                return (records, if white_score > 0 { GameResult::WhiteWin } else { GameResult::BlackWin });
+            }
+
+            // Noisy positions: the static evaluation cannot resolve checks or captures
+            if !position.stm_in_check() && !best_move.is_capture() {
+                records.push((position.board, white_score));
+            }
+
+            if !position.make_move(best_move, MoveScope::AllMoves) { return (records, GameResult::Draw) }
+            position.refresh_nnue();
+        }
+
+        (records, GameResult::Draw)
+    }
+
+    /// Plays `random_plies` random legal moves from the start position, and retries if the game ends on the way
+    fn random_opening(&self, prng: &mut PRNG) -> Position {
+        'retry: loop {
+            let mut position = Position::with(Board::try_from(START_POSITION).unwrap());
+
+            for _ in 0..self.random_plies {
+                let mvs = legal_moves(&position.board);
+                if mvs.is_empty() { continue 'retry; }
+
+                let mv = mvs[(prng.get_random_u64() % mvs.len() as u64) as usize];
+                position.make_move(mv, MoveScope::AllMoves);
+            }
+
+            if legal_moves(&position.board).is_empty() { continue 'retry; }
+            position.refresh_nnue();
+            return position;
+        }
+    }
+}
+
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut mvs = MoveStack::<Move>::new();
+    board.gen_movement::<{ MoveScope::ALL }, Move>(&mut mvs);
+
+    (0..mvs.count_mvs()).map(|i| *mvs.at(i).unwrap()).filter(|&mv| board.make_move(mv, MoveScope::AllMoves).is_some()).collect()
+}
+
+
+#[cfg(test)]
+mod datagen_tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_datagen_options() {
+        let datagen = DataGen::parse("threads 4 games 10 nodes 8000 plies 12 seed 7 out /tmp/data".split_whitespace()).unwrap();
+        assert_eq!(datagen, DataGen { threads: 4, games: 10, nodes: 8000, random_plies: 12, seed: 7, out: String::from("/tmp/data") });
+
+        assert!(DataGen::parse("nodes".split_whitespace()).is_err());
+        assert!(DataGen::parse("depth 5".split_whitespace()).is_err());
+    }
+
+    #[test]
+    fn random_openings_should_be_deterministic_for_a_seed() {
+        let datagen = DataGen::default();
+        let first = datagen.random_opening(&mut PRNG::new(42)).board;
+        let second = datagen.random_opening(&mut PRNG::new(42)).board;
+
+        assert_eq!(first, second);
+        assert_eq!(first.ply, datagen.random_plies);
+        assert!(!legal_moves(&first).is_empty());
+    }
+}
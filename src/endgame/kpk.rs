@@ -0,0 +1,307 @@
+use lazy_static::lazy_static;
+
+use crate::board::{piece::Piece, state::board::Board};
+use crate::color::Color;
+use crate::constants::PIECE_ATTACKS;
+use crate::squares::Square;
+
+
+/// wk (64) * bk (64) * stm (2) * pawn (files a-d (4) * ranks 2-7 (6))
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+
+/// KPK (king and pawn vs king) bitbase, from white's (the side with the pawn) perspective.
+/// Every position is either a win for white, or a draw, so one bit per position is enough (24KB)
+struct Bitbase([u64; MAX_INDEX / 64]);
+
+impl Bitbase {
+    fn is_win(&self, idx: usize) -> bool {
+        self.0[idx / 64] & (1 << (idx % 64)) != 0
+    }
+}
+
+lazy_static! {
+    static ref KPK: Bitbase = generate();
+}
+
+/// Generates the bitbase on start up, instead of on the first probe
+pub(crate) fn init() {
+    lazy_static::initialize(&KPK);
+}
+
+/// Returns true if white (the side with the pawn) wins with best play.
+/// The pawn can be on any file, positions with the pawn on e-h are mirrored to a-d
+pub(crate) fn probe(wk: Square, bk: Square, pawn: Square, stm: Color) -> bool {
+    let (wk, bk, pawn) = if pawn as usize % 8 >= 4 { (wk as usize ^ 7, bk as usize ^ 7, pawn as usize ^ 7) } else { (wk as usize, bk as usize, pawn as usize) };
+    KPK.is_win(index(stm, bk, wk, pawn))
+}
+
+/// Probes the bitbase if this is a KPK position (for either colour), and returns true if the side with the pawn wins
+pub(crate) fn probe_board(board: &Board) -> Option<bool> {
+    if board.occupancies[Color::Both].count_ones() != 3 { return None }
+
+    let strong = [Color::White, Color::Black].into_iter().find(|&c| board[Piece::pawn(c)].count_ones() == 1)?;
+    // the bitbase is from the perspective of white being the side with the pawn
+    let sq = |piece: Piece| {
+        let sq = board[piece].trailing_zeros() as u64;
+        Square::from(if strong == Color::White { sq } else { sq ^ 56 })
+    };
+    let stm = if board.turn == strong { Color::White } else { Color::Black };
+
+    Some(probe(sq(Piece::king(strong)), sq(Piece::king(!strong)), sq(Piece::pawn(strong)), stm))
+}
+
+/// wk (bits 0-5), bk (bits 6-11), stm (bit 12), pawn file (bits 13-14), 6 - pawn rank (bits 15-17, with ranks counted from 0)
+fn index(stm: Color, bk: usize, wk: usize, pawn: usize) -> usize {
+    wk | (bk << 6) | ((stm as usize) << 12) | ((pawn % 8) << 13) | ((6 - pawn / 8) << 15)
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    ((a % 8).abs_diff(b % 8)).max((a / 8).abs_diff(b / 8))
+}
+
+/// The squares attacked by a white pawn on sq
+fn pawn_attacks(sq: usize) -> u64 {
+    let pawn = 1u64 << sq;
+    ((pawn << 7) & !0x8080_8080_8080_8080) | ((pawn << 9) & !0x0101_0101_0101_0101)
+}
+
+fn king_attacks(sq: usize) -> u64 {
+    PIECE_ATTACKS.king_attacks[sq]
+}
+
+
+/// Retrograde analysis: every position is first classified on its own (illegal, immediate promotion, stalemate, pawn lost),
+/// and the unknown positions are then resolved from their children, until nothing changes anymore.
+/// Whatever is still unknown at the end can never be won by white, so it is a draw
+fn generate() -> Bitbase {
+    let positions = (0..MAX_INDEX).map(|idx| {
+        let (wk, bk, stm, pawn) = (idx & 0x3F, (idx >> 6) & 0x3F, if (idx >> 12) & 1 == 0 { Color::White } else { Color::Black }, ((6 - (idx >> 15)) * 8) + ((idx >> 13) & 3));
+        KPKPosition { wk, bk, stm, pawn }
+    }).collect::<Vec<_>>();
+
+    let mut db = positions.iter().map(|p| p.initial()).collect::<Vec<_>>();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (idx, position) in positions.iter().enumerate() {
+            if db[idx] != UNKNOWN { continue }
+
+            let result = position.classify(&db);
+            if result != UNKNOWN {
+                db[idx] = result;
+                changed = true;
+            }
+        }
+    }
+
+    let mut bitbase = Bitbase([0; MAX_INDEX / 64]);
+    for (idx, _) in db.iter().enumerate().filter(|(_, &r)| r == WIN) {
+        bitbase.0[idx / 64] |= 1 << (idx % 64);
+    }
+    bitbase
+}
+
+
+#[derive(Debug, Clone, Copy)]
+struct KPKPosition {
+    wk: usize,
+    bk: usize,
+    stm: Color,
+    pawn: usize,
+}
+
+impl KPKPosition {
+    fn initial(&self) -> u8 {
+        let Self { wk, bk, stm, pawn } = *self;
+        let push = pawn + 8;
+
+        // kings next to each other, overlapping pieces, or black in check with white to move
+        if distance(wk, bk) <= 1 || wk == pawn || bk == pawn || (stm == Color::White && pawn_attacks(pawn) & (1 << bk) != 0) {
+            return INVALID;
+        }
+
+        if stm == Color::White {
+            // the pawn promotes, and the new queen cannot be captured
+            if pawn / 8 == 6 && wk != push && bk != push && (distance(bk, push) > 1 || distance(wk, push) == 1) {
+                return WIN;
+            }
+            return UNKNOWN;
+        }
+
+        // stalemate
+        if king_attacks(bk) & !(king_attacks(wk) | pawn_attacks(pawn)) == 0 {
+            return DRAW;
+        }
+        // the pawn is captured
+        if king_attacks(bk) & (1 << pawn) != 0 && king_attacks(wk) & (1 << pawn) == 0 {
+            return DRAW;
+        }
+
+        UNKNOWN
+    }
+
+    /// White wins if any move wins, and black draws if any move draws
+    fn classify(&self, db: &[u8]) -> u8 {
+        let Self { wk, bk, stm, pawn } = *self;
+        let mut result = INVALID;
+
+        if stm == Color::White {
+            let mut targets = king_attacks(wk);
+            while targets != 0 {
+                let sq = targets.trailing_zeros() as usize;
+                targets &= targets - 1;
+                result |= db[index(Color::Black, bk, sq, pawn)];
+            }
+
+            // promotions are already resolved by the initial classification
+            let push = pawn + 8;
+            if pawn / 8 < 6 && push != wk && push != bk {
+                result |= db[index(Color::Black, bk, wk, push)];
+
+                let double = push + 8;
+                if pawn / 8 == 1 && double != wk && double != bk {
+                    result |= db[index(Color::Black, bk, wk, double)];
+                }
+            }
+
+            if result & WIN != 0 { WIN } else if result & UNKNOWN != 0 { UNKNOWN } else { DRAW }
+        } else {
+            let mut targets = king_attacks(bk);
+            while targets != 0 {
+                let sq = targets.trailing_zeros() as usize;
+                targets &= targets - 1;
+                result |= db[index(Color::White, sq, wk, pawn)];
+            }
+
+            if result & DRAW != 0 { DRAW } else if result & UNKNOWN != 0 { UNKNOWN } else { WIN }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod kpk_tests {
+    use super::*;
+    use crate::move_logic::{bitmove::Move, move_stack::MoveStack};
+    use crate::move_scope::MoveScope;
+    use crate::squares::Square::*;
+
+    fn children(board: &Board) -> Vec<Board> {
+        let mut mvs = MoveStack::<Move>::new();
+        board.gen_movement::<{ MoveScope::ALL }, Move>(&mut mvs);
+        (0..mvs.count_mvs()).filter_map(|i| board.make_move(*mvs.at(i).unwrap(), MoveScope::AllMoves)).collect()
+    }
+
+    fn fen(wk: usize, bk: usize, pawn: usize, stm: Color) -> String {
+        let mut squares = ['1'; 64];
+        (squares[wk], squares[bk], squares[pawn]) = ('K', 'k', 'P');
+
+        let ranks = (0..8).rev().map(|r| squares[r * 8..(r + 1) * 8].iter().collect::<String>()).collect::<Vec<_>>().join("/");
+        format!("{ranks} {} - - 0 1", if stm == Color::White { "w" } else { "b" })
+    }
+
+    /// A KPK position, as a key into the search results: wk (bits 0-5), bk (bits 6-11), pawn (bits 12-17), stm (bit 18)
+    fn key(wk: usize, bk: usize, pawn: usize, stm: Color) -> usize {
+        wk | (bk << 6) | (pawn << 12) | ((stm as usize) << 18)
+    }
+
+    /// Where a move leads: another KPK position, or the end of the search (a promotion, or the pawn is lost)
+    #[derive(Debug, Clone, Copy)]
+    enum Child { Kpk(usize), Won, Drawn }
+
+    /// Promotions are scored by hand: white wins if the new queen (or rook) survives, and black is not stalemated
+    fn child(board: &Board) -> Child {
+        if board[Piece::WP].count_ones() == 1 {
+            let sq = |piece: Piece| board[piece].trailing_zeros() as usize;
+            return Child::Kpk(key(sq(Piece::WK), sq(Piece::BK), sq(Piece::WP), board.turn));
+        }
+        // the pawn was captured, or underpromoted to a minor piece
+        if *board[Piece::WQ] | *board[Piece::WR] == 0 { return Child::Drawn }
+
+        let replies = children(board);
+        let won = if replies.is_empty() { board.stm_in_check() } else { replies.iter().all(|reply| *reply[Piece::WQ] | *reply[Piece::WR] != 0) };
+        if won { Child::Won } else { Child::Drawn }
+    }
+
+    /// Full width search with the real move generator, that never looks at the bitbase: every legal KPK position (pawn on a-d) is searched at once,
+    /// one ply deeper on every iteration, and only promotions and positions without moves end a line.
+    /// Returns the positions that white wins within the depth at which no result changes anymore, i.e. the forced wins (a drawn cycle is never won)
+    fn brute_force() -> Vec<(KPKPosition, bool)> {
+        let mut nodes = Vec::new();
+        for idx in 0..MAX_INDEX {
+            let (wk, bk, stm, pawn) = (idx & 0x3F, (idx >> 6) & 0x3F, if (idx >> 12) & 1 == 0 { Color::White } else { Color::Black }, ((6 - (idx >> 15)) * 8) + ((idx >> 13) & 3));
+            let position = KPKPosition { wk, bk, stm, pawn };
+            if position.initial() == INVALID { continue }
+
+            let board = Board::try_from(fen(wk, bk, pawn, stm).as_str()).unwrap();
+            let moves = children(&board).iter().map(child).collect::<Vec<_>>();
+            // mated, or stalemated
+            let terminal = moves.is_empty().then(|| stm == Color::Black && board.stm_in_check());
+            nodes.push((position, moves, terminal));
+        }
+
+        let mut wins = vec![false; 1 << 19];
+        loop {
+            let won = |child: &Child| match *child { Child::Kpk(k) => wins[k], Child::Won => true, Child::Drawn => false };
+            let deeper = nodes.iter().map(|(position, moves, terminal)| match (terminal, position.stm) {
+                (Some(mated), _) => *mated,
+                (None, Color::White) => moves.iter().any(won),
+                (None, _) => moves.iter().all(won),
+            }).collect::<Vec<_>>();
+
+            let mut changed = false;
+            for ((position, _, _), win) in nodes.iter().zip(&deeper) {
+                let k = key(position.wk, position.bk, position.pawn, position.stm);
+                changed |= wins[k] != *win;
+                wins[k] = *win;
+            }
+            if !changed { break }
+        }
+
+        nodes.into_iter().map(|(position, _, _)| (position, wins[key(position.wk, position.bk, position.pawn, position.stm)])).collect()
+    }
+
+    #[test]
+    fn bitbase_should_match_a_brute_force_search() {
+        let results = brute_force();
+        assert!(results.len() > 100_000);
+
+        for (KPKPosition { wk, bk, stm, pawn }, win) in results {
+            assert_eq!(probe(Square::from(wk as u64), Square::from(bk as u64), Square::from(pawn as u64), stm), win, "{}", fen(wk, bk, pawn, stm));
+        }
+    }
+
+    #[test]
+    fn black_pawns_should_be_probed_from_blacks_perspective() {
+        let white = Board::try_from("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap();
+        let black = Board::try_from("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(probe_board(&white), Some(true));
+        assert_eq!(probe_board(&black), Some(true));
+        assert_eq!(probe_board(&Board::try_from("4k3/8/4K3/4P3/8/8/8/7R b - - 0 1").unwrap()), None);
+    }
+
+    #[test]
+    fn should_classify_known_kpk_positions() {
+        // the king is in front of its pawn, with the opposition
+        assert!(probe(E6, E8, E5, Color::Black));
+        // black holds the opposition in front of the pawn
+        assert!(!probe(E4, E6, E3, Color::White));
+        // the black king cannot catch the pawn
+        assert!(probe(A1, H8, A5, Color::White));
+        // a rook pawn with the defending king in the corner
+        assert!(!probe(A6, A8, A5, Color::White));
+    }
+
+    #[test]
+    fn mirrored_positions_should_have_the_same_result() {
+        assert_eq!(probe(B5, D7, C4, Color::White), probe(G5, E7, F4, Color::White));
+        assert_eq!(probe(D6, D8, D5, Color::Black), probe(E6, E8, E5, Color::Black));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::bitboard::Bitboard;
+use crate::board::{piece::Piece, state::board::Board};
+use crate::color::Color;
+use crate::constants::KNOWN_WIN;
+use crate::squares::Square;
+
+pub(crate) mod kpk;
+
+
+/// Scale factors are out of SCALE_NORMAL, i.e. a scale factor of 32 halves the evaluation
+pub(crate) const SCALE_NORMAL: i32 = 64;
+pub(crate) const SCALE_DRAW: i32 = 0;
+
+const PAWN: i32 = Piece::PIECE_VALUES[0];
+const BISHOP: i32 = Piece::PIECE_VALUES[2];
+const ROOK: i32 = Piece::PIECE_VALUES[3];
+
+const A_FILE: u64 = 0x0101_0101_0101_0101;
+const H_FILE: u64 = A_FILE << 7;
+
+
+/// The material signature of a position: the number of pawns, knights, bishops, rooks and queens of each side (4 bits per piece).
+/// Kings are not counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct MaterialKey(u64);
+
+impl MaterialKey {
+    /// code: the pieces of the strong side and then the weak side, e.g. "KBNK" (king, bishop and knight vs king)
+    fn new(code: &str, strong: Color) -> Self {
+        let weak_king = code[1..].find('K').expect("Both sides must have a king") + 1;
+        let (ours, theirs) = code.split_at(weak_king);
+
+        let mut key = 0;
+        for (pieces, color) in [(ours, strong), (theirs, !strong)] {
+            for piece in pieces.chars().filter(|&p| p != 'K') {
+                let p = "PNBRQ".find(piece).expect("Unrecognized piece in the material code");
+                key += 1 << Self::shift(p, color);
+            }
+        }
+        Self(key)
+    }
+
+    /// The signature, spread over all 64 bits (the raw counts mostly live in the low bits)
+    pub(crate) fn hash(&self) -> u64 {
+        self.0.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn shift(piece: usize, color: Color) -> u64 {
+        ((color as usize * 5 + piece) * 4) as u64
+    }
+}
+
+impl From<&Board> for MaterialKey {
+    fn from(board: &Board) -> Self {
+        let mut key = 0;
+        for color in [Color::White, Color::Black] {
+            for piece in 0..5 {
+                let count = board[Piece::from((color as usize * Piece::COUNT + piece) as u8)].count_ones().min(15) as u64;
+                key |= count << Self::shift(piece, color);
+            }
+        }
+        Self(key)
+    }
+}
+
+
+/// Returns the score of the position from the strong side's perspective
+type EndgameFn = fn(&Board, Color) -> i32;
+
+lazy_static! {
+    /// Specialised evaluations, by material signature (and the side they are specialised for)
+    static ref ENDGAMES: HashMap<MaterialKey, (EndgameFn, Color)> = {
+        let endgames: [(&str, EndgameFn); 2] = [("KPK", kpk), ("KBNK", kbnk)];
+
+        let mut map = HashMap::new();
+        for (code, f) in endgames {
+            for strong in [Color::White, Color::Black] {
+                map.insert(MaterialKey::new(code, strong), (f as EndgameFn, strong));
+            }
+        }
+        map
+    };
+}
+
+/// Generates the tables used by the endgame evaluations (i.e. the KPK bitbase)
+pub(crate) fn init() {
+    kpk::init();
+    lazy_static::initialize(&ENDGAMES);
+}
+
+
+/// Adds the endgame knowledge to a static evaluation (HCE, or NNUE) from the side to move's perspective.
+/// Known endgames replace the evaluation altogether, and drawish endings are scaled down towards 0
+pub(crate) fn evaluate(board: &Board, eval: i32) -> i32 {
+    if let Some(score) = specialised(board) { return score }
+
+    let strong = if eval >= 0 { board.turn } else { !board.turn };
+    eval * scale_factor(board, strong) / SCALE_NORMAL
+}
+
+/// Endgames where one side only has its king left
+fn specialised(board: &Board) -> Option<i32> {
+    let bare = |color: Color| board.occupancies[color] == *board[Piece::king(color)];
+    let strong = match (bare(Color::White), bare(Color::Black)) {
+        (false, true) => Color::White,
+        (true, false) => Color::Black,
+        _ => return None,
+    };
+
+    let score = match ENDGAMES.get(&MaterialKey::from(board)) {
+        Some(&(f, side)) if side == strong => f(board, strong),
+        _ if non_pawn_material(board, strong) >= ROOK => kxk(board, strong),
+        _ => return None,
+    };
+
+    Some(if board.turn == strong { score } else { -score })
+}
+
+
+fn king_sq(board: &Board, color: Color) -> usize {
+    board[Piece::king(color)].trailing_zeros() as usize
+}
+
+fn count(board: &Board, piece: Piece) -> i32 {
+    board[piece].count_ones() as i32
+}
+
+fn material(board: &Board, color: Color) -> i32 {
+    (0..5).map(|p| count(board, Piece::from((color as usize * Piece::COUNT + p) as u8)) * Piece::PIECE_VALUES[p]).sum()
+}
+
+fn non_pawn_material(board: &Board, color: Color) -> i32 {
+    material(board, color) - count(board, Piece::pawn(color)) * PAWN
+}
+
+fn distance(a: usize, b: usize) -> i32 {
+    ((a % 8).abs_diff(b % 8)).max((a / 8).abs_diff(b / 8)) as i32
+}
+
+/// Bonus for a king far away from the centre: 0 on the central squares, up to 120 in the corners
+fn push_to_edge(sq: usize) -> i32 {
+    let centre = |x: usize| if x < 4 { 3 - x } else { x - 4 };
+    20 * (centre(sq % 8) + centre(sq / 8)) as i32
+}
+
+/// Bonus for the kings being close to each other
+fn push_close(a: usize, b: usize) -> i32 {
+    140 - 20 * distance(a, b)
+}
+
+
+/// K + mating material vs K: the weak king is driven to the edge, with the strong king following it
+fn kxk(board: &Board, strong: Color) -> i32 {
+    let (sk, wk) = (king_sq(board, strong), king_sq(board, !strong));
+    let score = material(board, strong) + push_to_edge(wk) + push_close(sk, wk);
+
+    let bishops = *board[Piece::bishop(strong)];
+    let bishop_pair = bishops & Bitboard::WHITE_SQUARES != 0 && bishops & Bitboard::BLACK_SQUARES != 0;
+
+    if count(board, Piece::queen(strong)) > 0 || count(board, Piece::rook(strong)) > 0 || bishop_pair ||
+        (bishops != 0 && count(board, Piece::knight(strong)) > 0) {
+        return score + KNOWN_WIN;
+    }
+    score
+}
+
+/// K + B + N vs K: mate can only be forced in a corner of the bishop's colour, so that is where the weak king is driven
+fn kbnk(board: &Board, strong: Color) -> i32 {
+    let (sk, wk) = (king_sq(board, strong), king_sq(board, !strong));
+    let manhattan = |a: usize, b: usize| ((a % 8).abs_diff(b % 8) + (a / 8).abs_diff(b / 8)) as i32;
+
+    // a1 and h8 are dark squares
+    let corners = if *board[Piece::bishop(strong)] & Bitboard::BLACK_SQUARES != 0 { [Square::A1, Square::H8] } else { [Square::H1, Square::A8] };
+    let corner_distance = corners.iter().map(|&c| manhattan(wk, c as usize)).min().unwrap();
+
+    KNOWN_WIN + material(board, strong) + push_close(sk, wk) + 30 * (14 - corner_distance)
+}
+
+/// K + P vs K: exact win/draw from the bitbase, wins are rewarded for advancing the pawn
+fn kpk(board: &Board, strong: Color) -> i32 {
+    if kpk::probe_board(board) != Some(true) { return 0 }
+
+    let pawn = board[Piece::pawn(strong)].trailing_zeros() as i32;
+    let rank = if strong == Color::White { pawn / 8 } else { 7 - pawn / 8 };
+    KNOWN_WIN + PAWN + 10 * rank
+}
+
+
+/// How much of the evaluation (out of SCALE_NORMAL) should be kept for the strong side, in endings that are harder to win than the material suggests
+pub(crate) fn scale_factor(board: &Board, strong: Color) -> i32 {
+    let weak = !strong;
+    let (pawns, bishops) = (*board[Piece::pawn(strong)], *board[Piece::bishop(strong)]);
+    let npm = (non_pawn_material(board, strong), non_pawn_material(board, weak));
+
+    // Bishop and rook pawn(s), when the bishop does not control the promotion square and the defending king is already there
+    if pawns != 0 && bishops != 0 && npm.0 == count(board, Piece::bishop(strong)) * BISHOP && npm.1 == 0 &&
+        (pawns & !A_FILE == 0 || pawns & !H_FILE == 0) {
+        let file = pawns.trailing_zeros() as usize % 8;
+        let promotion = if strong == Color::White { 56 + file } else { file };
+        let promotion_colour = if (1u64 << promotion) & Bitboard::BLACK_SQUARES != 0 { Bitboard::BLACK_SQUARES } else { Bitboard::WHITE_SQUARES };
+
+        if bishops & promotion_colour == 0 && distance(king_sq(board, weak), promotion) <= 1 {
+            return SCALE_DRAW;
+        }
+    }
+
+    // Opposite-coloured bishops
+    let their_bishops = *board[Piece::bishop(weak)];
+    if bishops.count_ones() == 1 && their_bishops.count_ones() == 1 &&
+        ((bishops & Bitboard::WHITE_SQUARES != 0) != (their_bishops & Bitboard::WHITE_SQUARES != 0)) {
+        // only the bishops and pawns are left
+        if npm.0 == BISHOP && npm.1 == BISHOP {
+            let pawn_difference = (count(board, Piece::pawn(strong)) - count(board, Piece::pawn(weak))).abs();
+            return if pawn_difference <= 1 { 16 } else { 24 };
+        }
+        return 48;
+    }
+
+    // Without pawns, less than a rook up is (almost) never enough to win
+    if pawns == 0 && npm.0 - npm.1 <= BISHOP {
+        if npm.0 < ROOK { return SCALE_DRAW }
+        return if npm.1 <= BISHOP { 4 } else { 14 };
+    }
+
+    SCALE_NORMAL
+}
+
+
+#[cfg(test)]
+mod endgame_tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::try_from(fen).unwrap()
+    }
+
+    #[test]
+    fn material_keys_should_match_the_codes() {
+        assert_eq!(MaterialKey::from(&board("8/8/4k3/8/2B5/8/4K3/3N4 b - - 0 1")), MaterialKey::new("KBNK", Color::White));
+        assert_eq!(MaterialKey::from(&board("8/8/4k3/4p3/8/8/4K3/8 w - - 0 1")), MaterialKey::new("KPK", Color::Black));
+        assert_ne!(MaterialKey::new("KPK", Color::White), MaterialKey::new("KPK", Color::Black));
+    }
+
+    #[test]
+    fn kxk_should_drive_the_weak_king_to_the_edge() {
+        let centre = evaluate(&board("8/8/8/3k4/8/3K4/8/7R w - - 0 1"), 0);
+        let edge = evaluate(&board("3k4/8/3K4/8/8/8/8/7R w - - 0 1"), 0);
+        let corner = evaluate(&board("k7/8/2K5/8/8/8/8/7R w - - 0 1"), 0);
+
+        assert!(centre > KNOWN_WIN);
+        assert!(edge > centre && corner > edge);
+        // scores are from the side to move's perspective
+        assert_eq!(evaluate(&board("k7/8/2K5/8/8/8/8/7R b - - 0 1"), 0), -corner);
+    }
+
+    #[test]
+    fn kbnk_should_drive_the_weak_king_to_the_bishops_corner() {
+        // light squared bishop: a8 and h1
+        let right = evaluate(&board("k7/8/2K5/8/8/8/8/3BN3 w - - 0 1"), 0);
+        let wrong = evaluate(&board("7k/8/5K2/8/8/8/8/3BN3 w - - 0 1"), 0);
+        assert!(right > wrong && wrong > KNOWN_WIN);
+    }
+
+    #[test]
+    fn kpk_should_be_classified_exactly() {
+        assert!(evaluate(&board("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), 0) < -KNOWN_WIN);
+        assert_eq!(evaluate(&board("k7/8/K7/P7/8/8/8/8 w - - 0 1"), 500), 0);
+        // the same positions, with the colours flipped
+        assert!(evaluate(&board("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"), 0) < -KNOWN_WIN);
+        assert_eq!(evaluate(&board("8/8/8/8/p7/k7/8/K7 b - - 0 1"), -500), 0);
+    }
+
+    #[test]
+    fn drawish_endings_should_be_scaled_down() {
+        // the h8 promotion square is dark, and the bishop is light squared
+        assert_eq!(evaluate(&board("7k/8/8/7P/8/8/8/3BK3 w - - 0 1"), 300), 0);
+        // a dark squared bishop controls h8
+        assert_eq!(evaluate(&board("7k/8/8/7P/8/8/8/2B1K3 w - - 0 1"), 300), 300);
+
+        // opposite-coloured bishops, a pawn up
+        assert_eq!(evaluate(&board("4k3/5p2/8/2b5/8/3B4/4PP2/4K3 w - - 0 1"), 128), 32);
+        // knight vs bishop without pawns
+        assert_eq!(evaluate(&board("4k3/8/8/2b5/8/3N4/8/4K3 w - - 0 1"), 40), 0);
+    }
+}
@@ -0,0 +1,316 @@
+use std::{sync::{Arc, Mutex}, thread, time::Duration};
+
+use thiserror::Error;
+
+use crate::{board::{position::Position, state::board::Board}, constants::{params::MAX_DEPTH, START_POSITION}, endgame, move_logic::{bitmove::Move, move_stack::MoveStack}, move_scope::MoveScope, nnue::EMBEDDED_NET, search::{contempt::Contempt, info::{Score, SearchInfo}, search::SearchWorker, signals::SearchSignals}, syzygy::probe::SyzygyConfig, tt::table::TTable, uci::{clock::Clock, counter::Counter}};
+
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EngineError {
+    #[error("Invalid FEN: {0}")]
+    InvalidFen(String),
+    #[error("Illegal move: {0}")]
+    IllegalMove(String),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineConfig {
+    /// The number of search threads
+    pub threads: usize,
+    /// Evaluate with the NNUE, or with the handcrafted evaluation (builds without the `nnue` feature always use the latter)
+    pub use_nnue: bool,
+    /// In centipawns, the root side scores draws at -contempt (see the UCI Contempt option)
+    pub contempt: i32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self { threads: 1, use_nnue: EMBEDDED_NET, contempt: 0 }
+    }
+}
+
+
+/// When a search ends (it also ends with Engine::stop, a pondering search only starts counting at Engine::ponderhit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchLimits {
+    Depth(u8),
+    /// The nodes of every search thread
+    Nodes(u64),
+    MoveTime(Duration),
+    /// The clock of a game (in milliseconds), the engine decides how long to think
+    Clock { wtime: u64, btime: u64, winc: u64, binc: u64, movestogo: Option<u64> },
+    /// Until Engine::stop
+    Infinite,
+}
+
+impl From<SearchLimits> for Counter {
+    fn from(limits: SearchLimits) -> Self {
+        match limits {
+            SearchLimits::Depth(depth) => Counter::Depth(depth),
+            SearchLimits::Nodes(nodes) => Counter::Nodes(nodes),
+            SearchLimits::MoveTime(time) => Counter::Time(time.as_millis() as u64),
+            SearchLimits::Clock { wtime, btime, winc, binc, movestogo } => Counter::Dynamic { wtime, btime, winc, binc, movestogo },
+            SearchLimits::Infinite => Counter::Infinite,
+        }
+    }
+}
+
+
+/// The outcome of Engine::search, the moves are in UCI notation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// None only when the side to move has no legal move
+    pub best_move: Option<String>,
+    /// The expected reply to the best move
+    pub ponder: Option<String>,
+    pub score: Score,
+    pub pv: Vec<String>,
+    /// The last completed iteration
+    pub depth: usize,
+    /// The nodes of all the search threads
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+
+/// The game state of the engine, only one search can use it at a time
+struct Game {
+    position: Position,
+    tt: TTable,
+    workers: Vec<SearchWorker>,
+    config: EngineConfig,
+}
+
+
+/// The engine as a library: a position, and the search threads that live as long as the engine (their histories carry over from one
+/// search to the next, until new_game).
+/// Every method takes `&self`, so that `stop` (and `ponderhit`) can be called from another thread while a search is running
+pub struct Engine {
+    signals: Arc<SearchSignals>,
+    game: Mutex<Game>,
+}
+
+impl Engine {
+    pub fn new(config: EngineConfig) -> Self {
+        endgame::init();
+
+        let config = EngineConfig { threads: config.threads.max(1), ..config };
+        let signals = Arc::new(SearchSignals::default());
+        let tt = TTable::default();
+        let clock = Clock::new(Arc::clone(&signals));
+        let workers = (0..config.threads).map(|_| SearchWorker::new(tt.get(), clock.clone(), SyzygyConfig::default())).collect();
+        let position = Position::with(Board::try_from(START_POSITION).unwrap());
+
+        Self { signals, game: Mutex::new(Game { position, tt, workers, config }) }
+    }
+
+    /// Sets the position to search: a FEN (or "startpos"), followed by moves in UCI notation (e.g. e2e4, e7e8q).
+    /// The position is left unchanged if the FEN or any of the moves is invalid
+    pub fn set_position(&self, fen: &str, moves: &[&str]) -> Result<(), EngineError> {
+        let fen = if fen == "startpos" { START_POSITION } else { fen };
+        let board = Board::try_from(fen).map_err(|e| EngineError::InvalidFen(e.to_string()))?;
+        let mut position = Position::with(board);
+
+        for &mv in moves {
+            let legal = Self::legal_moves(&position).into_iter().find(|legal| legal.to_uci() == mv);
+            let Some(legal) = legal else { return Err(EngineError::IllegalMove(mv.to_string())) };
+            position.make_move(legal, MoveScope::AllMoves);
+            // every move pushes an accumulator, and the stack only has room for the search (MAX_DEPTH), not for the whole game
+            position.refresh_nnue();
+        }
+
+        self.game.lock().unwrap().position = position;
+        Ok(())
+    }
+
+    /// Forgets everything learnt in the previous games (the UCI `ucinewgame`)
+    pub fn new_game(&self) {
+        let mut game = self.game.lock().unwrap();
+        game.tt = TTable::default();
+        game.workers.iter_mut().for_each(SearchWorker::clear);
+    }
+
+    /// Searches the current position until the limits are reached (or stop is called), `on_info` receives every completed iteration
+    pub fn search(&self, limits: SearchLimits, on_info: impl FnMut(&SearchInfo)) -> SearchResult {
+        self.run(limits, false, on_info)
+    }
+
+    /// Searches the current position (the one after the expected reply) without any limit until ponderhit, then like `search`
+    pub fn ponder(&self, limits: SearchLimits, on_info: impl FnMut(&SearchInfo)) -> SearchResult {
+        self.run(limits, true, on_info)
+    }
+
+    /// The opponent played the expected move: the pondering search now ends at its limits
+    pub fn ponderhit(&self) {
+        self.signals.ponderhit();
+    }
+
+    fn run(&self, limits: SearchLimits, ponder: bool, mut on_info: impl FnMut(&SearchInfo)) -> SearchResult {
+        let mut game = self.game.lock().unwrap();
+        let Game { position, tt, workers, config } = &mut *game;
+
+        // The stop flag is only cleared once a search is over, a stop() that comes in before the search starts is not lost
+        self.signals.start(ponder);
+        let mut clock = Clock::new(Arc::clone(&self.signals));
+        clock.set_limit(Counter::from(limits), position.turn);
+        tt.increase_age();
+        clock.start();
+
+        let depth = match limits { SearchLimits::Depth(depth) => depth as usize, _ => MAX_DEPTH };
+        let mut root = position.clone();
+        root.use_nnue = config.use_nnue && EMBEDDED_NET;
+
+        for worker in workers.iter_mut() {
+            worker.new_search(tt.get(), clock.clone(), SyzygyConfig::default());
+            worker.set_contempt(Contempt { value: config.contempt.clamp(-Contempt::MAX, Contempt::MAX), dynamic: false });
+        }
+
+        let (main, helpers) = workers.split_first_mut().unwrap();
+        thread::scope(|s| {
+            for helper in helpers {
+                let mut root = root.clone();
+                s.spawn(move || helper.iterative_deepening_with(depth, &mut root, &mut |_| {}));
+            }
+
+            main.iterative_deepening_with(depth, &mut root.clone(), &mut on_info);
+            // the helpers only fill the TT for the main thread
+            self.signals.stop();
+        });
+
+        let main = &workers[0];
+        let pv = main.pv();
+        // stopped before the first iteration: any legal move is better than none
+        let best_move = pv.first().copied().or_else(|| Self::legal_moves(&root).first().copied());
+
+        let result = SearchResult {
+            best_move: best_move.map(|mv| mv.to_uci()),
+            ponder: pv.get(1).map(|mv| mv.to_uci()),
+            score: Score::from(main.eval()),
+            pv: pv.iter().map(|mv| mv.to_uci()).collect(),
+            depth: main.depth(),
+            nodes: self.signals.nodes(),
+            time: clock.elapsed(),
+        };
+
+        // still holding the game: a stop() sent from now on is for the next search
+        self.signals.reset(false);
+        result
+    }
+
+    /// Ends the running search, its result is the one of the last completed iteration.
+    /// Without a running search, the next one is stopped as soon as it starts (so that a stop sent right before a search starts is not lost)
+    pub fn stop(&self) {
+        self.signals.stop();
+    }
+
+    pub(crate) fn legal_moves(position: &Position) -> Vec<Move> {
+        let mut mvs = MoveStack::<Move>::new();
+        position.gen_movement::<{ MoveScope::ALL }, Move>(&mut mvs);
+        mvs.into_iter().filter(|&mv| position.board.make_move(mv, MoveScope::AllMoves).is_some()).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod engine_tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn positions_should_only_accept_legal_moves() {
+        let engine = Engine::new(EngineConfig::default());
+        assert!(engine.set_position("startpos", &["e2e4", "e7e5", "g1f3"]).is_ok());
+        assert_eq!(engine.set_position("startpos", &["e2e4", "e2e4"]), Err(EngineError::IllegalMove(String::from("e2e4"))));
+        assert!(matches!(engine.set_position("8/8/8 w - - 0 1", &[]), Err(EngineError::InvalidFen(_))));
+
+        // promotions are lowercase
+        assert!(engine.set_position("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1", &["e7e8q"]).is_ok());
+    }
+
+    #[test]
+    fn games_longer_than_the_accumulator_stack_should_be_searchable() {
+        let engine = Engine::new(EngineConfig::default());
+        // 144 plies of knight moves
+        let moves = ["g1f3", "g8f6", "f3g1", "f6g8"].repeat(36);
+        engine.set_position("startpos", &moves).unwrap();
+
+        let result = engine.search(SearchLimits::Depth(4), |_| {});
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn search_should_report_every_iteration() {
+        let engine = Engine::new(EngineConfig { threads: 2, ..EngineConfig::default() });
+        engine.set_position("startpos", &["e2e4"]).unwrap();
+
+        let mut depths = Vec::new();
+        let result = engine.search(SearchLimits::Depth(4), |info| depths.push(info.depth));
+
+        assert_eq!(depths, [1, 2, 3, 4]);
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.best_move.as_ref(), result.pv.first());
+        assert_eq!(result.ponder.as_ref(), result.pv.get(1));
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn search_should_win_a_hanging_queen() {
+        for use_nnue in [true, false] {
+            let engine = Engine::new(EngineConfig { use_nnue, ..EngineConfig::default() });
+            engine.set_position("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", &[]).unwrap();
+
+            let result = engine.search(SearchLimits::Depth(4), |_| {});
+            assert_eq!(result.best_move.as_deref(), Some("d2d5"));
+        }
+    }
+
+    #[test]
+    fn stop_should_end_an_infinite_search_from_another_thread() {
+        let engine = Engine::new(EngineConfig { threads: 2, ..EngineConfig::default() });
+        let start = Instant::now();
+
+        let result = thread::scope(|s| {
+            s.spawn(|| { thread::sleep(Duration::from_millis(200)); engine.stop(); });
+            engine.search(SearchLimits::Infinite, |_| {})
+        });
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+
+        // the next search is not stopped
+        let result = engine.search(SearchLimits::Depth(2), |_| {});
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn stop_should_not_be_lost_when_sent_before_the_search_starts() {
+        let engine = Engine::new(EngineConfig::default());
+        let start = Instant::now();
+
+        engine.stop();
+        let result = engine.search(SearchLimits::Infinite, |_| {});
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+
+        // that stop was only for the search that followed it
+        let result = engine.search(SearchLimits::Depth(2), |_| {});
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn pondering_should_ignore_the_limits_until_ponderhit() {
+        let engine = Engine::new(EngineConfig::default());
+        let start = Instant::now();
+
+        let result = thread::scope(|s| {
+            s.spawn(|| { thread::sleep(Duration::from_millis(300)); engine.ponderhit(); });
+            engine.ponder(SearchLimits::MoveTime(Duration::from_millis(10)), |_| {})
+        });
+
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+    }
+}
@@ -0,0 +1,83 @@
+//! A UCI chess engine (NNUE evaluation, alpha-beta search), that can also be embedded as a library:
+//!
+//! ```no_run
+//! use chess::{Engine, EngineConfig, SearchLimits};
+//!
+//! let engine = Engine::new(EngineConfig::default());
+//! // https://www.chessprogramming.org/Perft_Results#Position_2
+//! engine.set_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[]).unwrap();
+//!
+//! let result = engine.search(SearchLimits::Depth(6), |info| println!("{info}"));
+//! println!("bestmove {}", result.best_move.unwrap_or_else(|| String::from("0000")));
+//!
+//! // or from a game
+//! engine.set_position("startpos", &["e2e4", "e7e5"]).unwrap();
+//! ```
+//!
+//! `Engine::stop` ends a running search from any other thread (the engine is `Sync`, e.g. shared in an `Arc`), a stop sent just before
+//! a search starts ends that search.
+
+mod bitboard;
+mod masks;
+mod game_phase;
+mod utils;
+mod squares;
+mod zobrist;
+mod cuckoo;
+mod color;
+mod search;
+mod nnue;
+mod shift;
+mod board;
+mod command;
+mod move_logic;
+#[allow(dead_code)]
+mod constants;
+// mod random_magic;
+mod piece_attacks;
+mod move_scope;
+mod perft;
+mod kogge_stone;
+mod uci;
+mod tt;
+mod syzygy;
+mod datagen;
+mod endgame;
+mod tuner;
+mod engine;
+
+pub use engine::{Engine, EngineConfig, EngineError, SearchLimits, SearchResult};
+pub use search::info::{Score, SearchInfo};
+
+
+/// The commands of the binary, they are not part of the engine API
+#[doc(hidden)]
+pub mod cli {
+    use crate::{datagen::DataGen, endgame, tuner::TexelTuner, uci::UCI};
+
+    /// The UCI loop, on stdin and stdout (the default command)
+    pub fn uci() {
+        endgame::init();
+        if let Err(e) = UCI::default().reader() {
+            eprintln!("uci failed: {e}");
+        }
+    }
+
+    /// `chess datagen [threads N] [games N] [nodes N] [plies N] [seed N] [out PATH]`
+    pub fn datagen(args: &[String]) {
+        endgame::init();
+        match DataGen::parse(args.iter().map(|arg| arg.as_str())) {
+            Ok(datagen) => datagen.run(),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+
+    /// `chess tune-hce data PATH [epochs N] [lr X] [out PATH]`
+    pub fn tune_hce(args: &[String]) {
+        endgame::init();
+        match TexelTuner::parse(args.iter().map(|arg| arg.as_str())) {
+            Ok(tuner) => if let Err(e) = tuner.run() { eprintln!("tune-hce failed: {e}") },
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
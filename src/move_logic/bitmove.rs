@@ -63,6 +63,12 @@ impl Move {
          let bmove = src as u16 | (tgt as u16) << 6 | (variant as u16) << 12;
          Self(bmove)
      }
+
+    /// Long algebraic notation as expected by UCI (e.g. e7e8q), i.e. without the capture marker of Display
+    pub(crate) fn to_uci(&self) -> String {
+        let promotion = self.get_promotion().map(|x| x.to_string().to_lowercase()).unwrap_or_default();
+        format!("{}{}{promotion}", self.get_src(), self.get_target())
+    }
      
     pub(crate) fn get_src(&self) -> Square {
         let sq = (**self & SOURCE_SQUARE) as u64;
@@ -78,7 +78,7 @@ impl MovePicker {
             // let victim = (position.piece_at(mv.get_target()).unwrap() as usize) % 6;
 
             let attacker = position.piece_at(mv.get_src()).unwrap();
-            let victim = position.piece_at(mv.get_target()).unwrap();
+            let victim = if mv.get_enpassant() { Piece::pawn(!position.turn) } else { position.piece_at(mv.get_target()).unwrap() };
             let pre_score = match mv.move_type() {
                 MoveType::CaptureAndPromoteToQueen => {
                     Self::QUEEN_PROMOTION + Self::PROMOTES_AND_CAPTURE
@@ -91,12 +91,14 @@ impl MovePicker {
                     (Piece::MVV[victim as usize % 6] as i32 + caphist.get(attacker, mv.get_target(), PieceType::from(victim)) as i32) as i32},
             };
 
+            // Captures that don't win at least `see_threshold` in the exchange are only tried after the quiet moves
             let good_capture = position.see(&mv, self.see_threshold);
             let score = if good_capture {
                 self.total_good_captures += 1;
                 Self::GOOD_CAPTURE + pre_score
             } else {
-                Self::BAD_CAPTURE
+                // Bad captures are still ordered by the value of their victim
+                Self::BAD_CAPTURE + Piece::MVV[victim as usize % 6] as i32
             };
 
             capture.update_score(score);
@@ -53,8 +53,8 @@ impl<T: MoveAction> Iterator for MoveStack<T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.at < self.count {
-            self.at += 1;
             let current = Some(self.list[self.at]);
+            self.at += 1;
             return current;
         }
         return None
@@ -75,4 +75,22 @@ impl<T: MoveAction> DerefMut for MoveStack<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.list
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod move_stack_tests {
+    use crate::move_logic::bitmove::Move;
+
+    use super::*;
+
+    #[test]
+    fn iteration_should_yield_the_pushed_moves_in_order() {
+        let mut stack = MoveStack::<Move>::new();
+        (1..=5).for_each(|mv: u16| stack.push(Move::from(mv)));
+
+        let indexed = (0..stack.count_mvs()).map(|i| *stack.at(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), indexed);
+        assert_eq!(indexed.len(), 5);
+    }
+}
@@ -12,6 +12,9 @@ use crate::color::Color;
 use crate::squares::Square;
 
 use super::align64::Align64;
+use super::constants::custom_kp::L1_SIZE;
+use super::relu::Crelu;
+use super::dirty_pieces::{DirtyPieces, MAX_DIRTY};
 use super::feature_idx::FeatureIdx;
 use super::{halfka_idx, PARAMS};
 
@@ -137,70 +140,79 @@ impl<const U: usize> Accumulator<Feature, U> {
     }
 
     
-    pub(crate) unsafe fn update(&self, removed_features: Vec<(FeatureIdx, FeatureIdx)>, added_features: Vec<(FeatureIdx, FeatureIdx)>) -> Self {
-        const REGS_LEN: usize = ACCUMULATOR_SIZE * 2;
-
-        let mut regs: [__m256i; REGS_LEN] = [std::mem::zeroed(); REGS_LEN];
-
-        for idx in removed_features.into_iter() {
-            Self::update_weights::<false, REGS_LEN>(&mut regs, idx);
-        }
+    /// Returns the feature index of this piece, as seen from the perspective of `color`
+    fn perspective_idx(piece: Piece, sq: Square, color: Color) -> usize {
+        let (white, black) = halfka_idx(piece, sq);
+        if color == White { *white } else { *black }
+    }
 
-        for idx in added_features {
-            Self::update_weights::<true, REGS_LEN>(&mut regs, idx);
+    /// Recomputes only the `color` perspective of this accumulator from the pieces on the board
+    pub(crate) unsafe fn refresh_perspective(&mut self, board: &Board, color: Color) {
+        for i in 0..U {
+            *self[color].as_mut_ptr().add(i) = _mm256_load_si256(PARAMS.input_bias.as_ptr().add(i * Self::REGISTER_WIDTH) as *const __m256i);
         }
 
+        for (p, bitboard) in (*board.board).into_iter().enumerate() {
+            let mut sqs: u64 = *bitboard;
 
-        let mut acc = self.clone();
-        // move the computed data into the accumulator
-        for color in [Color::White, Color::Black].into_iter() {
-            for index in 0..ACCUMULATOR_SIZE {
-                let regs_idx = (color as usize * ACCUMULATOR_SIZE) + index;
+            while sqs != 0 {
+                let sq = Square::from(sqs.trailing_zeros() as u8);
+                let idx = Self::perspective_idx(Piece::from(p as u8), sq, color);
+                sqs &= sqs - 1;
 
-                // println!("regs_index is {regs_idx}, and index is {index}");
-                if color == White {
-                    *acc.white.as_mut_ptr().add(index) = regs[regs_idx];
-                } else {
-                    *acc.black.as_mut_ptr().add(index) = regs[regs_idx];
+                for i in 0..U {
+                    let weights = *(PARAMS.input_weight.as_ptr().add(idx + (i * Self::REGISTER_WIDTH)) as *const __m256i);
+                    let value = self[color].as_mut_ptr().add(i);
+                    *value = _mm256_add_epi16(*value, weights);
                 }
             }
         }
-    
-        acc
     }
 
-    pub(crate) unsafe fn crelu16(&self, stm: Color) -> [[__m256i; U]; 2] {
-        const IN_REGISTER_WIDTH: usize = 256/16;
-        const OUT_REGISTER_WIDTH: usize = 256/8;
-        assert_eq!(U % OUT_REGISTER_WIDTH, 0, "We're processing 32 elements at a time");
-        let num_out_chunks = U/OUT_REGISTER_WIDTH;
+    /// Computes the `color` perspective of this accumulator by applying the pieces changed by a move (dirty)
+    /// on top of the accumulator of the parent position (prev).
+    /// The other perspective is left untouched, so that each side can be brought up to date independently
+    pub(crate) unsafe fn update_perspective(&mut self, prev: &Self, color: Color, dirty: &DirtyPieces) {
+        let mut removed = [0usize; MAX_DIRTY];
+        let mut added = [0usize; MAX_DIRTY];
+        for (i, &(piece, sq)) in dirty.removed().iter().enumerate() { removed[i] = Self::perspective_idx(piece, sq, color); }
+        for (i, &(piece, sq)) in dirty.added().iter().enumerate() { added[i] = Self::perspective_idx(piece, sq, color); }
+        let (removed, added) = (&removed[..dirty.removed().len()], &added[..dirty.added().len()]);
+
+        for i in 0..U {
+            let mut value = *prev[color].as_ptr().add(i);
+
+            for idx in removed {
+                let weights = *(PARAMS.input_weight.as_ptr().add(idx + (i * Self::REGISTER_WIDTH)) as *const __m256i);
+                value = _mm256_sub_epi16(value, weights);
+            }
 
-        let input = if stm == Color::White {[self.white, self.black]} else {[self.black, self.white]};
-        let mut output: [[__m256i; U]; 2] = [[_mm256_setzero_si256(); U]; 2];
+            for idx in added {
+                let weights = *(PARAMS.input_weight.as_ptr().add(idx + (i * Self::REGISTER_WIDTH)) as *const __m256i);
+                value = _mm256_add_epi16(value, weights);
+            }
 
-        let zero = _mm256_setzero_si256();
-        const CONTROL: i32 = 0b11011000; // 3, 1, 2, 0; lane 0 is the rightmost one
+            *self[color].as_mut_ptr().add(i) = value;
+        }
+    }
 
-        for i in 0..num_out_chunks {
-            for color in [Color::White, Color::Black] {
-                let curr_input = *(input.as_ptr().add(color as usize));
-                let in0 = _mm256_load_si256(curr_input.as_ptr().add((i * 2 + 0) * IN_REGISTER_WIDTH));
-                let in1 = _mm256_load_si256(curr_input.as_ptr().add((i * 2 + 1) * IN_REGISTER_WIDTH));
-                
-                let result = _mm256_permute4x64_epi64(_mm256_max_epi8(_mm256_packs_epi16(in0, in1), zero), CONTROL);
+    /// Clips both perspectives into 0..=127 (side to move first), this is the int8 input of the deep architecture
+    pub(crate) unsafe fn crelu16(&self, stm: Color) -> Align64<[i8; L1_SIZE * 2]> {
+        assert_eq!(U * Self::REGISTER_WIDTH, L1_SIZE);
+        let mut output = Align64([0; L1_SIZE * 2]);
 
-                _mm256_store_si256(output.as_mut_ptr().add(i * OUT_REGISTER_WIDTH) as *mut __m256i, result);
-            }
+        for (i, color) in [stm, !stm].into_iter().enumerate() {
+            let input = std::ptr::read(self[color].as_ptr() as *const [i16; L1_SIZE]);
+            output[(i * L1_SIZE)..((i + 1) * L1_SIZE)].copy_from_slice(&Crelu::crelu16(L1_SIZE, input));
         }
 
         output
     }
 
     /// Loads input(16 i16 values), and 
-    ///     1. Ensures that the max of the input is i8(127) (saturates the input)
-    ///     2. And ensures that the min of the input is 0 (i8)
-    /// Then multiplies the input(now 16 i8 values) with the weights(16 i16 values)
-    /// to generates 16 i16 values (the output)
+    ///     1. Ensures that the max of the input is QA(255)
+    ///     2. And ensures that the min of the input is 0
+    /// The clamped values are squared (SCReLU) in propagate, since 255 * 255 does not fit in an i16
     pub(crate) unsafe fn sq_crelu16(&self, stm: Color) -> [Align64<[__m256i; U]>; 2] { // U is 1024
         let input = if stm == Color::White {[self.white, self.black]} else {[self.black, self.white]};
         let mut output: [Align64<[__m256i; U]>; 2] = [Align64([_mm256_setzero_si256(); U]); 2];  // [[_; 1024]; 2];
@@ -216,12 +228,8 @@ impl<const U: usize> Accumulator<Feature, U> {
                 let in0 = _mm256_load_si256(input[color].as_ptr().add(i)); // loads 16 i16 values from curr_input 
 
                 let clamped_min = _mm256_max_epi16(in0, min);
-                // despite being inside (i16 * 16) data structure, these are just (i8 * 16) values
-                // the reason for this, is to still be capable of returning (i16 * 16) values after the squaring (i.e multiplication)
                 let clamped_max = _mm256_min_epi16(clamped_min, max);
-                // // y = Ax + b
-                let result = _mm256_mullo_epi16(clamped_max, clamped_max); // square
-                _mm256_store_si256(output[color as usize].as_mut_ptr().add(i) as *mut __m256i, result);
+                _mm256_store_si256(output[color as usize].as_mut_ptr().add(i) as *mut __m256i, clamped_max);
             }
         }
 
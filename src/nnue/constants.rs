@@ -66,4 +66,10 @@ pub(crate) mod custom_kp {
     /// the number of neurons on this layer
     pub(crate) const L1_SIZE: usize = 1024;
     // pub(crate) const L2_SIZE: usize = 1024*2;
+    /// Only used by the deep architecture: (768 -> L1_SIZE)x2 -> L2_SIZE -> L3_SIZE -> 1
+    pub(crate) const L2_SIZE: usize = 32;
+    pub(crate) const L3_SIZE: usize = 32;
+    /// Whether the input features depend on the king's square (king buckets).
+    /// If true, a king move requires a refresh of that king's perspective of the accumulator
+    pub(crate) const KING_BUCKETS: bool = false;
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::{board::piece::Piece, color::Color, squares::Square};
+
+
+/// A single move can remove at most 2 pieces (the moved piece from its source, and a captured piece),
+/// and add at most 2 pieces (the moved/promoted piece on its target, and the rook when castling)
+pub(crate) const MAX_DIRTY: usize = 2;
+
+/// The features (pieces) that were added to, or removed from the board by a single move.
+/// These are recorded on every make, and only applied to the accumulator when the position is evaluated
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DirtyPieces {
+    added: [(Piece, Square); MAX_DIRTY],
+    removed: [(Piece, Square); MAX_DIRTY],
+    total_added: usize,
+    total_removed: usize,
+    /// Color of the king that moved (if this was a king move)
+    king_moved: Option<Color>,
+}
+
+impl Default for DirtyPieces {
+    fn default() -> Self {
+        Self { added: [(Piece::WP, Square::A1); MAX_DIRTY], removed: [(Piece::WP, Square::A1); MAX_DIRTY], total_added: 0, total_removed: 0, king_moved: None }
+    }
+}
+
+impl DirtyPieces {
+    pub(crate) fn add(&mut self, piece: Piece, sq: Square) {
+        self.added[self.total_added] = (piece, sq);
+        self.total_added += 1;
+        if piece == Piece::king(piece.color()) { self.king_moved = Some(piece.color()) }
+    }
+
+    pub(crate) fn remove(&mut self, piece: Piece, sq: Square) {
+        self.removed[self.total_removed] = (piece, sq);
+        self.total_removed += 1;
+    }
+
+    pub(crate) fn added(&self) -> &[(Piece, Square)] {
+        &self.added[..self.total_added]
+    }
+
+    pub(crate) fn removed(&self) -> &[(Piece, Square)] {
+        &self.removed[..self.total_removed]
+    }
+
+    pub(crate) fn king_moved(&self) -> Option<Color> {
+        self.king_moved
+    }
+}
@@ -0,0 +1,101 @@
+/// Every versioned net starts with these 4 bytes, nets without them are treated as legacy (version 0) nets
+pub(crate) const NET_MAGIC: [u8; 4] = *b"DOJA";
+/// The newest net version this build can read
+pub(crate) const NET_VERSION: u16 = 1;
+
+
+/// The layers that sit on top of the feature transformer (input -> L1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Architecture {
+    /// (768 -> L1)x2 -> 1
+    /// The accumulator is activated with SCReLU, and fed straight into a single i16 output neuron
+    SingleLayer = 0,
+    /// (768 -> L1)x2 -> L2 -> L3 -> 1
+    /// The accumulator is activated with CReLU (clipped to 0..=127), and fed into int8 affine layers
+    Deep = 1,
+}
+
+
+/// Versioned net header (8 bytes, little-endian):
+/// | magic: [u8; 4] | version: u16 | architecture: u8 | output_buckets: u8 |
+/// The feature transformer weights follow immediately after the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NetHeader {
+    pub(crate) version: u16,
+    pub(crate) arch: Architecture,
+    /// The number of material buckets on the output layer(s), (always 1 on legacy nets)
+    pub(crate) output_buckets: usize,
+}
+
+impl NetHeader {
+    pub(crate) const SIZE: usize = 8;
+
+    /// Nets written before the header existed, i.e. (768 -> 1024)x2 -> 1 without output buckets
+    pub(crate) const LEGACY: Self = Self { version: 0, arch: Architecture::SingleLayer, output_buckets: 1 };
+
+    /// Runs at compile time on the embedded net, so an unsupported net fails the build instead of the engine
+    pub(crate) const fn parse(bytes: &[u8]) -> Self {
+        if bytes.len() < Self::SIZE || bytes[0] != NET_MAGIC[0] || bytes[1] != NET_MAGIC[1] || bytes[2] != NET_MAGIC[2] || bytes[3] != NET_MAGIC[3] {
+            return Self::LEGACY;
+        }
+
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version == 0 || version > NET_VERSION { panic!("Unsupported net version") }
+
+        let arch = match bytes[6] {
+            0 => Architecture::SingleLayer,
+            1 => Architecture::Deep,
+            _ => panic!("Unrecognized net architecture"),
+        };
+
+        let output_buckets = bytes[7] as usize;
+        if output_buckets == 0 || output_buckets > 32 { panic!("The number of output buckets must be within 1..=32") }
+
+        Self { version, arch, output_buckets }
+    }
+
+    /// The number of bytes taken by the header in the net file
+    pub(crate) const fn size(&self) -> usize {
+        if self.version == 0 { 0 } else { Self::SIZE }
+    }
+
+    /// Output buckets are selected by the number of pieces on the board (kings included),
+    /// e.g. with 8 buckets: 2..=5 pieces use bucket 0, ..., 30..=32 pieces use bucket 7
+    pub(crate) fn output_bucket(&self, pieces: u32) -> usize {
+        let divisor = 32usize.div_ceil(self.output_buckets);
+        ((pieces.saturating_sub(2) as usize) / divisor).min(self.output_buckets - 1)
+    }
+}
+
+
+#[cfg(test)]
+mod header_tests {
+    use super::*;
+
+    #[test]
+    fn should_treat_nets_without_the_magic_as_legacy_nets() {
+        let bytes = [12u8, 0, 255, 3, 1, 0, 1, 8, 4, 4];
+        let header = NetHeader::parse(&bytes);
+        assert_eq!(header, NetHeader::LEGACY);
+        assert_eq!(header.size(), 0);
+    }
+
+    #[test]
+    fn should_read_the_architecture_and_buckets_from_a_versioned_header() {
+        let bytes = [b'D', b'O', b'J', b'A', 1, 0, 1, 8, 99, 99];
+        let header = NetHeader::parse(&bytes);
+        assert_eq!(header, NetHeader { version: 1, arch: Architecture::Deep, output_buckets: 8 });
+        assert_eq!(header.size(), NetHeader::SIZE);
+    }
+
+    #[test]
+    fn should_select_output_buckets_by_piece_count() {
+        let header = NetHeader { version: 1, arch: Architecture::SingleLayer, output_buckets: 8 };
+        assert_eq!(header.output_bucket(2), 0);
+        assert_eq!(header.output_bucket(5), 0);
+        assert_eq!(header.output_bucket(6), 1);
+        assert_eq!(header.output_bucket(32), 7);
+        assert_eq!(NetHeader::LEGACY.output_bucket(32), 0);
+    }
+}
@@ -0,0 +1,200 @@
+use std::arch::x86_64::*;
+
+use super::align64::Align64;
+use super::constants::custom_kp::{L1_SIZE, L2_SIZE, L3_SIZE};
+use super::header::{Architecture, NetHeader};
+use super::relu::Crelu;
+
+
+/// The weights of the int8 layers are scaled by 2^6 (i.e. 64)
+pub(crate) const WEIGHT_SCALE_BITS: u32 = 6;
+/// Activations of the deep architecture are clipped to 0..=127, which represents 0.0..=1.0
+pub(crate) const QA_DEEP: i32 = 127;
+pub(crate) const QB_DEEP: i32 = 1 << WEIGHT_SCALE_BITS;
+
+
+/// Reads little-endian values from the net, one layer after the other
+struct NetReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> NetReader<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let value = self.bytes[self.offset..self.offset + N].try_into().expect("The net is smaller than its architecture");
+        self.offset += N;
+        value
+    }
+
+    fn i8s(&mut self, len: usize) -> Vec<i8> {
+        (0..len).map(|_| i8::from_le_bytes(self.take())).collect()
+    }
+
+    fn i16s(&mut self, len: usize) -> Vec<i16> {
+        (0..len).map(|_| i16::from_le_bytes(self.take())).collect()
+    }
+
+    fn i32s(&mut self, len: usize) -> Vec<i32> {
+        (0..len).map(|_| i32::from_le_bytes(self.take())).collect()
+    }
+}
+
+
+/// y = Wx + b, with int8 weights, and int32 biases.
+/// The weights are stored row by row, i.e. all the weights of the first output neuron come first
+#[derive(Debug, Clone)]
+pub(crate) struct AffineLayer {
+    weights: Vec<i8>,
+    biases: Vec<i32>,
+    inputs: usize,
+    outputs: usize,
+}
+
+impl AffineLayer {
+    fn read(reader: &mut NetReader, inputs: usize, outputs: usize) -> Self {
+        let weights = reader.i8s(inputs * outputs);
+        let biases = reader.i32s(outputs);
+        Self { weights, biases, inputs, outputs }
+    }
+
+    /// https://disservin.github.io/stockfish-docs/nnue-pytorch-wiki/docs/nnue.html#linear-layer-with-sparse-input
+    /// The inputs are the output of a ClippedReLU (0..=127), so they can safely be treated as u8 by maddubs
+    pub(crate) unsafe fn propagate(&self, input: &[i8], output: &mut [i32]) {
+        const REGISTER_WIDTH: usize = 256/8; // 32 i8 values
+        assert!(self.inputs.is_multiple_of(REGISTER_WIDTH), "We're processing 32 inputs at a time");
+        assert!(input.len() == self.inputs && output.len() == self.outputs);
+
+        let ones = _mm256_set1_epi16(1);
+
+        for (o, out) in output.iter_mut().enumerate() {
+            let row = self.weights.as_ptr().add(o * self.inputs);
+            let mut sum = _mm256_setzero_si256();
+
+            for i in (0..self.inputs).step_by(REGISTER_WIDTH) {
+                let data = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+                let weights = _mm256_loadu_si256(row.add(i) as *const __m256i);
+
+                // u8 * i8 -> (pairwise sum) i16 -> (pairwise sum) i32
+                // 127 * -128 * 2 still fits in an i16, so maddubs never saturates here
+                let product = _mm256_madd_epi16(_mm256_maddubs_epi16(data, weights), ones);
+                sum = _mm256_add_epi32(sum, product);
+            }
+
+            let lanes: [i32; 8] = std::mem::transmute(sum);
+            *out = self.biases[o] + lanes.iter().sum::<i32>();
+        }
+    }
+
+    /// Float reference of propagate, on dequantised inputs (0.0..=1.0) and weights
+    #[cfg(test)]
+    pub(crate) fn propagate_f32(&self, input: &[f32]) -> Vec<f32> {
+        (0..self.outputs).map(|o| {
+            let row = &self.weights[(o * self.inputs)..((o + 1) * self.inputs)];
+            let sum = input.iter().zip(row).map(|(&x, &w)| x * (w as f32 / QB_DEEP as f32)).sum::<f32>();
+            sum + self.biases[o] as f32 / (QA_DEEP * QB_DEEP) as f32
+        }).collect()
+    }
+}
+
+
+/// L1 -> L2 -> L3 -> 1
+#[derive(Debug, Clone)]
+pub(crate) struct LayerStack {
+    l1: AffineLayer,
+    l2: AffineLayer,
+    output: AffineLayer,
+}
+
+impl LayerStack {
+    fn read(reader: &mut NetReader) -> Self {
+        let l1 = AffineLayer::read(reader, L1_SIZE * 2, L2_SIZE);
+        let l2 = AffineLayer::read(reader, L2_SIZE, L3_SIZE);
+        let output = AffineLayer::read(reader, L3_SIZE, 1);
+        Self { l1, l2, output }
+    }
+
+    /// input: the clipped accumulator (side to move first)
+    /// Returns the raw output, which is scaled by QA_DEEP * QB_DEEP
+    pub(crate) unsafe fn forward(&self, input: &Align64<[i8; L1_SIZE * 2]>) -> i32 {
+        let mut l1_out = [0i32; L2_SIZE];
+        self.l1.propagate(&input[..], &mut l1_out);
+        let l1_out = Crelu::crelu32(L2_SIZE, l1_out.map(|x| x >> WEIGHT_SCALE_BITS));
+
+        let mut l2_out = [0i32; L3_SIZE];
+        self.l2.propagate(&l1_out, &mut l2_out);
+        let l2_out = Crelu::crelu32(L3_SIZE, l2_out.map(|x| x >> WEIGHT_SCALE_BITS));
+
+        let mut output = [0i32; 1];
+        self.output.propagate(&l2_out, &mut output);
+        output[0]
+    }
+
+    /// Float reference of forward, input: the dequantised and clipped accumulator (side to move first)
+    #[cfg(test)]
+    pub(crate) fn forward_f32(&self, input: &[f32]) -> f32 {
+        let clip = |v: Vec<f32>| v.into_iter().map(|x| x.clamp(0.0, 1.0)).collect::<Vec<_>>();
+
+        let l1_out = clip(self.l1.propagate_f32(input));
+        let l2_out = clip(self.l2.propagate_f32(&l1_out));
+        self.output.propagate_f32(&l2_out)[0]
+    }
+}
+
+
+/// Everything after the feature transformer, one set of weights per output bucket
+#[derive(Debug, Clone)]
+pub(crate) enum OutputLayers {
+    /// weights: [bucket][L1_SIZE * 2], biases: [bucket]
+    /// Legacy nets are a SingleLayer with one bucket
+    SingleLayer { weights: Vec<i16>, biases: Vec<i16> },
+    /// One layer stack per bucket
+    Deep(Vec<LayerStack>),
+}
+
+impl OutputLayers {
+    /// bytes: the section of the net right after the feature transformer
+    pub(crate) fn read(bytes: &[u8], header: &NetHeader) -> Self {
+        let buckets = header.output_buckets;
+        let mut reader = NetReader { bytes, offset: 0 };
+
+        match header.arch {
+            Architecture::SingleLayer => {
+                let weights = reader.i16s(L1_SIZE * 2 * buckets);
+                let biases = reader.i16s(buckets);
+                Self::SingleLayer { weights, biases }
+            }
+            Architecture::Deep => Self::Deep((0..buckets).map(|_| LayerStack::read(&mut reader)).collect()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod layers_tests {
+    use super::*;
+
+    #[test]
+    fn affine_layer_should_match_a_scalar_dot_product() {
+        let (inputs, outputs) = (64, 3);
+        let weights: Vec<i8> = (0..inputs * outputs).map(|i| ((i * 37) % 256) as u8 as i8).collect();
+        let biases = vec![-300, 0, 12_000];
+        let layer = AffineLayer { weights: weights.clone(), biases: biases.clone(), inputs, outputs };
+
+        let input: Vec<i8> = (0..inputs).map(|i| ((i * 11) % 128) as i8).collect();
+        let mut output = [0i32; 3];
+        unsafe { layer.propagate(&input, &mut output) };
+
+        for o in 0..outputs {
+            let expected = biases[o] + (0..inputs).map(|i| input[i] as i32 * weights[o * inputs + i] as i32).sum::<i32>();
+            assert_eq!(output[o], expected);
+        }
+    }
+
+    #[test]
+    fn crelu32_should_clip_every_chunk_into_the_i8_range() {
+        let input: [i32; 64] = std::array::from_fn(|i| (i as i32 - 20) * 7);
+        let output = unsafe { Crelu::crelu32(64, input) };
+        let expected: [i8; 64] = input.map(|x| x.clamp(0, 127) as i8);
+        assert_eq!(output, expected);
+    }
+}
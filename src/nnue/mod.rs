@@ -2,6 +2,9 @@
 
 use constants::custom_kp::*;
 use feature_idx::FeatureIdx;
+use header::NetHeader;
+use layers::OutputLayers;
+use lazy_static::lazy_static;
 use network::NNUEParams;
 use crate::color::Color::*;
 
@@ -20,20 +23,48 @@ pub(crate) mod network;
 pub(crate) mod relu;
 pub(crate) mod align64;
 pub(crate) mod accumulator_ptr;
+pub(crate) mod dirty_pieces;
+pub(crate) mod header;
+pub(crate) mod layers;
+pub(crate) mod trace;
+/// Only used by the tests, to validate the quantised evaluation
+#[cfg(test)]
+pub(crate) mod reference;
 
 // All layers are linear, and all hidden neurons use ClippedReLU activation function
 
 // HalfKP is just P taken 64 times, once for each king square
 
 
-pub(crate) static PARAMS: NNUEParams<{INPUT * L1_SIZE}, L1_SIZE, {L1_SIZE*2}, i16> = unsafe {
-    let bytes: &[u8] = include_bytes!("../../bin/net.bin");
+#[cfg(feature = "nnue")]
+pub(crate) static NET: &[u8] = include_bytes!("../../bin/net.bin");
+
+/// Builds without the `nnue` feature have no net file, they embed an all-zero legacy net that is never used to evaluate (see EMBEDDED_NET)
+#[cfg(not(feature = "nnue"))]
+pub(crate) static NET: &[u8] = &[0; std::mem::size_of::<NNUEParams<{INPUT*L1_SIZE}, L1_SIZE, i16>>() + (L1_SIZE * 2 + 1) * std::mem::size_of::<i16>()];
+
+/// Whether the build embeds a real net, the handcrafted evaluation is the only evaluation otherwise
+pub(crate) const EMBEDDED_NET: bool = cfg!(feature = "nnue");
+
+/// Parsed at compile time, nets without a header are read as legacy (768 -> 1024)x2 -> 1 nets
+pub(crate) static HEADER: NetHeader = NetHeader::parse(NET);
+
+/// The feature transformer (input -> L1), this is shared by every architecture
+pub(crate) static PARAMS: NNUEParams<{INPUT * L1_SIZE}, L1_SIZE, i16> = unsafe {
     /// Each of the 1024 neurons (L1_SIZE) in this layer has 768 weights connecting it to the input features
     /// Henece 768(input) * 1024(l1_size) = 786432 is the total number of connections(weights) between the input and this layer(hidden)
     const HIDDEN: usize = INPUT * L1_SIZE;
-    std::ptr::read_unaligned(bytes.as_ptr() as *const NNUEParams<{INPUT*L1_SIZE}, {L1_SIZE}, {L1_SIZE * 2}, i16>)
+    std::ptr::read_unaligned(NET.as_ptr().add(NetHeader::parse(NET).size()) as *const NNUEParams<{INPUT*L1_SIZE}, {L1_SIZE}, i16>)
 };
 
+lazy_static! {
+    /// The layers after the feature transformer, their shape depends on the architecture in the net header
+    pub(crate) static ref OUTPUT_LAYERS: OutputLayers = {
+        let offset = HEADER.size() + std::mem::size_of::<NNUEParams<{INPUT*L1_SIZE}, {L1_SIZE}, i16>>();
+        OutputLayers::read(&NET[offset..], &HEADER)
+    };
+}
+
 /// NNUE model is initialized from binary values
 // pub(crate) static MODEL: NNUEParamz = unsafe { std::mem::transmute(*include_bytes!("../../bin/net.bin")) };
 
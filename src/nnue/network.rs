@@ -3,32 +3,40 @@ use std::arch::x86_64::*;
 use std::{ptr, usize};
 
 
-use crate::board::{piece::Piece, state::board::Board};
+use crate::board::state::board::Board;
 use crate::color::{Color, Color::*};
-use crate::nnue::PARAMS;
-use crate::squares::Square;
+use crate::nnue::{HEADER, OUTPUT_LAYERS};
 
 use super::accumulator::{QA, QAB};
 use super::accumulator_ptr::AccumulatorPtr;
-use super::halfka_idx;
+use super::constants::custom_kp::KING_BUCKETS;
+use super::dirty_pieces::DirtyPieces;
+use super::layers::{OutputLayers, QA_DEEP, QB_DEEP};
 use super::{accumulator::Accumulator, accumulator::Feature, align64::Align64};
 
 pub(crate) const MAX_DEPTH: usize = 127;
 pub(crate) const SCALE: i32 = 400;
 
 
+/// The feature transformer, the layers after it are read separately (see OutputLayers)
 /// M: is input_size(768 for halfKPA) * l1_size(1024 for HalfKPA in this case)
 /// N: is L1_SIZE
-/// P: L1_SIZE * 2
 /// T: Expected type of the weight/bias
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
-pub(crate) struct NNUEParams<const M: usize, const N: usize, const P: usize, T: Copy> {
+pub(crate) struct NNUEParams<const M: usize, const N: usize, T: Copy> {
     pub(crate) input_weight: Align64<[T; M]>,
     pub(crate) input_bias: Align64<[T; N]>,
+}
+
 
-    pub(crate) output_weights: [i16; P],
-    pub(crate) output_bias: i16,
+/// Lazy update information about each accumulator on the stack
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct AccumulatorEntry {
+    /// Pieces changed by the move that led to this accumulator
+    dirty: DirtyPieces,
+    /// Whether the white, and black perspectives of this accumulator are up to date
+    computed: [bool; 2],
 }
 
 
@@ -36,6 +44,7 @@ pub(crate) struct NNUEParams<const M: usize, const N: usize, const P: usize, T:
 #[derive(Debug)]
 pub(crate) struct NNUEState<T, const U: usize> {
     accumulators: AccumulatorPtr<T, U>,
+    entries: [AccumulatorEntry; MAX_DEPTH + 1],
     current_acc: usize,
 }
 
@@ -49,6 +58,7 @@ impl<T, const U: usize>  NNUEState<T, U> {
 
         NNUEState {
             accumulators: AccumulatorPtr(ptr), // Initializer with raw pointer
+            entries: [AccumulatorEntry::default(); MAX_DEPTH + 1],
             current_acc: 0
         }
     }
@@ -65,7 +75,7 @@ impl<T, const U: usize> Clone for NNUEState<T, U> {
 
             std::ptr::copy_nonoverlapping(*self.accumulators, ptr, MAX_DEPTH + 1);
 
-            Self { accumulators: AccumulatorPtr(ptr), current_acc: self.current_acc }
+            Self { accumulators: AccumulatorPtr(ptr), entries: self.entries, current_acc: self.current_acc }
         }
     }
 }
@@ -93,6 +103,7 @@ impl<const U: usize> From<&Board> for NNUEState<Feature, U> {
             ptr::write(target, acc);
         }
         state.current_acc = 0;
+        state.entries[0].computed = [true; 2];
 
         
         state
@@ -105,24 +116,16 @@ impl<const U: usize> NNUEState<Feature, U> {
         self.current_acc -= 1;
     }
 
-    /// Increases the curr_acc index, and copies the previous accumualator to the new_idx
+    /// Increases the curr_acc index (null moves), the new accumulator is the same as the previous one,
+    /// and would only be computed(copied) if it is ever evaluated
     pub(crate) fn push(&mut self) {
-        unsafe {
-            *self.accumulators.add(self.current_acc + 1) = *self.accumulators.add(self.current_acc);
-            self.current_acc += 1;
-        }
+        self.update(DirtyPieces::default());
     }
 
-    pub(crate) fn update(&mut self, removed: Vec<(Piece, Square)>, added: Vec<(Piece, Square)>) {
-        unsafe {
-            let acc = *(self.accumulators.add(self.current_acc));
-            let added = added.into_iter().map(|(p, sq)| halfka_idx(p, sq)).collect::<Vec<_>>();
-            let removed = removed.into_iter().map(|(p, sq)| halfka_idx(p, sq)).collect::<Vec<_>>();
-            
-            let new_acc = acc.update(removed, added);
-            self.current_acc += 1;
-            *self.accumulators.add(self.current_acc) = new_acc;
-        }
+    /// Records the pieces changed by a move, the accumulator itself is only updated when this position is evaluated
+    pub(crate) fn update(&mut self, dirty: DirtyPieces) {
+        self.current_acc += 1;
+        self.entries[self.current_acc] = AccumulatorEntry { dirty, computed: [false; 2] };
     }
 
     pub(crate) fn refresh(&mut self, board: &Board) {
@@ -131,14 +134,45 @@ impl<const U: usize> NNUEState<Feature, U> {
             self.current_acc = 0;
             *self.accumulators.add(self.current_acc) = acc;
         }
+        self.entries[0] = AccumulatorEntry { dirty: DirtyPieces::default(), computed: [true; 2] };
+    }
+
+    /// Brings the `color` perspective of the current accumulator up to date.
+    /// We walk back to the last accumulator where this perspective was computed, and apply the pending updates forward.
+    /// If the king of this perspective moved on the way, we refresh this perspective from the board instead, the other perspective is not affected.
+    fn materialize(&mut self, board: &Board, color: Color) {
+        let curr = self.current_acc;
+        if self.entries[curr].computed[color] { return }
+
+        let mut last = curr;
+        let mut king_moved = false;
+        while !self.entries[last].computed[color] {
+            king_moved |= self.entries[last].dirty.king_moved() == Some(color);
+            last -= 1; // the root accumulator is always computed
+        }
+
+        unsafe {
+            if king_moved && KING_BUCKETS {
+                (*self.accumulators.add(curr)).refresh_perspective(board, color);
+                self.entries[curr].computed[color] = true;
+                return;
+            }
+
+            for idx in (last + 1)..=curr {
+                let prev = &*self.accumulators.add(idx - 1);
+                (*self.accumulators.add(idx)).update_perspective(prev, color, &self.entries[idx].dirty);
+                self.entries[idx].computed[color] = true;
+            }
+        }
     }
 
     /// Register size for AVX2
     pub(crate) const REGISTER_WIDTH: usize = 256/16; 
     
     /// The input here are 16 *i16s per m156i 
-    pub(crate) unsafe fn propagate(inputs: [Align64<[Feature; U]>; 2], stm: &Color) -> i32 {
-        assert!(U%16 == 0, "We're ecpecting i16 values");        
+    /// weights: the output weights of the selected bucket (side to move first)
+    pub(crate) unsafe fn propagate(inputs: [Align64<[Feature; U]>; 2], stm: &Color, weights: &[i16]) -> i32 {
+        assert!(U.is_multiple_of(16), "We're ecpecting i16 values");        
         let mut output: i32 = 0;
 
         let colors = match stm {
@@ -150,17 +184,22 @@ impl<const U: usize> NNUEState<Feature, U> {
 
         for color in colors {
             for i in 0..U {
-                let w_idx = ((color as usize) * (PARAMS.output_weights.len()/2)) + (i * Self::REGISTER_WIDTH);
+                let w_idx = ((color as usize) * (weights.len()/2)) + (i * Self::REGISTER_WIDTH);
 
-                let data = _mm256_load_si256(inputs[color].as_ptr().add(i) as *const __m256i);
-                let weights = _mm256_load_si256(PARAMS.output_weights.as_ptr().add(w_idx) as *const __m256i);
+                let data = _mm256_load_si256(inputs[color].as_ptr().add(i));
+                let weights = _mm256_loadu_si256(weights.as_ptr().add(w_idx) as *const __m256i);
 
                 let datalo = _mm256_cvtepi16_epi32(_mm256_castsi256_si128(data));
                 let multiplier_lo = _mm256_cvtepi16_epi32(_mm256_castsi256_si128(weights));
 
                 let datahi = _mm256_cvtepi16_epi32(_mm256_extracti128_si256(data, 1));
                 let multiplier_hi = _mm256_cvtepi16_epi32(_mm256_extracti128_si256(weights, 1));
+
+                // SCReLU: the clamped values (0..=255) are squared here, as i32
+                let datalo = _mm256_mullo_epi32(datalo, datalo);
+                let datahi = _mm256_mullo_epi32(datahi, datahi);
              
+                // 255 * 255 * weight can overflow an i32 for large weights, see reference::QuantisationReport
                 let result_lo = _mm256_mullo_epi32(datalo, multiplier_lo);
                 let result_hi = _mm256_mullo_epi32(datahi, multiplier_hi);
                 
@@ -174,15 +213,29 @@ impl<const U: usize> NNUEState<Feature, U> {
         output
     }
 
-    pub(crate) fn evaluate(&self, stm: Color) -> i32 {
+    pub(crate) fn evaluate(&mut self, board: &Board) -> i32 {
+        let stm = board.turn;
+        self.materialize(board, White);
+        self.materialize(board, Black);
+
+        let bucket = HEADER.output_bucket(board.occupancies[Both].count_ones());
+
         unsafe {
             let acc = self.accumulators.add(self.current_acc);
-            
-            let clipped_acc = (*acc).sq_crelu16(stm); // [i16; 16]
-            let output = Self::propagate(clipped_acc, &stm);
 
-            let abc = (output / (QA as i32) + PARAMS.output_bias as i32) * SCALE / QAB;
-            return abc;
+            match &*OUTPUT_LAYERS {
+                OutputLayers::SingleLayer { weights, biases } => {
+                    let size = weights.len() / biases.len();
+                    let clipped_acc = (*acc).sq_crelu16(stm); // [i16; 16]
+                    let output = Self::propagate(clipped_acc, &stm, &weights[(bucket * size)..((bucket + 1) * size)]);
+
+                    (output / (QA as i32) + biases[bucket] as i32) * SCALE / QAB
+                }
+                OutputLayers::Deep(stacks) => {
+                    let output = stacks[bucket].forward(&(*acc).crelu16(stm));
+                    output * SCALE / (QA_DEEP * QB_DEEP)
+                }
+            }
         }
     }
 }
\ No newline at end of file
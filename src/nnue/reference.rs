@@ -0,0 +1,174 @@
+use std::fmt::Display;
+
+use crate::board::{piece::Piece, position::ACCUMULATOR_SIZE, state::board::Board};
+use crate::color::Color::*;
+use crate::squares::Square;
+
+use super::accumulator::{Accumulator, Feature, QA, QAB};
+use super::constants::custom_kp::L1_SIZE;
+use super::layers::{OutputLayers, QA_DEEP};
+use super::network::{NNUEState, SCALE};
+use super::{halfka_idx, HEADER, OUTPUT_LAYERS, PARAMS};
+
+
+/// The weights of the (single layer) output are scaled by QB
+const QB: i32 = QAB / QA as i32;
+
+
+/// The accumulator of both perspectives (white, black) as f32, i.e. without the i16 wrapping of the quantised path.
+/// The values are still in quantised units (QA)
+fn accumulate(board: &Board) -> [Vec<f32>; 2] {
+    let bias = PARAMS.input_bias.iter().map(|&b| b as f32).collect::<Vec<_>>();
+    let mut acc = [bias.clone(), bias];
+
+    for (p, bitboard) in (*board.board).into_iter().enumerate() {
+        let mut sqs = *bitboard;
+        while sqs != 0 {
+            let (white, black) = halfka_idx(Piece::from(p as u8), Square::from(sqs.trailing_zeros() as u64));
+            sqs &= sqs - 1;
+
+            for i in 0..L1_SIZE {
+                acc[0][i] += PARAMS.input_weight[*white + i] as f32;
+                acc[1][i] += PARAMS.input_weight[*black + i] as f32;
+            }
+        }
+    }
+
+    acc
+}
+
+/// Float32 forward pass over the same (dequantised) weights as NNUEState::evaluate.
+/// This is slow, and is only meant to validate the quantised (AVX2) path. Returns centipawns from the side to move's perspective
+pub(crate) fn evaluate_f32(board: &Board) -> f32 {
+    let [white, black] = accumulate(board);
+    let (us, them) = if board.turn == White { (white, black) } else { (black, white) };
+    let bucket = HEADER.output_bucket(board.occupancies[Both].count_ones());
+
+    match &*OUTPUT_LAYERS {
+        OutputLayers::SingleLayer { weights, biases } => {
+            let size = weights.len() / biases.len();
+            let weights = &weights[(bucket * size)..((bucket + 1) * size)];
+            let screlu = |x: f32| (x / QA as f32).clamp(0.0, 1.0).powi(2);
+
+            let output = us.iter().chain(them.iter()).zip(weights).map(|(&x, &w)| screlu(x) * (w as f32 / QB as f32)).sum::<f32>();
+            (output + biases[bucket] as f32 / QAB as f32) * SCALE as f32
+        }
+        OutputLayers::Deep(stacks) => {
+            let input = us.iter().chain(them.iter()).map(|&x| (x / QA_DEEP as f32).clamp(0.0, 1.0)).collect::<Vec<_>>();
+            stacks[bucket].forward_f32(&input) * SCALE as f32
+        }
+    }
+}
+
+/// The output of NNUEState::propagate (i32), and the same sum computed exactly (i64).
+/// Both only differ when the i32 accumulation (_mm256_mullo_epi32) overflows.
+/// This only applies to the single layer architecture, the int8 layers of the deep architecture cannot overflow
+fn propagate_exact(board: &Board) -> Option<(i32, i64)> {
+    let OutputLayers::SingleLayer { weights, biases } = &*OUTPUT_LAYERS else { return None };
+    let size = weights.len() / biases.len();
+    let bucket = HEADER.output_bucket(board.occupancies[Both].count_ones());
+    let weights = &weights[(bucket * size)..((bucket + 1) * size)];
+
+    unsafe {
+        let clipped = Accumulator::<Feature, ACCUMULATOR_SIZE>::refresh(board).sq_crelu16(board.turn);
+        let simd = NNUEState::<Feature, ACCUMULATOR_SIZE>::propagate(clipped, &board.turn, weights);
+
+        let mut exact = 0i64;
+        for (k, perspective) in clipped.iter().enumerate() {
+            let values = std::ptr::read(perspective.as_ptr() as *const [i16; L1_SIZE]);
+            for (i, &v) in values.iter().enumerate() {
+                exact += (v as i64) * (v as i64) * (weights[k * L1_SIZE + i] as i64);
+            }
+        }
+
+        Some((simd, exact))
+    }
+}
+
+
+/// Compares the quantised evaluation with the float reference over a corpus of positions
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QuantisationReport {
+    pub(crate) positions: usize,
+    pub(crate) max_error: f32,
+    pub(crate) mean_error: f32,
+    /// The position with the largest error
+    pub(crate) worst: Option<String>,
+    /// Positions where the i32 accumulation in propagate overflowed
+    pub(crate) overflows: Vec<String>,
+}
+
+impl QuantisationReport {
+    pub(crate) fn new<'a>(fens: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut report = Self::default();
+        let mut total_error = 0.0;
+
+        for fen in fens {
+            let Ok(board) = Board::try_from(fen) else { continue };
+
+            let quantised = NNUEState::<Feature, ACCUMULATOR_SIZE>::from(&board).evaluate(&board) as f32;
+            let error = (quantised - evaluate_f32(&board)).abs();
+
+            if error > report.max_error || report.worst.is_none() {
+                report.max_error = error;
+                report.worst = Some(fen.trim().to_string());
+            }
+            if propagate_exact(&board).is_some_and(|(simd, exact)| simd as i64 != exact) {
+                report.overflows.push(fen.trim().to_string());
+            }
+
+            total_error += error;
+            report.positions += 1;
+        }
+
+        if report.positions != 0 { report.mean_error = total_error / report.positions as f32; }
+        report
+    }
+}
+
+impl Display for QuantisationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "positions:  {}", self.positions)?;
+        writeln!(f, "max error:  {:.3}cp ({})", self.max_error, self.worst.as_deref().unwrap_or("-"))?;
+        writeln!(f, "mean error: {:.3}cp", self.mean_error)?;
+        writeln!(f, "overflows:  {}", self.overflows.len())?;
+        for fen in &self.overflows {
+            writeln!(f, "    {fen}")?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod reference_tests {
+    use super::*;
+    use crate::constants::{CMK_POSITION, START_POSITION, TRICKY_POSITION};
+
+    const CORPUS: [&str; 8] = [
+        START_POSITION,
+        TRICKY_POSITION,
+        CMK_POSITION,
+        "4kb2/3p1n2/1r2p3/3b1p1p/PpN5/8/BP1P3P/R2QK3 b -Q-- - 0 2",
+        "rnbqk1nr/p2p3p/4p3/8/Pp1P1B2/6Pp/1PP2P1P/R2QKB1R b KQkq a3 0 1",
+        "r3k1n1/4p1pp/8/1p1p1Q2/P2p1N2/4P3/5P1P/R3KBNR w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "8/8/4k3/8/2B5/8/4K3/3N4 b - - 0 1",
+    ];
+
+    /// Set NNUE_FEN_CORPUS to a file (one FEN per line), to run the comparison over a bigger corpus
+    #[test]
+    fn quantised_evaluation_should_match_the_float_reference() {
+        let corpus = std::env::var("NNUE_FEN_CORPUS").ok().and_then(|path| std::fs::read_to_string(path).ok());
+        let report = match &corpus {
+            Some(fens) => QuantisationReport::new(fens.lines()),
+            None => QuantisationReport::new(CORPUS),
+        };
+        println!("{report}");
+
+        assert!(report.positions > 0);
+        assert!(report.overflows.is_empty(), "i32 overflow in propagate:\n{report}");
+        // The quantised path only loses precision to integer division, which is at most ~1cp
+        assert!(report.max_error < 2.0, "{report}");
+    }
+}
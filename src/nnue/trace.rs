@@ -0,0 +1,150 @@
+use std::fmt::Display;
+
+use crate::board::{piece::Piece, position::Position, state::board::Board};
+use crate::color::Color::*;
+use crate::game_phase::GamePhase;
+use crate::search::evaluation::Evaluation;
+use crate::squares::Square;
+
+use super::accumulator::Feature;
+use super::network::NNUEState;
+use super::HEADER;
+
+
+/// A breakdown of the static evaluation of a position (UCI `eval` command).
+/// All scores here are in centipawns, and from white's perspective
+#[derive(Debug, Clone)]
+pub(crate) struct EvalTrace {
+    /// The output of the net
+    nnue: i32,
+    /// The output of the net, after the material scaling used by the search (Position::evaluate)
+    scaled: i32,
+    /// Handcrafted evaluation (Evaluation::evaluate)
+    hce: i32,
+    /// How much the net's output drops when each piece is removed (kings are never removed)
+    contributions: [Option<(Piece, Option<i32>)>; 64],
+    /// The material of white and black (not counting the kings)
+    material: [i32; 2],
+    phase_score: i32,
+    bucket: usize,
+}
+
+impl EvalTrace {
+    pub(crate) fn new(board: &Board) -> Self {
+        let white = |score: i32| if board.turn == White { score } else { -score };
+
+        let nnue = white(Self::nnue(board));
+        let scaled = white(Position::with(*board).evaluate());
+        let hce = white(Evaluation::evaluate(board));
+
+        let mut contributions = [None; 64];
+        let mut material = [0; 2];
+
+        for (p, bitboard) in (*board.board).into_iter().enumerate() {
+            let piece = Piece::from(p as u8);
+            let mut sqs = *bitboard;
+
+            while sqs != 0 {
+                let sq = Square::from(sqs.trailing_zeros() as u64);
+                sqs &= sqs - 1;
+
+                material[piece.color()] += piece.piece_value();
+                if piece == Piece::king(piece.color()) {
+                    contributions[sq as usize] = Some((piece, None));
+                    continue;
+                }
+
+                // The accumulator is refreshed (not updated) for the board without this piece
+                let mut without = *board;
+                without.remove_piece(piece, sq);
+                contributions[sq as usize] = Some((piece, Some(nnue - white(Self::nnue(&without)))));
+            }
+        }
+
+        let phase_score = Evaluation::get_game_phase_score(board);
+        let bucket = HEADER.output_bucket(board.occupancies[Both].count_ones());
+
+        Self { nnue, scaled, hce, contributions, material, phase_score, bucket }
+    }
+
+    /// The raw output of the net (side to move's perspective)
+    fn nnue(board: &Board) -> i32 {
+        NNUEState::<Feature, { crate::board::position::ACCUMULATOR_SIZE }>::from(board).evaluate(board)
+    }
+
+    fn pawns(cp: i32) -> String {
+        format!("{:+.2}", cp as f64 / 100.0)
+    }
+}
+
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const SEPARATOR: &str = "+-------+-------+-------+-------+-------+-------+-------+-------+";
+
+        writeln!(f, "NNUE derived piece values (in pawns, white's perspective):")?;
+        writeln!(f, "{SEPARATOR}")?;
+        for rank in (0..8).rev() {
+            let mut pieces = String::from("|");
+            let mut values = String::from("|");
+
+            for file in 0..8 {
+                let (piece, value) = match self.contributions[rank * 8 + file] {
+                    Some((piece, Some(value))) => (piece.to_string(), Self::pawns(value)),
+                    Some((piece, None)) => (piece.to_string(), String::new()),
+                    None => (String::new(), String::new()),
+                };
+                pieces.push_str(&format!("{piece:^7}|"));
+                values.push_str(&format!("{value:^7}|"));
+            }
+
+            writeln!(f, "{pieces}")?;
+            writeln!(f, "{values}")?;
+            writeln!(f, "{SEPARATOR}")?;
+        }
+
+        let phase = match GamePhase::from(self.phase_score) {
+            GamePhase::Opening => "opening",
+            GamePhase::MiddleGame => "middlegame",
+            GamePhase::EndGame => "endgame",
+        };
+
+        writeln!(f)?;
+        writeln!(f, "Material:          white {}, black {}, difference {}", self.material[White], self.material[Black], self.material[White] - self.material[Black])?;
+        writeln!(f, "Phase:             {phase} ({})", self.phase_score)?;
+        writeln!(f, "Output bucket:     {}", self.bucket)?;
+        writeln!(f)?;
+        writeln!(f, "NNUE evaluation:   {} (white side)", Self::pawns(self.nnue))?;
+        writeln!(f, "Scaled evaluation: {} (white side)", Self::pawns(self.scaled))?;
+        writeln!(f, "HCE evaluation:    {} (white side)", Self::pawns(self.hce))
+    }
+}
+
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+    use crate::constants::TRICKY_POSITION;
+    use crate::squares::Square::*;
+
+    #[test]
+    fn should_trace_every_piece_on_the_board() {
+        let board = Board::try_from(TRICKY_POSITION).unwrap();
+        let trace = EvalTrace::new(&board);
+
+        let traced = trace.contributions.iter().filter(|c| c.is_some()).count() as u32;
+        assert_eq!(traced, board.occupancies[Both].count_ones());
+        assert!(matches!(trace.contributions[E1 as usize], Some((Piece::WK, None))));
+        assert!(matches!(trace.contributions[A1 as usize], Some((Piece::WR, Some(_)))));
+        assert_eq!(trace.material[White], trace.material[Black]);
+    }
+
+    #[test]
+    fn should_report_scores_from_whites_perspective() {
+        let white = EvalTrace::new(&Board::try_from(TRICKY_POSITION).unwrap());
+        let black = EvalTrace::new(&Board::try_from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1").unwrap());
+
+        // same pieces, only the side to move differs
+        assert_eq!(white.hce, black.hce);
+        assert_eq!(white.material, black.material);
+    }
+}
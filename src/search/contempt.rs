@@ -0,0 +1,43 @@
+/// A contempt factor is another name for a draw score: with a positive contempt, the root side scores draws below 0 and avoids them,
+/// and with a negative one it steers towards them (e.g. against a stronger opponent).
+/// https://www.chessprogramming.org/Contempt_Factor
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Contempt {
+    /// In centipawns, from the root side's point of view
+    pub(crate) value: i32,
+    /// Also scale the contempt with the root evaluation: a side that is better wants to avoid draws more, and a side that is worse less
+    pub(crate) dynamic: bool,
+}
+
+impl Contempt {
+    pub(crate) const MAX: i32 = 100;
+    /// The most the dynamic part adds (or removes) for a clearly winning (or losing) root
+    const DYNAMIC_MAX: i32 = 88;
+
+    /// The contempt of the root side, when the root is evaluated at `eval` (the score of the previous iteration)
+    pub(crate) fn at(&self, eval: i32) -> i32 {
+        match self.dynamic {
+            true => self.value + Self::DYNAMIC_MAX * eval / (eval.abs() + 200),
+            false => self.value,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod contempt_tests {
+    use super::*;
+
+    #[test]
+    fn dynamic_contempt_should_follow_the_root_evaluation() {
+        let contempt = Contempt { value: 20, dynamic: false };
+        assert_eq!(contempt.at(300), 20);
+
+        let contempt = Contempt { value: 20, dynamic: true };
+        assert_eq!(contempt.at(0), 20);
+        assert_eq!(contempt.at(200), 64);
+        assert_eq!(contempt.at(-200), -24);
+        // bounded, even for mate scores
+        assert!(contempt.at(32_000) < 20 + Contempt::DYNAMIC_MAX);
+    }
+}
@@ -1,201 +1,280 @@
-use crate::{board::{state::board::Board, piece::Piece}, color::Color, constants::{BISHOP_MOBILITY_ENDGAME, BISHOP_MOBILITY_OPENING, BISHOP_UNIT, DOUBLE_PAWN_PENALTY_ENDGAME, DOUBLE_PAWN_PENALTY_OPENING, EVAL_MASKS, ISOLATED_PAWN_PENALTY_ENDGAME, ISOLATED_PAWN_PENALTY_OPENING, KING_SHIELD_BONUS, MATERIAL_SCORE, MIRROR_SCORE, OPENING_PHASE_SCORE, OPEN_FILE_SCORE, PASSED_PAWN_BONUS, PIECE_ATTACKS, PLAYER_PIECES, POSITIONAL_SCORES, QUEEN_MOBILITY_ENDGAME, QUEEN_MOBILITY_OPENING, QUEEN_UNIT, SEMI_OPEN_FILE_SCORE}, game_phase::GamePhase, squares::Square};
+use std::{fmt::Display, ops::{Add, AddAssign, Mul}};
+
+use crate::{endgame, board::{state::board::Board, piece::Piece}, color::Color, constants::{EVAL_MASKS, MATERIAL_SCORE, NOT_A_FILE, NOT_H_FILE, PIECE_ATTACKS}, game_phase::GamePhase};
+
+use super::hce_weights::*;
+
+
+/// A (middle game, end game) pair of scores
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct S(pub(crate) i32, pub(crate) i32);
+
+impl Add for S {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output { S(self.0 + rhs.0, self.1 + rhs.1) }
+}
+
+impl AddAssign for S {
+    fn add_assign(&mut self, rhs: Self) { *self = *self + rhs }
+}
+
+impl Mul<i32> for S {
+    type Output = Self;
+    fn mul(self, rhs: i32) -> Self::Output { S(self.0 * rhs, self.1 * rhs) }
+}
+
+impl Display for S {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "S({:>4}, {:>4})", self.0, self.1)
+    }
+}
+
+
+/// The offsets of every weight of the evaluation in a flat list of terms (WEIGHTS), which is what the Texel tuner optimizes
+pub(crate) mod terms {
+    pub(crate) const MATERIAL: usize = 0;
+    pub(crate) const PSQT: usize = MATERIAL + 5;
+    pub(crate) const KNIGHT_MOBILITY: usize = PSQT + 6 * 64;
+    pub(crate) const BISHOP_MOBILITY: usize = KNIGHT_MOBILITY + 9;
+    pub(crate) const ROOK_MOBILITY: usize = BISHOP_MOBILITY + 14;
+    pub(crate) const QUEEN_MOBILITY: usize = ROOK_MOBILITY + 15;
+    pub(crate) const DOUBLE_PAWN: usize = QUEEN_MOBILITY + 28;
+    pub(crate) const ISOLATED_PAWN: usize = DOUBLE_PAWN + 1;
+    pub(crate) const PASSED_PAWN: usize = ISOLATED_PAWN + 1;
+    pub(crate) const PASSED_BLOCKED: usize = PASSED_PAWN + 8;
+    pub(crate) const ROOK_OPEN_FILE: usize = PASSED_BLOCKED + 8;
+    pub(crate) const ROOK_SEMI_OPEN_FILE: usize = ROOK_OPEN_FILE + 1;
+    pub(crate) const ROOK_ON_SEVENTH: usize = ROOK_SEMI_OPEN_FILE + 1;
+    pub(crate) const KING_OPEN_FILE: usize = ROOK_ON_SEVENTH + 1;
+    pub(crate) const KING_SEMI_OPEN_FILE: usize = KING_OPEN_FILE + 1;
+    pub(crate) const KING_SHIELD: usize = KING_SEMI_OPEN_FILE + 1;
+    pub(crate) const KING_ATTACK: usize = KING_SHIELD + 1;
+    pub(crate) const BISHOP_PAIR: usize = KING_ATTACK + 4;
+    pub(crate) const THREAT_BY_PAWN: usize = BISHOP_PAIR + 1;
+    pub(crate) const THREAT_BY_MINOR: usize = THREAT_BY_PAWN + 1;
+    pub(crate) const THREAT_BY_ROOK: usize = THREAT_BY_MINOR + 1;
+    pub(crate) const COUNT: usize = THREAT_BY_ROOK + 1;
+
+    /// (offset, table size) of the mobility tables of knights, bishops, rooks and queens
+    pub(crate) const MOBILITY: [(usize, usize); 4] = [(KNIGHT_MOBILITY, 9), (BISHOP_MOBILITY, 14), (ROOK_MOBILITY, 15), (QUEEN_MOBILITY, 28)];
+}
+
+/// Every weight of search::hce_weights, at its offset in `terms`
+pub(crate) const WEIGHTS: [S; terms::COUNT] = {
+    let mut weights = [S(0, 0); terms::COUNT];
+
+    macro_rules! place {
+        ($($offset:expr => $table:expr),* $(,)?) => {$(
+            let table = $table;
+            let mut i = 0;
+            while i < table.len() { weights[$offset + i] = table[i]; i += 1; }
+        )*};
+    }
+
+    place! {
+        terms::MATERIAL => MATERIAL,
+        terms::PSQT => PSQT[0], terms::PSQT + 64 => PSQT[1], terms::PSQT + 128 => PSQT[2],
+        terms::PSQT + 192 => PSQT[3], terms::PSQT + 256 => PSQT[4], terms::PSQT + 320 => PSQT[5],
+        terms::KNIGHT_MOBILITY => KNIGHT_MOBILITY, terms::BISHOP_MOBILITY => BISHOP_MOBILITY,
+        terms::ROOK_MOBILITY => ROOK_MOBILITY, terms::QUEEN_MOBILITY => QUEEN_MOBILITY,
+        terms::DOUBLE_PAWN => [DOUBLE_PAWN], terms::ISOLATED_PAWN => [ISOLATED_PAWN],
+        terms::PASSED_PAWN => PASSED_PAWN, terms::PASSED_BLOCKED => PASSED_BLOCKED,
+        terms::ROOK_OPEN_FILE => [ROOK_OPEN_FILE], terms::ROOK_SEMI_OPEN_FILE => [ROOK_SEMI_OPEN_FILE], terms::ROOK_ON_SEVENTH => [ROOK_ON_SEVENTH],
+        terms::KING_OPEN_FILE => [KING_OPEN_FILE], terms::KING_SEMI_OPEN_FILE => [KING_SEMI_OPEN_FILE], terms::KING_SHIELD => [KING_SHIELD],
+        terms::KING_ATTACK => KING_ATTACK, terms::BISHOP_PAIR => [BISHOP_PAIR],
+        terms::THREAT_BY_PAWN => [THREAT_BY_PAWN], terms::THREAT_BY_MINOR => [THREAT_BY_MINOR], terms::THREAT_BY_ROOK => [THREAT_BY_ROOK],
+    }
+
+    weights
+};
+
+
+/// Receives every term of the evaluation, with how many times it counts for white (negative counts are for black).
+/// The evaluation is linear in its weights, so these counts are all the Texel tuner needs
+pub(crate) trait Trace {
+    fn add(&mut self, term: usize, count: i32);
+}
+
+/// The search does not trace its evaluations
+impl Trace for () {
+    #[inline(always)]
+    fn add(&mut self, _term: usize, _count: i32) {}
+}
+
+/// A dense list of counts, indexed by term
+impl Trace for Vec<i32> {
+    fn add(&mut self, term: usize, count: i32) {
+        self[term] += count;
+    }
+}
+
 
 pub(crate) struct Evaluation;
 
 impl Evaluation {
+    /// The phase of a position with all the pieces on the board (knights and bishops count 1, rooks 2, and queens 4)
+    pub(crate) const MAX_PHASE: i32 = 24;
+
     pub(crate) fn evaluate(board: &Board) -> i32 {
-        let (mut score_opening, mut score_endgame): (i32, i32) = (0, 0);
+        let score = Self::taper(Self::score(board, &mut ()), Self::phase(board));
 
-        let game_phase_score = Self::get_game_phase_score(board);
-        let game_phase = GamePhase::from(game_phase_score);
+        let score = match board.turn {
+            Color::White => score,
+            _ => -score
+        };
+        endgame::evaluate(board, score)
+    }
 
-        for piece in Piece::ascii_pieces() {
-            let mut bitboard = board[piece];
+    /// The (untapered) white relative score of the position, every term that makes it up is also sent to the trace.
+    /// Both sides are evaluated by the same code, from their own perspective, so the evaluation is symmetric
+    pub(crate) fn score<T: Trace>(board: &Board, trace: &mut T) -> S {
+        let mut score = S::default();
+        let mut add = |term: usize, count: i32| {
+            score += WEIGHTS[term] * count;
+            trace.add(term, count);
+        };
 
-            
-            while bitboard.not_zero() {
-                let square = Square::from(bitboard.get_lsb1().unwrap());
-                let mirror_index = MIRROR_SCORE[square];
+        let occupancy = board.occupancies[Color::Both];
+        let pawn_attacks = [Self::pawn_attacks(*board[Piece::WP], Color::White), Self::pawn_attacks(*board[Piece::BP], Color::Black)];
 
-                // interpolate scores in middle game
-                // (score_opening * game_phase_score + score_endgame * (opening_phase_score - game_phase_score))/opening_phase_score
-                // e.g. the score for pawn on d4 at phase say 5000 would be:
-                // interpolated_score = (12 * 5000 + (-7) * (6192 - 5000)/6192 = 8,342377261
-                let piece_index = (piece as usize) % PLAYER_PIECES;
+        for color in [Color::White, Color::Black] {
+            let sign = if color == Color::White { 1 } else { -1 };
+            let mut add = |term: usize, count: i32| add(term, sign * count);
 
-                score_opening += MATERIAL_SCORE[GamePhase::Opening][piece];
-                score_endgame += MATERIAL_SCORE[GamePhase::EndGame][piece];
+            let (ours, theirs) = (board.occupancies[color], board.occupancies[!color]);
+            let (our_pawns, their_pawns) = (*board[Piece::pawn(color)], *board[Piece::pawn(!color)]);
+            let their_queens = *board[Piece::from(Piece::WQ as u8 + 6 * !color as u8)];
+            let their_rooks = *board[Piece::from(Piece::WR as u8 + 6 * !color as u8)];
 
+            let their_king = board[Piece::king(!color)].trailing_zeros() as usize;
+            let king_zone = PIECE_ATTACKS.king_attacks[their_king] | (1 << their_king);
+            // squares that are neither ours, nor defended by their pawns
+            let safe = !ours & !pawn_attacks[!color];
 
-                match piece {
-                    Piece::WP | Piece::WN | Piece::WR | Piece::WB | Piece::WQ | Piece::WK => {
-                        score_opening += POSITIONAL_SCORES[GamePhase::Opening][piece_index][mirror_index];
-                        score_endgame  += POSITIONAL_SCORES[GamePhase::EndGame][piece_index][mirror_index];
-                    }
-                    Piece::BP | Piece::BN | Piece::BR | Piece::BB | Piece::BQ | Piece::BK  => {
-                        score_opening -= POSITIONAL_SCORES[GamePhase::Opening][piece_index][square];
-                        score_endgame  -= POSITIONAL_SCORES[GamePhase::EndGame][piece_index][square];
-                    }
-                }
+            let mut attackers = 0;
+            let mut attack_units = [0; 4];
 
-                match piece {
-                    // we're using this order because our board is inverted i.e. starts from the left --> to right, and then bottom --> up
-                    Piece::WP => {
-                        let double_pawns = (*board[piece] & EVAL_MASKS.file_masks[mirror_index]).count_ones() as i32;
-                        // double pawn penalty
-                        if double_pawns > 1 {
-                            score_opening += (double_pawns - 1) * DOUBLE_PAWN_PENALTY_OPENING;
-                            score_endgame += (double_pawns - 1) * DOUBLE_PAWN_PENALTY_ENDGAME;
-                        }
+            for kind in 0..6 {
+                let mut pieces = *board[Piece::from(kind as u8 + 6 * color as u8)];
 
-                        // isolated pawn
-                        if *board[piece] & EVAL_MASKS.isolated_masks[mirror_index] == 0 {
-                            score_opening += ISOLATED_PAWN_PENALTY_OPENING;
-                            score_endgame += ISOLATED_PAWN_PENALTY_ENDGAME;
-                        }
+                while pieces != 0 {
+                    let sq = pieces.trailing_zeros() as usize;
+                    pieces &= pieces - 1;
+                    // the square from our side of the board (a8 is 0 in the psqt)
+                    let relative = if color == Color::White { sq } else { sq ^ 56 };
 
-                        // passed pawn bonus
-                        // if EVAL_MASKS.white_passed_masks[square] & *board[piece] == 0 {
-                        if EVAL_MASKS.white_passed_masks[square] & *board[Piece::BP] == 0 {
-                            // give passed pawn bonus
-                            score_opening += PASSED_PAWN_BONUS[Square::from(mirror_index).rank()] as i32;
-                            score_endgame += PASSED_PAWN_BONUS[Square::from(mirror_index).rank()] as i32;
-                        }
-                    },
-                    Piece::WN => {},
-                    Piece::WB => {
-                        let bishop = PIECE_ATTACKS.get_bishop_attacks(square.into(), board.get_occupancy(Color::Both)).count_ones() as i32 - BISHOP_UNIT;
-                        // mobility
-                        score_opening += bishop * BISHOP_MOBILITY_OPENING;
-                        score_endgame += bishop * BISHOP_MOBILITY_ENDGAME;
-                    },
-                    Piece::WR => {
-                        // semi open file bonus
-                        if *board[Piece::WP] & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening += SEMI_OPEN_FILE_SCORE;
-                            score_endgame += SEMI_OPEN_FILE_SCORE;
-                        }
-                        
-                        // open file bonus
-                        if (*board[Piece::WP] | *board[Piece::BP]) & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening += OPEN_FILE_SCORE;
-                            score_endgame += OPEN_FILE_SCORE;
+                    if kind < 5 { add(terms::MATERIAL + kind, 1) }
+                    add(terms::PSQT + kind * 64 + (relative ^ 56), 1);
+
+                    let attacks = match kind {
+                        1 => PIECE_ATTACKS.knight_attacks[sq],
+                        2 => PIECE_ATTACKS.nnbishop_attacks(1 << sq, occupancy),
+                        3 => PIECE_ATTACKS.nnrook_attacks(1 << sq, occupancy),
+                        4 => PIECE_ATTACKS.nnbishop_attacks(1 << sq, occupancy) | PIECE_ATTACKS.nnrook_attacks(1 << sq, occupancy),
+                        _ => 0,
+                    };
+
+                    if (1..5).contains(&kind) {
+                        let (offset, size) = terms::MOBILITY[kind - 1];
+                        add(offset + ((attacks & safe).count_ones() as usize).min(size - 1), 1);
+
+                        if attacks & king_zone != 0 {
+                            attackers += 1;
+                            attack_units[kind - 1] += (attacks & king_zone).count_ones() as i32;
                         }
-                    },
-                    Piece::WQ => {
-                        let queen = PIECE_ATTACKS.get_queen_attacks(square as u64, board.get_occupancy(Color::Both)).count_ones() as i32 - QUEEN_UNIT;
-                        score_opening += queen * QUEEN_MOBILITY_OPENING;
-                        score_endgame += queen * QUEEN_MOBILITY_ENDGAME;
                     }
-                    Piece::WK => {
-                        // semi open file penalty (discourage semi-open files on the king)
-                        if *board[Piece::WP] & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening -= SEMI_OPEN_FILE_SCORE;
-                            score_endgame -= SEMI_OPEN_FILE_SCORE;
-                        }
-                        
-                        // open file penalty (discourage open files on the king)
-                        if (*board[Piece::WP] | *board[Piece::BP]) & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening -= OPEN_FILE_SCORE;
-                            score_endgame -= OPEN_FILE_SCORE;
-                        }
 
-                        let king_shields = (PIECE_ATTACKS.king_attacks[square] & board.get_occupancy(Color::White)).count_ones() as i32 * KING_SHIELD_BONUS;
-                        score_opening += king_shields;
-                        score_endgame += king_shields;
-                    },
-
-                    Piece::BP => {
-                        // let pawn_piece = Piece::WP;
-                        // println!("score before {}", score);
-                        let double_pawns = (*board[Piece::BP] & EVAL_MASKS.file_masks[square]).count_ones() as i32;
-                        if double_pawns > 1 {
-                            score_opening -= double_pawns * DOUBLE_PAWN_PENALTY_OPENING;
-                            score_endgame -= double_pawns * DOUBLE_PAWN_PENALTY_ENDGAME;
-                        }
-                        // isolated pawn
-                        if *board[Piece::BP] & EVAL_MASKS.isolated_masks[square] == 0 {
-                            score_opening -= ISOLATED_PAWN_PENALTY_OPENING;
-                            score_endgame -= ISOLATED_PAWN_PENALTY_OPENING;
+                    let file = EVAL_MASKS.file_masks[sq];
+                    let ahead = Self::passed_mask(color, sq);
+
+                    match kind {
+                        0 => {
+                            // only the rearmost pawn of a file is not doubled
+                            let doubled = our_pawns & file & ahead != 0;
+                            if doubled { add(terms::DOUBLE_PAWN, 1) }
+                            if our_pawns & EVAL_MASKS.isolated_masks[sq] == 0 { add(terms::ISOLATED_PAWN, 1) }
+
+                            if !doubled && their_pawns & ahead == 0 {
+                                let rank = relative / 8;
+                                add(terms::PASSED_PAWN + rank, 1);
+
+                                let push = if color == Color::White { sq + 8 } else { sq - 8 };
+                                if occupancy & (1 << push) != 0 { add(terms::PASSED_BLOCKED + rank, 1) }
+                            }
                         }
-                        // passed pawn bonus 
-                        if EVAL_MASKS.black_passed_masks[square] & *board[Piece::WP] == 0 {
-                            score_opening -= PASSED_PAWN_BONUS[Square::from(square).rank()] as i32;
-                            score_endgame -= PASSED_PAWN_BONUS[Square::from(square).rank()] as i32;
+                        1 | 2 if attacks & (their_rooks | their_queens) != 0 => {
+                            add(terms::THREAT_BY_MINOR, (attacks & (their_rooks | their_queens)).count_ones() as i32);
                         }
-                    },
-                    Piece::BN => {},
-                    Piece::BB => {
-                        let bishop_mobility_units = PIECE_ATTACKS.get_bishop_attacks(square as u64, board.get_occupancy(Color::Both)).count_ones() as i32 - BISHOP_UNIT;
-                        score_opening -= bishop_mobility_units * BISHOP_MOBILITY_OPENING;
-                        score_endgame -= bishop_mobility_units * BISHOP_MOBILITY_OPENING;
-                    },
-                    Piece::BR => {
-                        // semi open file bonus
-                        if *board[Piece::BP] & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening -= SEMI_OPEN_FILE_SCORE;
-                            score_endgame -= SEMI_OPEN_FILE_SCORE;
+                        3 => {
+                            if (our_pawns | their_pawns) & file == 0 {
+                                add(terms::ROOK_OPEN_FILE, 1);
+                            } else if our_pawns & file == 0 {
+                                add(terms::ROOK_SEMI_OPEN_FILE, 1);
+                            }
+                            if relative / 8 == 6 { add(terms::ROOK_ON_SEVENTH, 1) }
+                            if attacks & their_queens != 0 { add(terms::THREAT_BY_ROOK, (attacks & their_queens).count_ones() as i32) }
                         }
-                        
-                        // open file bonus
-                        if (*board[Piece::WP] | *board[Piece::BP]) & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening -= OPEN_FILE_SCORE;
-                            score_endgame -= OPEN_FILE_SCORE;
+                        5 => {
+                            if (our_pawns | their_pawns) & file == 0 {
+                                add(terms::KING_OPEN_FILE, 1);
+                            } else if our_pawns & file == 0 {
+                                add(terms::KING_SEMI_OPEN_FILE, 1);
+                            }
+
+                            let next_ranks = if color == Color::White { (EVAL_MASKS.rank_masks[sq] << 8) | (EVAL_MASKS.rank_masks[sq] << 16) } else { (EVAL_MASKS.rank_masks[sq] >> 8) | (EVAL_MASKS.rank_masks[sq] >> 16) };
+                            add(terms::KING_SHIELD, (our_pawns & ahead & next_ranks).count_ones() as i32);
                         }
-                    },
-                    Piece::BQ => {
-                        let queen_mobility = PIECE_ATTACKS.get_queen_attacks(square as u64, board.get_occupancy(Color::Both)).count_ones() as i32 - QUEEN_UNIT;
-                        score_opening -= queen_mobility *  QUEEN_MOBILITY_OPENING;
-                        score_endgame -= queen_mobility * QUEEN_MOBILITY_ENDGAME;
+                        _ => {}
                     }
-                    Piece::BK => {
-                         // semi open file penalty (discourage semi-open files on the king)
-                        if *board[Piece::WP] & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening += SEMI_OPEN_FILE_SCORE;
-                            score_endgame += SEMI_OPEN_FILE_SCORE;
-                        }
-                        
-                        // open file penalty (discourage open files on the king)
-                        if (*board[Piece::WP] | *board[Piece::BP]) & EVAL_MASKS.file_masks[mirror_index] == 0 {
-                            score_opening += OPEN_FILE_SCORE;
-                            score_endgame += OPEN_FILE_SCORE;
-                        }
-
-                        score_opening -= (PIECE_ATTACKS.king_attacks[square] & board.get_occupancy(Color::Black)).count_ones() as i32 * KING_SHIELD_BONUS;
-                        score_endgame -= (PIECE_ATTACKS.king_attacks[square] & board.get_occupancy(Color::Black)).count_ones() as i32 * KING_SHIELD_BONUS;
-                    },
-
-                    _ => {}
                 }
-                
-                bitboard.pop_bit(square.into());
             }
 
+            // https://www.chessprogramming.org/King_Safety#Attacking_King_Zone
+            // a single piece rarely makes a king attack
+            if attackers >= 2 {
+                for (i, units) in attack_units.into_iter().enumerate() { add(terms::KING_ATTACK + i, units) }
+            }
+
+            if board[Piece::from(Piece::WB as u8 + 6 * color as u8)].count_ones() >= 2 { add(terms::BISHOP_PAIR, 1) }
+            add(terms::THREAT_BY_PAWN, (pawn_attacks[color] & theirs & !their_pawns).count_ones() as i32);
         }
 
-        // println!("game phase>>>>>> {:?}", game_phase);
-        // println!("::::opening >>> {score_opening} :::::endgame >>> {score_endgame}");
+        score
+    }
 
-        let score = match game_phase {
-            GamePhase::MiddleGame => {
-                (score_opening * game_phase_score + 
-                    score_endgame * (OPENING_PHASE_SCORE - game_phase_score)
-                ) / OPENING_PHASE_SCORE
-            }
-            GamePhase::Opening => score_opening,
-            GamePhase::EndGame => score_endgame
-        };
+    /// The squares attacked by these pawns
+    fn pawn_attacks(pawns: u64, color: Color) -> u64 {
+        match color {
+            Color::White => ((pawns << 9) & NOT_A_FILE) | ((pawns << 7) & NOT_H_FILE),
+            _ => ((pawns >> 7) & NOT_A_FILE) | ((pawns >> 9) & NOT_H_FILE),
+        }
+    }
 
-        match board.turn {
-            Color::White => score,
-            _ => -score
+    /// The squares in front of sq (on its file, and the adjacent ones), from color's perspective
+    fn passed_mask(color: Color, sq: usize) -> u64 {
+        match color {
+            Color::White => EVAL_MASKS.white_passed_masks[sq],
+            _ => EVAL_MASKS.black_passed_masks[sq],
         }
     }
 
+    /// https://www.chessprogramming.org/Tapered_Eval
+    /// 0 (only kings and pawns) to MAX_PHASE (every piece is still on the board, more after promotions is capped)
+    pub(crate) fn phase(board: &Board) -> i32 {
+        let count = |pieces: [Piece; 2]| pieces.iter().map(|&p| board[p].count_ones() as i32).sum::<i32>();
+        let phase = count([Piece::WN, Piece::BN]) + count([Piece::WB, Piece::BB]) + 2 * count([Piece::WR, Piece::BR]) + 4 * count([Piece::WQ, Piece::BQ]);
+        phase.min(Self::MAX_PHASE)
+    }
+
+    /// Interpolates the middle game and end game scores by the phase
+    pub(crate) fn taper(score: S, phase: i32) -> i32 {
+        (score.0 * phase + score.1 * (Self::MAX_PHASE - phase)) / Self::MAX_PHASE
+    }
+
     /// The game phase score of the game is derived from the pieces (not counting pawns and kings)
     /// that are still on the board.
     /// The full material starting position game phase is:
-    /// (4 * knight count * material score in the opening) + 
+    /// (4 * knight count * material score in the opening) +
     /// (4 * bishop count *  material score in the opening) +
     /// (4 * rook count * material score in the opening) +
     /// (2 * queen count * material score in the opening)
@@ -215,9 +294,91 @@ impl Evaluation {
         let white = white_rooks + white_bishops + white_knights + white_queens;
         let black = black_rooks + black_bishops + black_knights + black_queens;
 
- 
+
         return white + black;
     }
 
-    // pub(crate) 
-}
\ No newline at end of file
+    // pub(crate)
+}
+
+
+#[cfg(test)]
+mod evaluation_tests {
+    use super::*;
+    use crate::constants::{CMK_POSITION, START_POSITION, TRICKY_POSITION};
+
+    /// The same position with the colours swapped (and the board mirrored vertically)
+    fn flip(fen: &str) -> String {
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        let swap_case = |s: &str| s.chars().map(|c| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect::<String>();
+
+        let ranks = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>().join("/");
+        let turn = if fields[1] == "w" { "b" } else { "w" };
+        let mut castling = swap_case(fields[2]).chars().collect::<Vec<_>>();
+        castling.sort();
+        let castling = if fields[2] == "-" { String::from("-") } else { castling.into_iter().collect() };
+        let enpassant = match fields[3] {
+            "-" => String::from("-"),
+            sq => format!("{}{}", &sq[..1], 9 - sq[1..].parse::<u8>().unwrap()),
+        };
+
+        format!("{ranks} {turn} {castling} {enpassant} {} {}", fields[4], fields[5])
+    }
+
+    #[test]
+    fn evaluation_should_be_symmetric() {
+        let fens = [
+            START_POSITION, TRICKY_POSITION, CMK_POSITION,
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r1bqk2r/pp2bppp/2n1pn2/2pp4/3P1B2/2P1PN2/PP1N1PPP/R2QKB1R w KQkq c6 0 7",
+            "6k1/5ppp/8/3R4/8/1b6/5PPP/6K1 b - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::try_from(fen).unwrap();
+            let flipped = Board::try_from(flip(fen).as_str()).unwrap();
+
+            assert_eq!(Evaluation::score(&board, &mut ()), Evaluation::score(&flipped, &mut ()) * -1, "{fen}");
+            assert_eq!(Evaluation::evaluate(&board), Evaluation::evaluate(&flipped), "{fen}");
+        }
+    }
+
+    #[test]
+    fn phase_should_taper_from_the_middle_game_to_the_end_game() {
+        assert_eq!(Evaluation::phase(&Board::try_from(START_POSITION).unwrap()), Evaluation::MAX_PHASE);
+        assert_eq!(Evaluation::phase(&Board::try_from("4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap()), 0);
+        assert_eq!(Evaluation::phase(&Board::try_from("3rk3/8/8/8/8/8/8/1N2K3 w - - 0 1").unwrap()), 3);
+
+        assert_eq!(Evaluation::taper(S(100, -20), Evaluation::MAX_PHASE), 100);
+        assert_eq!(Evaluation::taper(S(100, -20), 0), -20);
+        assert_eq!(Evaluation::taper(S(100, -20), 12), 40);
+    }
+
+    #[test]
+    fn handcrafted_terms_should_favour_the_better_side() {
+        let eval = |fen: &str| Evaluation::evaluate(&Board::try_from(fen).unwrap());
+
+        // a passed pawn on the 6th, compared to the same pawn on the 3rd (the a-pawns keep the KPK bitbase out of it)
+        assert!(eval("4k3/p7/3P4/8/8/8/P7/4K3 w - - 0 1") > eval("4k3/p7/8/8/8/3P4/P7/4K3 w - - 0 1"));
+        // the same passed pawn, blocked by the enemy king
+        assert!(eval("4k3/p7/4K3/3P4/8/8/P7/8 w - - 0 1") > eval("8/p7/3k4/3P4/8/8/P7/4K3 w - - 0 1"));
+        // the bishop pair
+        assert!(eval("4k3/pppp4/8/8/8/8/PPPP4/2B1KB2 w - - 0 1") > eval("4k3/pppp4/8/8/8/8/PPPP4/2B1KN2 w - - 0 1"));
+        // a rook on the open file, against the same rook behind its own pawn
+        assert!(eval("4k3/ppp5/8/8/8/8/PP6/3RK3 w - - 0 1") > eval("4k3/ppp5/8/8/8/8/PP6/R3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn trace_should_account_for_the_whole_score() {
+        let board = Board::try_from(TRICKY_POSITION).unwrap();
+        let mut counts = vec![0; terms::COUNT];
+        let score = Evaluation::score(&board, &mut counts);
+
+        let traced = counts.iter().enumerate().fold(S::default(), |acc, (term, &c)| acc + WEIGHTS[term] * c);
+        assert_eq!(score, traced);
+        // both sides have the same material
+        assert!(counts[terms::MATERIAL..terms::MATERIAL + 5].iter().all(|&c| c == 0));
+    }
+}
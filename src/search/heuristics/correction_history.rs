@@ -0,0 +1,98 @@
+use crate::{board::{piece::Piece, position::Position}, constants::TB_WIN_IN_MAX_PLY, endgame::MaterialKey, squares::Square};
+
+
+/// Number of entries of the pawn and material tables (for each side)
+const SIZE: usize = 16384;
+/// Entries are stored with extra precision: 1 centipawn is GRAIN
+const GRAIN: i32 = 256;
+const WEIGHT_SCALE: i32 = 256;
+/// A single table can correct the evaluation by at most 128 centipawns
+const MAX_CORRECTION: i32 = 128 * GRAIN;
+
+
+/// Static evaluation correction history
+/// https://www.chessprogramming.org/Static_Evaluation_Correction_History
+/// Each table tracks the average gap between the search score and the static evaluation of positions that share
+/// a pawn structure, a material signature, or the previous move (continuation). The static evaluation is corrected by those gaps before it is used for pruning.
+#[derive(Debug, Clone)]
+pub(crate) struct CorrectionHistory {
+    /// [stm][pawn_key % SIZE]
+    pawn: Vec<i32>,
+    /// [stm][material key % SIZE]
+    material: Vec<i32>,
+    /// [stm][previous moved piece][previous target square]
+    continuation: Vec<i32>,
+}
+
+impl Default for CorrectionHistory {
+    fn default() -> Self {
+        Self { pawn: vec![0; 2 * SIZE], material: vec![0; 2 * SIZE], continuation: vec![0; 2 * Piece::TOTAL * Square::TOTAL] }
+    }
+}
+
+impl CorrectionHistory {
+    /// The indices into the pawn, material, and continuation tables.
+    /// prev: the piece moved by the opponent on the last move, and its target square (None after a null move)
+    fn indices(position: &Position, prev: Option<(Piece, Square)>) -> (usize, usize, Option<usize>) {
+        let stm = position.turn as usize;
+        let pawn = stm * SIZE + position.pawn_key as usize % SIZE;
+        let material = stm * SIZE + MaterialKey::from(&position.board).hash() as usize % SIZE;
+        let continuation = prev.map(|(piece, sq)| (stm * Piece::TOTAL + piece as usize) * Square::TOTAL + sq as usize);
+
+        (pawn, material, continuation)
+    }
+
+    /// The static evaluation, corrected by the (weighted) average error of the tables. The pawn structure has the most weight
+    pub(crate) fn correct(&self, position: &Position, prev: Option<(Piece, Square)>, eval: i32) -> i32 {
+        let (pawn, material, continuation) = Self::indices(position, prev);
+        let correction = 2 * self.pawn[pawn] + self.material[material] + continuation.map_or(0, |i| self.continuation[i]);
+
+        (eval + correction / (4 * GRAIN)).clamp(-TB_WIN_IN_MAX_PLY + 1, TB_WIN_IN_MAX_PLY - 1)
+    }
+
+    /// Moves every table towards the error of the static evaluation (score - eval). Deeper searches get a bigger weight
+    pub(crate) fn update(&mut self, position: &Position, prev: Option<(Piece, Square)>, depth: u8, score: i32, eval: i32) {
+        let (pawn, material, continuation) = Self::indices(position, prev);
+        let error = (score - eval) * GRAIN;
+        let weight = (depth as i32 + 1).min(16);
+
+        let update = |entry: &mut i32| *entry = ((*entry * (WEIGHT_SCALE - weight) + error * weight) / WEIGHT_SCALE).clamp(-MAX_CORRECTION, MAX_CORRECTION);
+        update(&mut self.pawn[pawn]);
+        update(&mut self.material[material]);
+        if let Some(i) = continuation { update(&mut self.continuation[i]) }
+    }
+}
+
+
+#[cfg(test)]
+mod correction_history_tests {
+    use super::*;
+    use crate::board::state::board::Board;
+    use crate::constants::TRICKY_POSITION;
+
+    #[test]
+    fn corrections_should_converge_towards_the_search_error() {
+        let position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+        let mut corrhist = CorrectionHistory::default();
+        assert_eq!(corrhist.correct(&position, None, 50), 50);
+
+        // the search keeps finding that this position is 60cp better than its static evaluation
+        for _ in 0..200 { corrhist.update(&position, None, 10, 110, 50); }
+        // without a previous move, only the pawn and material tables (3/4 of the weight) apply
+        let corrected = corrhist.correct(&position, None, 50);
+        assert!((90..=95).contains(&corrected), "{corrected}");
+
+        // the other side's tables are untouched
+        let flipped = Position::with(Board::try_from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1").unwrap());
+        assert_eq!(corrhist.correct(&flipped, None, 50), 50);
+    }
+
+    #[test]
+    fn corrections_should_be_bounded() {
+        let position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+        let mut corrhist = CorrectionHistory::default();
+
+        for _ in 0..1000 { corrhist.update(&position, Some((Piece::WN, Square::E5)), 30, 20_000, 0); }
+        assert!(corrhist.correct(&position, Some((Piece::WN, Square::E5)), 0) <= 128);
+    }
+}
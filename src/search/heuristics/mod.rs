@@ -7,6 +7,7 @@ pub(crate) mod pv;
 pub(crate) mod repetitions;
 pub(crate) mod capture_history;
 pub(crate) mod continuation_history;
+pub(crate) mod correction_history;
 
 
 // from Carp
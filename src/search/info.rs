@@ -0,0 +1,68 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::constants::{LONGEST_TB_MATE, MATE_VALUE};
+
+
+/// The score of a search, from the side to move's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// In centipawns
+    Cp(i32),
+    /// Mate in n moves (not plies), negative when the side to move is getting mated
+    Mate(i32),
+}
+
+impl From<i32> for Score {
+    fn from(value: i32) -> Self {
+        match value {
+            s if s >= LONGEST_TB_MATE => Self::Mate((MATE_VALUE - s + 1) / 2),
+            s if s <= -LONGEST_TB_MATE => Self::Mate(-(MATE_VALUE + s) / 2),
+            s => Self::Cp(s),
+        }
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cp(cp) => write!(f, "cp {cp}"),
+            Self::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+
+/// The result of a completed iteration of the iterative deepening (the UCI `info` line)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: usize,
+    pub seldepth: usize,
+    pub score: Score,
+    /// The nodes searched by all the threads so far
+    pub nodes: u64,
+    pub tbhits: u64,
+    pub time: Duration,
+    /// The principal variation, in UCI notation
+    pub pv: Vec<String>,
+}
+
+impl Display for SearchInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "info depth {} seldepth {} score {} nodes {} tbhits {} pv {}", self.depth, self.seldepth, self.score, self.nodes, self.tbhits, self.pv.join(" "))
+    }
+}
+
+
+#[cfg(test)]
+mod info_tests {
+    use super::*;
+
+    #[test]
+    fn mate_scores_should_be_in_moves() {
+        assert_eq!(Score::from(35), Score::Cp(35));
+        assert_eq!(Score::from(MATE_VALUE - 1), Score::Mate(1));
+        assert_eq!(Score::from(MATE_VALUE - 3), Score::Mate(2));
+        assert_eq!(Score::from(-(MATE_VALUE - 2)), Score::Mate(-1));
+        assert_eq!(Score::Mate(-1).to_string(), "mate -1");
+    }
+}
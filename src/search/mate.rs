@@ -0,0 +1,182 @@
+use crate::{board::state::board::Board, constants::{INFINITY, MATE_VALUE}, move_logic::{bitmove::Move, move_stack::MoveStack}, move_scope::MoveScope, uci::clock::Clock};
+
+use super::heuristics::pv::PVTable;
+
+
+/// A forced mate, as proven by the MateSearch
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Mate {
+    /// Mate in `moves` (the attacker's moves, not plies)
+    pub(crate) moves: u8,
+    /// The mating line, the defender always plays its longest resistance
+    pub(crate) pv: Vec<Move>,
+}
+
+
+/// Mate finder for `go mate N`.
+/// This is a plain alpha-beta on mate scores, without any evaluation or pruning: every position at the horizon is scored as a draw, so that any score
+/// above zero is a proven mate. The mate distance is deepened one move at a time, the first iteration that finds a mate has found the shortest one.
+/// With `checks_only`, the attacker only plays checking moves, which solves most mate problems much faster, but can miss mates that need a quiet move.
+/// https://www.chessprogramming.org/Mate_Search
+pub(crate) struct MateSearch {
+    nodes: usize,
+    clock: Clock,
+    checks_only: bool,
+    stopped: bool,
+}
+
+impl MateSearch {
+    /// How often (in nodes) the clock is checked
+    const CLOCK_INTERVAL: usize = 1024;
+
+    pub(crate) fn new(clock: Clock, checks_only: bool) -> Self {
+        Self { nodes: 0, clock, checks_only, stopped: false }
+    }
+
+    pub(crate) fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// The shortest forced mate for the side to move within `max_moves` moves, if there is one (and the search was not stopped)
+    pub(crate) fn find(&mut self, board: &Board, max_moves: u8) -> Option<Mate> {
+        for moves in 1..=max_moves {
+            let mut pv = PVTable::default();
+            // the attacker's last move is the (moves * 2 - 1)th ply
+            let score = self.search(board, 0, moves * 2 - 1, 0, 1, &mut pv);
+            if self.stopped { return None }
+
+            if score > 0 {
+                let pv = pv.mvs()[..pv.length].iter().map(|&mv| Move::from(mv)).collect();
+                return Some(Mate { moves, pv });
+            }
+        }
+
+        None
+    }
+
+    /// Fail-soft alpha-beta, the attacker plays on even plies.
+    /// The PV is updated on every new best move (not only inside the window), so that the defender's longest resistance also makes it to the PV
+    fn search(&mut self, board: &Board, ply: usize, depth: u8, mut alpha: i32, beta: i32, pv: &mut PVTable) -> i32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(Self::CLOCK_INTERVAL) && self.clock.stop(self.nodes as u64, 0) { self.stopped = true; }
+        if self.stopped { return 0 }
+
+        let attacker = ply.is_multiple_of(2);
+        let mut moves = MoveStack::<Move>::new();
+        board.gen_movement::<{ MoveScope::ALL }, Move>(&mut moves);
+
+        let mut legal_moves = 0;
+        let mut best_score = -INFINITY;
+
+        for mv in moves {
+            let Some(child) = board.make_move(mv, MoveScope::AllMoves) else { continue };
+            legal_moves += 1;
+
+            // The defender is not mated, and the attacker has no move left
+            if depth == 0 { return 0 }
+            if attacker && self.checks_only && !child.stm_in_check() { continue }
+
+            let mut child_pv = PVTable::default();
+            let score = -self.search(&child, ply + 1, depth - 1, -beta, -alpha, &mut child_pv);
+            if self.stopped { return 0 }
+
+            if score > best_score {
+                best_score = score;
+                pv.update(mv, &child_pv);
+
+                if score > alpha {
+                    alpha = score;
+                    if score >= beta { break }
+                }
+            }
+        }
+
+        if legal_moves == 0 {
+            return if board.stm_in_check() { -(MATE_VALUE - ply as i32) } else { 0 };
+        }
+
+        // with checks_only, an attacker without any check has no mate
+        if best_score == -INFINITY { 0 } else { best_score }
+    }
+}
+
+
+#[cfg(test)]
+mod mate_tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::constants::START_POSITION;
+    use crate::search::signals::SearchSignals;
+
+    fn find(fen: &str, max_moves: u8, checks_only: bool) -> Option<Mate> {
+        let signals = Arc::new(SearchSignals::default());
+        MateSearch::new(Clock::new(Arc::clone(&signals)), checks_only).find(&Board::try_from(fen).unwrap(), max_moves)
+    }
+
+    fn uci(pv: &[Move]) -> Vec<String> {
+        pv.iter().map(|mv| mv.to_uci()).collect()
+    }
+
+    #[test]
+    fn should_find_a_mate_in_two() {
+        // Légal's mate pattern: 1. Nf6+ gxf6 2. Bxf7#
+        let mate = find("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1", 2, true).unwrap();
+        assert_eq!(mate.moves, 2);
+        assert_eq!(uci(&mate.pv), ["d5f6", "g7f6", "c4f7"]);
+    }
+
+    #[test]
+    fn quiet_mates_should_only_be_found_without_checks_only() {
+        // 1. Ra6! bxa6 2. b7#
+        let fen = "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1";
+        assert_eq!(find(fen, 3, true), None);
+
+        let mate = find(fen, 3, false).unwrap();
+        assert_eq!(mate.moves, 2);
+        assert_eq!(uci(&mate.pv), ["a1a6", "b7a6", "b6b7"]);
+    }
+
+    #[test]
+    fn should_find_a_mate_in_three_for_black() {
+        // 1... Bc5+ 2. Kxc5 Qb6+ 3. Kd5 Qd6#
+        let mate = find("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1", 4, true).unwrap();
+        assert_eq!(mate.moves, 3);
+        assert_eq!(uci(&mate.pv), ["f8c5", "d4c5", "f6b6", "c5d5", "b6d6"]);
+    }
+
+    #[test]
+    fn should_find_a_smothered_mate_in_four() {
+        // Philidor's legacy: 1. Nf7+ Kg8 2. Nh6+ Kh8 3. Qg8+ Rxg8 4. Nf7#
+        let mate = find("5r1k/6pp/8/3QN3/8/8/6PP/6K1 w - - 0 1", 5, true).unwrap();
+        assert_eq!(mate.moves, 4);
+        assert_eq!(uci(&mate.pv), ["e5f7", "h8g8", "f7h6", "g8h8", "d5g8", "f8g8", "h6f7"]);
+    }
+
+    #[test]
+    fn should_find_the_shortest_mates_of_the_lasker_king_hunt() {
+        // Ed. Lasker - Thomas, London 1912: after 11. Qxh7+ Kxh7, the game went on 12. Nxf6+ Kh6 13. Neg4+ Kg5 14. h4+ Kf4 15. g3+ Kf3 16. Be2+ Kg2 17. Rh2+ Kg1 18. Kd2#
+        // but 14. f4+ Kh4 15. g3+ Kh3 16. Bf1+ Bg2 17. Nf2# is a move faster
+        let hunt = [
+            ("rn3r2/pbppq1pk/1p2pb2/4N3/3PN3/3B4/PPP2PPP/R3K2R w KQ - 0 12", 6),
+            ("rn3r2/pbppq1p1/1p2pN1k/4N3/3P4/3B4/PPP2PPP/R3K2R w KQ - 1 13", 5),
+            ("rn3r2/pbppq1p1/1p2pN2/6k1/3P2N1/3B4/PPP2PPP/R3K2R w KQ - 3 14", 4),
+        ];
+
+        for (fen, moves) in hunt {
+            let mate = find(fen, 6, true).unwrap();
+            assert_eq!(mate.moves, moves, "{fen}");
+            assert_eq!(mate.pv.len(), moves as usize * 2 - 1, "{fen}");
+            assert_eq!(uci(&mate.pv[mate.pv.len() - 7..]), ["f2f4", "g5h4", "g2g3", "h4h3", "d3f1", "b7g2", "g4f2"], "{fen}");
+        }
+    }
+
+    #[test]
+    fn should_report_no_mate_within_the_limit() {
+        // mate in 5
+        assert_eq!(find("rn3r2/pbppq1p1/1p2pN1k/4N3/3P4/3B4/PPP2PPP/R3K2R w KQ - 1 13", 4, true), None);
+        assert_eq!(find(START_POSITION, 2, false), None);
+        // stalemate
+        assert_eq!(find("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3, false), None);
+    }
+}
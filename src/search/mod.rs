@@ -1,12 +1,18 @@
 pub(crate) mod evaluation;
+pub(crate) mod hce_weights;
 pub(crate) mod alpha_beta;
 pub(crate) mod control;
 pub(crate) mod time_control;
-pub(crate) mod threads;
 pub(crate) mod stack;
 pub(crate) mod clock;
 pub(crate) mod search;
 pub(crate) mod heuristics;
 pub(crate) mod constants;
 pub(crate) mod sr;
-pub(crate) mod  threadpool;
\ No newline at end of file
+pub(crate) mod  threadpool;
+pub(crate) mod tunables;
+pub(crate) mod mate;
+pub(crate) mod skill;
+pub(crate) mod contempt;
+pub(crate) mod info;
+pub(crate) mod signals;
\ No newline at end of file
@@ -1,10 +1,17 @@
 use std::cmp::min;
 
-use crate::{board::{piece::Piece, position::Position}, color::Color, constants::{params::MAX_DEPTH, DEPTH_REDUCTION_FACTOR, FULL_DEPTH_MOVE, FUTILITY_MOVE_COUNTS, INFINITY, LONGEST_TB_MATE, MATE_IN_MAX_PLY, MATE_VALUE, MAX_PLY, RAZOR_MARGIN, REDUCTION_LIMIT, SE_LOWER_LIMIT, ZOBRIST}, move_logic::{bitmove::Move, move_picker::{MovePicker, Stage}}, move_scope::MoveScope, search::constants::Root, tt::{entry::{from_tt, TTData}, flag::HashFlag, tpt::TPT}, uci::clock::Clock, utils::lmr::reduction};
+use crate::{board::{piece::Piece, position::Position}, color::Color, constants::{params::MAX_DEPTH, DEPTH_REDUCTION_FACTOR, FULL_DEPTH_MOVE, FUTILITY_MOVE_COUNTS, IIR_DEPTH, INFINITY, LONGEST_TB_MATE, MATE_IN_MAX_PLY, MATE_VALUE, MAX_PLY, PROBCUT_DEPTH, PROBCUT_REDUCTION, QS_CHECK_PLIES, RANK_2, RANK_7, RAZOR_MARGIN, REDUCTION_LIMIT, SEE_PRUNING_DEPTH, SE_LOWER_LIMIT, TB_WIN_IN_MAX_PLY, ZOBRIST}, move_logic::{bitmove::Move, move_picker::{MovePicker, Stage}, move_stack::MoveStack}, move_scope::MoveScope, search::{constants::Root, tunables::*}, syzygy::probe::SyzygyConfig, tt::{entry::{from_tt, TTData}, flag::HashFlag, tpt::TPT}, uci::clock::Clock, utils::lmr::reduction};
 use crate::board::piece::Piece::*;
 use crate::color::Color::*;
+use crate::endgame::kpk;
+use crate::squares::Square;
+
+use super::{constants::{NodeType, NotPv, Pv}, heuristics::{capture_history::CaptureHistory, continuation_history::ContinuationHistory, correction_history::CorrectionHistory, countermove::CounterMove, history::HistoryHeuristic, killer_moves::KillerMoves, pv::PVTable}, contempt::Contempt, info::{Score, SearchInfo}, skill::Skill, stack::{Stack, StackItem}};
+
+/// The node ProbCut is tried at: the raised beta, the static eval, the depth and the TT move
+#[derive(Debug, Clone, Copy)]
+struct ProbCut { beta: i32, eval: i32, depth: u8, tt_move: Option<Move> }
 
-use super::{constants::{NodeType, NotPv, Pv}, heuristics::{capture_history::CaptureHistory, continuation_history::ContinuationHistory, countermove::CounterMove, history::HistoryHeuristic, killer_moves::KillerMoves, pv::PVTable}, stack::{Stack, StackItem}, threads::Thread};
 
 /// The number of nodes you can actually cut depends on:
 /// 1. How well written your alpha-beta program is
@@ -15,7 +22,7 @@ use super::{constants::{NodeType, NotPv, Pv}, heuristics::{capture_history::Capt
 ///  1. [x] AlphaBeta 
 ///  2. [-] Quiescence Search
 ///         a. [x] Standing Pat
-///         b. [-] Delta Pruning
+///         b. [x] Delta Pruning
 ///             i. https://www.chessprogramming.org/Delta_Pruning
 ///             ii. https://www.chessprogramming.org/CPW-Engine_quiescence (check how CPW implements its beta pruning)
 ///  3. [x] Late Move Reduction
@@ -34,8 +41,14 @@ use super::{constants::{NodeType, NotPv, Pv}, heuristics::{capture_history::Capt
 /// 16. [-] [Need to add implementation for detecting tactical moves](https://www.chessprogramming.org/Tactical_Moves)
 /// This implementation is a fail-soft implementation (meaning we have to keep track of the best score)[XX] 
 /// - fail-hard for now
-pub(crate) struct Search<'a> {
+///
+/// Every search thread has its own worker (heuristics, stack and node counter), and shares the TT, the limits (the clock) and the signals
+/// (stop, ponder, and the nodes of all the threads) with the others. A worker lives for a whole game, see new_search and clear.
+#[derive(Debug)]
+pub(crate) struct SearchWorker {
     nodes:  usize,
+    /// The nodes already added to the shared node count
+    published_nodes: usize,
     ply: usize,
     pv_table: PVTable,
     /// The Killer Move is a quiet move which caused a beta-cutoff in a sibling Cut-node,
@@ -44,9 +57,11 @@ pub(crate) struct Search<'a> {
     history_table: HistoryHeuristic,
     caphist: CaptureHistory,
     conthist: ContinuationHistory,
+    /// The average error of the static evaluation, by pawn structure, material and previous move
+    corrhist: CorrectionHistory,
     counter_mvs: CounterMove,
     // continuation_hist
-    tt: TPT<'a>,
+    tt: TPT,
     ss: [StackItem; MAX_PLY + 10],
     depth: usize,
     limit: usize,
@@ -54,28 +69,97 @@ pub(crate) struct Search<'a> {
     last_move_was_null: bool,
     clock: Clock,
     sdepth: usize,
+    tb: SyzygyConfig,
+    /// The number of successful tablebase probes
+    tbhits: usize,
+    /// When the root is in the tablebase: the only moves searched at the root (best first)
+    root_moves: Vec<Move>,
+    /// Root moves that are skipped (the best moves already found, when searching for the next best ones)
+    excluded_root_moves: Vec<Move>,
+    skill: Skill,
+    contempt: Contempt,
+    /// The side to move at the root, draws are scored from its point of view
+    root_color: Color,
+    /// The root side's contempt in the current iteration
+    root_contempt: i32,
 }
 
 
-impl<'a> Search<'a> {
-    pub(crate) fn new(tt: TPT<'a>, clock: Clock) -> Self {
-        Self { nodes: 0, ply: 0, pv_table: PVTable::default(), killer_moves: KillerMoves::new(), last_move_was_null: false,
-            history_table: HistoryHeuristic::new(), tt, caphist: CaptureHistory::default(), conthist: ContinuationHistory::new(),
-                counter_mvs: CounterMove::new(), ss: [StackItem::default(); MAX_PLY + 10], depth: 0, limit: 0, eval: 0, clock, sdepth: 0 }
+impl SearchWorker {
+    pub(crate) fn new(tt: TPT, clock: Clock, tb: SyzygyConfig) -> Self {
+        Self { nodes: 0, published_nodes: 0, ply: 0, pv_table: PVTable::default(), killer_moves: KillerMoves::new(), last_move_was_null: false,
+            history_table: HistoryHeuristic::new(), tt, caphist: CaptureHistory::default(), conthist: ContinuationHistory::new(), corrhist: CorrectionHistory::default(),
+                counter_mvs: CounterMove::new(), ss: [StackItem::default(); MAX_PLY + 10], depth: 0, limit: 0, eval: 0, clock, sdepth: 0, tb, tbhits: 0, root_moves: Vec::new(), excluded_root_moves: Vec::new(), skill: Skill::default(),
+                    contempt: Contempt::default(), root_color: White, root_contempt: 0 }
+    }
+
+    /// Hands the shared state of the next `go` (TT, stop flag and limits) to the worker, and resets everything that only belongs to the last search.
+    /// The move ordering and correction histories are kept: they are still relevant in the next positions of the same game (see clear)
+    pub(crate) fn new_search(&mut self, tt: TPT, clock: Clock, tb: SyzygyConfig) {
+        self.tt = tt;
+        self.clock = clock;
+        self.tb = tb;
+
+        self.nodes = 0;
+        self.published_nodes = 0;
+        self.ply = 0;
+        self.depth = 0;
+        self.limit = 0;
+        self.eval = 0;
+        self.sdepth = 0;
+        self.tbhits = 0;
+        self.last_move_was_null = false;
+        self.pv_table = PVTable::default();
+        self.killer_moves = KillerMoves::new();
+        self.ss = [StackItem::default(); MAX_PLY + 10];
+        self.root_moves.clear();
+        self.excluded_root_moves.clear();
+    }
+
+    /// Forgets the histories of the previous games (`ucinewgame`)
+    pub(crate) fn clear(&mut self) {
+        self.history_table = HistoryHeuristic::new();
+        self.caphist = CaptureHistory::default();
+        self.conthist = ContinuationHistory::new();
+        self.corrhist = CorrectionHistory::default();
+        self.counter_mvs = CounterMove::new();
+    }
+
+    /// Limits the strength of the search (full strength by default)
+    pub(crate) fn set_skill(&mut self, skill: Skill) {
+        self.skill = skill;
     }
 
-    fn aspiration_window(&mut self, position: &mut Position, t: &mut Thread) -> i32 {
+    pub(crate) fn set_contempt(&mut self, contempt: Contempt) {
+        self.contempt = contempt;
+    }
+
+    /// The score of a draw for the side to move: the root side scores draws at -contempt, and its opponent at +contempt, whatever the ply.
+    /// The jitter (±1, from the node count) keeps repetition lines from all being cached with the same exact score.
+    fn draw_score(&self, position: &Position) -> i32 {
+        let jitter = 1 - (self.nodes & 2) as i32;
+        let contempt = if position.turn == self.root_color { -self.root_contempt } else { self.root_contempt };
+        contempt + jitter
+    }
+
+    /// When the side to move can repeat a position with its next move, it can at least draw: alpha is raised to the draw score,
+    /// and the node is cut off if that is already enough
+    fn upcoming_repetition(&self, alpha: &mut i32, beta: i32, position: &Position) -> Option<i32> {
+        let draw = self.draw_score(position);
+        if *alpha >= draw || !position.has_game_cycle(self.ply) { return None }
+
+        *alpha = draw;
+        (draw >= beta).then_some(draw)
+    }
+
+    fn aspiration_window(&mut self, position: &mut Position) -> i32 {
         let mut alpha = -INFINITY;
         let mut beta = INFINITY;
         // let mut delta = -INFINITY;
         let mut new_depth = self.depth + 1;
         let mut pv = PVTable::default();
-        let mut delta = 25;
-
-        const BIG_DELTA: usize = 975;
+        let mut delta = aspiration_delta();
 
-        println!("||||||||||||||||||||||||||||||||||||||||||||||||| alpha-> {alpha}, and beta-->> {beta} ---------------- ((((({}))))))", new_depth);
-        println!("XXXXXXXXXXXXXXX eval => {}, delta==> {}, for al-->> {}, and for beta-->> {}", self.eval, delta, self.eval - delta, self.eval + delta);
 
         if new_depth >= 5 {
             // delta = 20;
@@ -83,10 +167,9 @@ impl<'a> Search<'a> {
             beta = (INFINITY).min(self.eval + delta);
         }
 
-        println!("proceeding with alpha-> {alpha}, and beta-->> {beta}");
 
         loop {
-            let score = self.negamax::<Root>(alpha, beta, new_depth  as u8, position, &mut pv, false, t);
+            let score = self.negamax::<Root>(alpha, beta, new_depth  as u8, position, &mut pv, false);
             if self.clock.stop(self.nodes as u64, new_depth as u8) { return -INFINITY}
 
             if score <= alpha {
@@ -94,10 +177,8 @@ impl<'a> Search<'a> {
                 beta = (alpha + beta) / 2;
                 alpha = (-INFINITY).max(alpha - delta);
                 new_depth = self.depth + 1;
-                println!("00000");
             } else if score >= beta {
                 // Fail-High
-                println!("1111-1111 {}", beta);
                 beta = (INFINITY).min(beta + delta);
                 self.pv_table = pv.clone();
                 // println!("currently alpha={alpha}, beta={beta}, and score==>>{score}");
@@ -105,44 +186,110 @@ impl<'a> Search<'a> {
                     new_depth -= 1;
                 }
             } else {
-                println!("4444---->>>4444");
                 self.pv_table = pv;
                 return score;
             }
 
             // Widen window
             delta += delta/2;
-            if delta >= BIG_DELTA as i32 { alpha = -INFINITY; beta = INFINITY } 
+            if delta >= aspiration_max_delta() { alpha = -INFINITY; beta = INFINITY } 
         }
     }
 
-    pub(crate) fn iterative_deepening(&mut self, limit: usize, position: &mut Position, t: &mut Thread) {
-        self.limit = limit;
+    /// Searches the position, and prints the UCI info line of every completed iteration
+    pub(crate) fn iterative_deepening(&mut self, limit: usize, position: &mut Position) {
+        self.iterative_deepening_with(limit, position, &mut |info| println!("{info}"));
+    }
+
+    /// Searches the position, and reports every completed iteration
+    pub(crate) fn iterative_deepening_with(&mut self, limit: usize, position: &mut Position, report: &mut dyn FnMut(&SearchInfo)) {
+        self.limit = self.skill.depth().map_or(limit, |depth| limit.min(depth));
+        self.root_color = position.turn;
+
+        // Restrict the root to the moves that keep the best tablebase outcome
+        if let Some(ranked) = self.tb.tablebase.probe_root(position) {
+            self.tbhits += ranked.len();
+            // DTZ already accounts for the 50-move rule, probing (WDL) inside the search would only tell us that every remaining move wins
+            if ranked[0].dtz.is_some() { self.tb.probe_limit = 0; }
+            self.root_moves = ranked.into_iter().map(|m| m.mv).collect();
+        }
+
         while self.depth < MAX_DEPTH && self.depth < self.limit {
             // println!("\n\n\n RUNNING ::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::<<>>::::::::::: {}", self.depth);
-            let eval = self.aspiration_window(position, t);
+            self.root_contempt = self.contempt.at(self.eval);
+            let eval = self.aspiration_window(position);
 
             if self.clock.stop(self.nodes as u64, self.depth as u8) { break; }
             
             self.eval = eval;
-            t.eval = eval;
             self.depth += 1;
 
+            self.publish_nodes();
+            report(&self.info());
+
+            if self.skill.nodes().is_some_and(|nodes| self.nodes >= nodes) { break; }
         }
 
+        if self.skill.enabled() && self.depth > 0 {
+            self.pick_weaker_move(position);
+        }
+        self.publish_nodes();
+    }
+
+    /// Adds the nodes searched since the last call to the shared node count
+    fn publish_nodes(&mut self) {
+        self.clock.signals().add_nodes((self.nodes - self.published_nodes) as u64);
+        self.published_nodes = self.nodes;
+    }
 
-        println!("Originally ||||||||||||||||||||||||| {}", position.to_string());
+    /// Searches the next best root moves at the depth of the last completed iteration (one at a time, by excluding the ones already found),
+    /// and replaces the principal variation with the one of the move picked by the skill
+    fn pick_weaker_move(&mut self, position: &mut Position) {
+        let Some(best) = self.best_move() else { return };
+        let mut lines = vec![(best, self.eval, self.pv_table.clone())];
 
+        while lines.len() < Skill::MULTIPV {
+            self.excluded_root_moves = lines.iter().map(|line| line.0).collect();
+            let mut pv = PVTable::default();
+            let score = self.negamax::<Root>(-INFINITY, INFINITY, self.depth as u8, position, &mut pv, false);
 
-        let length = self.pv_table.length;
-        println!("MOVES ARE :::: with length of {}", length);
-        let mvs = &self.pv_table.mvs()[0..length];
-        for i in 0..length {
-            print!("|||-->> {}", Move::from(mvs[i as usize]));
-            // position.make_move(mvs[i].into(), MoveScope::AllMoves);
-            // println!("in check???? {} \n\n", position.to_string());
+            if pv.length == 0 || self.clock.stop(self.nodes as u64, self.depth as u8) { break; }
+            lines.push((Move::from(pv.mvs[0]), score, pv));
         }
-        println!("\n\n");
+        self.excluded_root_moves.clear();
+
+        lines.sort_by_key(|line| -line.1);
+        let picked = self.skill.pick(&lines.iter().map(|line| (line.0, line.1)).collect::<Vec<_>>());
+        let (_, eval, pv) = lines.swap_remove(picked);
+        self.eval = eval;
+        self.pv_table = pv;
+    }
+
+    /// The first move of the principal variation from the last completed iteration
+    pub(crate) fn best_move(&self) -> Option<Move> {
+        if self.pv_table.length == 0 { return None }
+        Some(Move::from(self.pv_table.mvs[0]))
+    }
+
+    /// The score (from the side to move's perspective) of the last completed iteration
+    pub(crate) fn eval(&self) -> i32 {
+        self.eval
+    }
+
+    /// The principal variation of the last completed iteration
+    pub(crate) fn pv(&self) -> Vec<Move> {
+        self.pv_table.mvs()[0..self.pv_table.length].iter().map(|&mv| Move::from(mv)).collect()
+    }
+
+    /// The last completed iteration
+    pub(crate) fn info(&self) -> SearchInfo {
+        SearchInfo { depth: self.depth, seldepth: self.sdepth, score: Score::from(self.eval), nodes: self.clock.signals().nodes(), tbhits: self.tbhits as u64,
+            time: self.clock.elapsed(), pv: self.pv().iter().map(|mv| mv.to_uci()).collect() }
+    }
+
+    /// The depth of the last completed iteration
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
     }
 
     // In addition, we a score to return in case there are no captures available to be played. -->> static evaluation
@@ -150,14 +297,16 @@ impl<'a> Search<'a> {
     /// If the lower bound from the stand pat(static evaluation) is always greater than or equal to beta, we can return the stand-pat(fail-soft)
     /// or beta(fail-hard) as a lower bound. Otherwise, the search continues
     /// https://www.chessprogramming.org/Quiescence_Search
-    fn quiescence(&mut self, mut alpha: i32, beta: i32, position: &mut Position) -> i32 {
+    /// qs_ply: the number of plies since the quiescence search started
+    fn quiescence(&mut self, mut alpha: i32, beta: i32, position: &mut Position, qs_ply: u8) -> i32 {
         if self.clock.stop(self.nodes as u64, 0) { return 0 } // depth would not be increasing here, hence it won't be the grund(reason) for a cutoff
         self.nodes+=1;
         
         let stand_pat = position.evaluate();
         if self.ply >= MAX_DEPTH { return stand_pat }
         // check if it's a draw
-        if position.is_draw() { return 0; }
+        if position.is_draw() { return self.draw_score(position); }
+        if let Some(value) = self.upcoming_repetition(&mut alpha, beta, position) { return value }
 
         
         // Probe the Transposition Table here
@@ -207,21 +356,68 @@ impl<'a> Search<'a> {
         alpha = alpha.max(eval);
         // standing pat
         if eval >= beta { return beta }
+
+        // Delta pruning: https://www.chessprogramming.org/Delta_Pruning
+        // If winning a queen (or promoting a pawn on top of it) still can't raise alpha, no capture can
+        if !in_check {
+            let promotion_rank = if position.turn == Color::White { RANK_7 } else { RANK_2 };
+            let mut big_delta = Piece::WQ.piece_value() + delta_margin();
+            if *position[Piece::pawn(position.turn)] & promotion_rank != 0 { big_delta += Piece::WQ.piece_value() - Piece::WP.piece_value() }
+            if eval + big_delta < alpha { return alpha }
+        }
     
         let mut best_move: Option<Move> = None;
         let mut best_value = eval;
+        let mut legal_mvs = 0;
 
         let killer_mvs = self.killer_moves.get_killers(self.ply).map(|m| if m == 0 {None} else {Some(Move::from(m))});
         let mut mvs = MovePicker::new(0, tt_move, killer_mvs);
+        // When in check, every evasion is searched (not only captures)
         if !in_check { mvs.skip_quiets(); }
         while let Some(mv) = mvs.next(&position, &self.history_table, &self.caphist, &self.conthist, &self.counter_mvs) {
-            if position.make_move(mv, MoveScope::AllMoves) {
-                self.ply += 1;
-                let value = -self.quiescence(-beta, -alpha, position);
-                position.undo_move(true);
-                
-                self.ply -= 1;
+            if !in_check {
+                // Captures that lose material (SEE < 0) are never worth resolving
+                if mvs.stage == Stage::BadCapture { break }
+
+                // Delta pruning (per capture): the captured piece and a margin can't raise alpha
+                if let Some(victim) = position.piece_at(mv.get_target()).filter(|_| mv.get_promotion().is_none()) {
+                    if eval + victim.piece_value() + delta_margin() <= alpha { continue }
+                }
+            }
+
+            let Some(value) = self.quiescence_move(mv, alpha, beta, position, qs_ply) else { continue };
+            legal_mvs += 1;
+
+            if value > best_value {
+                best_value = value;
+
+                if value > alpha {
+                    best_move = Some(mv);
+                    alpha = value;
+                }
 
+                if value >= beta {
+                    alpha = beta;
+                    break;
+                }
+            }
+        }
+
+        // No evasion: checkmate
+        if in_check && legal_mvs == 0 {
+            return -MATE_VALUE + self.ply as i32;
+        }
+
+        // Quiet moves that give check, only in the first plies of the quiescence search
+        if !in_check && alpha < beta && qs_ply < QS_CHECK_PLIES {
+            let mut quiets = MoveStack::<Move>::new();
+            position.gen_movement::<{ MoveScope::QUIETS }, Move>(&mut quiets);
+
+            for i in 0..quiets.count_mvs() {
+                let mv = *quiets.at(i).unwrap();
+                if !position.board.make_move(mv, MoveScope::AllMoves).is_some_and(|board| board.stm_in_check()) { continue }
+
+                let Some(value) = self.quiescence_move(mv, alpha, beta, position, qs_ply) else { continue };
                 if value > best_value {
                     best_value = value;
 
@@ -238,10 +434,6 @@ impl<'a> Search<'a> {
             }
         }
 
-        if in_check && best_value == -INFINITY {
-            return  -5000;
-        }
-
         let tt_flag = if best_value >= beta { HashFlag::LowerBound } else if best_value > old_alpha { HashFlag::Exact } else { HashFlag::UpperBound };
         self.tt.record(position.hash_key, 0, alpha, self.ss[self.ply].eval, self.ply, tt_flag, best_move, false);
 
@@ -249,15 +441,33 @@ impl<'a> Search<'a> {
         alpha
     }
 
+    /// Plays mv and searches it with the quiescence search, returns None if mv is illegal
+    fn quiescence_move(&mut self, mv: Move, alpha: i32, beta: i32, position: &mut Position, qs_ply: u8) -> Option<i32> {
+        if !position.make_move(mv, MoveScope::AllMoves) { return None }
+        self.ply += 1;
+        let value = -self.quiescence(-beta, -alpha, position, qs_ply + 1);
+        position.undo_move(true);
+        self.ply -= 1;
+
+        Some(value)
+    }
+
     fn in_check(position: &Position, color: Color) -> bool {
         let king_square = u64::from(position[Piece::king(color)].trailing_zeros());
         position.is_square_attacked(king_square, !color)
     }
 
+    /// The piece moved on the previous ply, and its target square (None at the root, and after a null move)
+    fn prev_move(&self) -> Option<(Piece, Square)> {
+        if self.ply == 0 { return None }
+        let prev = &self.ss[self.ply - 1];
+        prev.moved.zip(prev.mv.map(|mv| mv.get_target()))
+    }
+
     /// nmfp: Null Move forward prunning
     /// https://web.archive.org/web/20040427014629/http://brucemo.com/compchess/programming/nullmove.htm
     /// "If I do nothing here, can the opponent do anything?"
-    fn make_null_move(&mut self, beta: i32, depth: u8, mut position: &mut Position, pv: &mut PVTable, cutnode: bool, t: &mut Thread,) -> i32 {
+    fn make_null_move(&mut self, beta: i32, depth: u8, mut position: &mut Position, pv: &mut PVTable, cutnode: bool) -> i32 {
         self.ss[self.ply].moved = None;
         self.ss[self.ply].mv = None;
         self.ply += 1;
@@ -276,7 +486,7 @@ impl<'a> Search<'a> {
         position.nnue_push();
 
         self.last_move_was_null = true;
-        let score = -self.negamax::<NotPv>(-beta, -beta+1, depth, &mut position, pv, cutnode, t);
+        let score = -self.negamax::<NotPv>(-beta, -beta+1, depth, &mut position, pv, cutnode);
    
         // reverse all actions, after we're done
         self.ply -= 1;
@@ -291,7 +501,42 @@ impl<'a> Search<'a> {
         score
     }
 
-    pub(crate) fn negamax<NT: NodeType>(&mut self, mut alpha: i32, mut beta: i32, depth: u8, mut position: &mut Position, pv: &mut PVTable, cutnode: bool, t: &mut Thread) -> i32 {
+    /// Tries the captures that win at least (probcut_beta - eval) in the exchange, with a qsearch and then a reduced search against probcut_beta.
+    /// Returns the score of the first capture that beats probcut_beta (it is also stored in the TT)
+    fn probcut(&mut self, node: ProbCut, position: &mut Position, pv: &mut PVTable, cutnode: bool) -> Option<i32> {
+        let ProbCut { beta: probcut_beta, eval, depth, tt_move } = node;
+        let mut captures = MovePicker::new(probcut_beta - eval, tt_move.filter(|mv| mv.is_capture()), [None; 2]);
+        captures.skip_quiets();
+
+        while let Some(mv) = captures.next(position, &self.history_table, &self.caphist, &self.conthist, &self.counter_mvs) {
+            // every remaining capture fails the SEE threshold
+            if captures.stage == Stage::BadCapture { break }
+
+            let moved_piece = position.piece_at(mv.get_src()).unwrap();
+            if !position.make_move(mv, MoveScope::AllMoves) { continue }
+            self.ss[self.ply].moved = Some(moved_piece);
+            self.ss[self.ply].mv = Some(mv);
+            self.ply += 1;
+
+            // the qsearch is a cheap confirmation, before the reduced search
+            let mut value = -self.quiescence(-probcut_beta, -probcut_beta + 1, position, 0);
+            if value >= probcut_beta {
+                value = -self.negamax::<NotPv>(-probcut_beta, -probcut_beta + 1, depth - PROBCUT_REDUCTION, position, pv, !cutnode);
+            }
+
+            position.undo_move(true);
+            self.ply -= 1;
+
+            if value >= probcut_beta {
+                self.tt.record(position.hash_key, depth - (PROBCUT_REDUCTION - 1), value, eval, self.ply, HashFlag::LowerBound, Some(mv), false);
+                return Some(value);
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn negamax<NT: NodeType>(&mut self, mut alpha: i32, mut beta: i32, depth: u8, mut position: &mut Position, pv: &mut PVTable, cutnode: bool) -> i32 {
         if self.clock.stop(self.nodes as u64, depth) { return 0 }
         
         let mut depth = depth;
@@ -306,10 +551,10 @@ impl<'a> Search<'a> {
         if NT::ROOT { self.sdepth = 0} else { self.sdepth = self.sdepth.max(self.ply) };
         
         if depth == 0 || self.ply >= MAX_DEPTH {
-            return self.quiescence(alpha, beta, position);
+            return self.quiescence(alpha, beta, position, 0);
         }
         
-        if position.is_draw() { return 0; }
+        if position.is_draw() { return self.draw_score(position); }
 
         let mut old_pv = PVTable::default();
         let opv = &mut old_pv;
@@ -324,7 +569,8 @@ impl<'a> Search<'a> {
             beta = beta.min(MATE_VALUE - ply - 1);
             if alpha >= beta { return alpha }
 
-            if position.is_draw() { return 0 };
+            if position.is_draw() { return self.draw_score(position) };
+            if let Some(value) = self.upcoming_repetition(&mut alpha, beta, position) { return value }
         }
 
         // Transposition table lookup
@@ -364,11 +610,44 @@ impl<'a> Search<'a> {
             }
         };
 
-        // if !NT::ROOT && !in_signular_search {
-        //     // if let Some()
-        // }
+        // Probe the Syzygy tablebase (WDL)
+        // The result is exact for a draw, and a bound for a win/loss (we know the outcome, but not how fast it happens)
+        let mut tb_max_value = INFINITY;
+        let mut tb_best_value = -INFINITY;
+        if !NT::ROOT && !in_signular_search && self.tb.can_probe(position, depth) {
+            if let Some(wdl) = self.tb.tablebase.probe_wdl(position) {
+                self.tbhits += 1;
+
+                let tb_value = wdl.eval(self.ply);
+                let tb_flag = HashFlag::from(wdl);
+
+                let cutoff = match tb_flag {
+                    HashFlag::LowerBound => tb_value >= beta,
+                    HashFlag::UpperBound => tb_value <= alpha,
+                    _ => true,
+                };
+
+                if cutoff {
+                    // The probe is worth more than any search at this depth
+                    let tb_depth = (depth as usize + 6).min(MAX_DEPTH) as u8;
+                    self.tt.record(hash_key, tb_depth, tb_value, -INFINITY, self.ply, tb_flag, None, pv_node);
+                    return tb_value;
+                }
+
+                // Otherwise, the result only bounds the score of this (pv) node
+                if pv_node {
+                    if tb_flag == HashFlag::LowerBound {
+                        tb_best_value = tb_value;
+                        alpha = alpha.max(tb_value);
+                    } else {
+                        tb_max_value = tb_value;
+                    }
+                }
+            }
+        }
 
-        // Probe Tablebase (Skipped for now)
+        // Drawn KPK positions are known without tablebases (the bitbase is exact)
+        if !NT::ROOT && kpk::probe_board(position) == Some(false) { return self.draw_score(position) }
 
         // Static evaluation of this position
         let eval = if in_signular_search {
@@ -379,11 +658,12 @@ impl<'a> Search<'a> {
                 let tt_eval = entry.eval as i32;
                 
 
-                self.ss[self.ply].eval = if tt_eval == -INFINITY {
+                self.ss[self.ply].raw_eval = if tt_eval == -INFINITY {
                     position.evaluate()
                 } else {
                     tt_eval
                 };
+                self.ss[self.ply].eval = self.corrhist.correct(position, self.prev_move(), self.ss[self.ply].raw_eval);
 
                 match entry.flag {
                     HashFlag::Exact => tt_value,
@@ -392,11 +672,13 @@ impl<'a> Search<'a> {
                     _ => self.ss[self.ply].eval,
                 }
             } else {
-                self.ss[self.ply].eval = position.evaluate();
+                self.ss[self.ply].raw_eval = position.evaluate();
                 // self.tt.record(position.hash_key, -6, score, eval, ply, flag, mv, pv);
+                self.ss[self.ply].eval = self.corrhist.correct(position, self.prev_move(), self.ss[self.ply].raw_eval);
                 self.ss[self.ply].eval
             }
         } else {
+            self.ss[self.ply].raw_eval = -INFINITY;
             self.ss[self.ply].eval = -INFINITY;
             self.ss[self.ply].eval
         };
@@ -408,14 +690,14 @@ impl<'a> Search<'a> {
         // if !pv_node && !NT::ROOT && !stm_in_check && !in_signular_search {
             if !pv_node && !NT::ROOT && !stm_in_check && !in_signular_search {
             // Razoring: If evaluation + margin isn't better than alpha at the lowest depth, Go straight to quiescence search.
-            if eval < alpha - 392 - 297 * (depth * depth) as i32 {
-                let value = self.quiescence(alpha -1, alpha, position);
+            if eval < alpha - razor_base() - razor_depth() * (depth as i32 * depth as i32) {
+                let value = self.quiescence(alpha -1, alpha, position, 0);
                 if value < alpha { return value; }
             }
 
             // Reverse futility pruning
             // https://www.chessprogramming.org/Reverse_Futility_Pruning
-            let rfp_margin = 80 * depth as i32 - 55 * (improving as i32);
+            let rfp_margin = rfp_margin() * depth as i32 - rfp_improving() * (improving as i32);
             if depth <= 8 && eval - rfp_margin >= beta {
                 return beta;
             }
@@ -426,26 +708,36 @@ impl<'a> Search<'a> {
             let null_move_forward_pruning_conditions = depth > 3 && self.ply > 0 && ((eval + 70 * (improving as i32)) >= beta) && !position.possibly_zugzwang() && !self.last_move_was_null;
             if null_move_forward_pruning_conditions {
                 // Null move dynamic reduction based on depth
-                let r = (4 + depth/4).min(depth);
+                let r = (nmp_base() as u8 + depth / nmp_depth_divisor() as u8).min(depth);
                 
-                let value = self.make_null_move(beta, depth-r as u8, position, opv, !cutnode, t);
+                let value = self.make_null_move(beta, depth-r, position, opv, !cutnode);
                 if value >= beta {
                     return beta;
                 }
             }
 
-            let futility_margin = depth as i32 * (175 - 50 * improving as i32);
+            let futility_margin = depth as i32 * (futility_base() - futility_improving() * improving as i32);
 
             if depth < 7 && (eval - futility_margin) >= beta && eval < 10_000 { return eval }
+
+            // ProbCut: https://www.chessprogramming.org/ProbCut
+            // If a capture beats beta by a margin in a reduced search, the full depth search would (very likely) beat beta too
+            let probcut_beta = beta + probcut_margin() - probcut_improving() * improving as i32;
+            let tt_refutes = tt_entry.is_some_and(|entry| entry.depth + 3 >= depth && from_tt(entry.score, self.ply) < probcut_beta);
+            if cfg!(feature = "probcut") && cutnode && depth >= PROBCUT_DEPTH && beta.abs() < TB_WIN_IN_MAX_PLY && !tt_refutes {
+                if let Some(value) = self.probcut(ProbCut { beta: probcut_beta, eval, depth, tt_move }, position, opv, cutnode) {
+                    return value;
+                }
+            }
         }
         
         // Internal Iterative Reduction (IIR)
         // https://www.chessprogramming.org/Internal_Iterative_Reductions
-        // if !NT::ROOT && depth > 5 && tt_entry.is_some_and(|entry| entry.mv.is_none()) {
-            // depth -= 3;
-        // if !NT::ROOT && depth >= 4 && !in_signular_search && tt_entry.is_none() {
-        //     depth -= 1;
-        // }
+        // Without a TT move, the move ordering of this (pv/cut) node is poor, so a shallower search is cheaper, and fills the TT for the next iteration
+        let has_tt_move = tt_entry.is_some_and(|entry| entry.mv.is_some());
+        if cfg!(feature = "iir") && !NT::ROOT && (pv_node || cutnode) && depth >= IIR_DEPTH && !has_tt_move && !in_signular_search {
+            depth -= 1;
+        }
 
         
         // When beta - alpha > 1, it indicates that there is a significant gap between the two bounds. This gap suggests that there are possible values for the evaluation score that have not yet been fully explored or are still uncertain.
@@ -459,20 +751,22 @@ impl<'a> Search<'a> {
         let mut mvs_searched = 0;
 
         if tt_entry.is_none() && !!stm_in_check && excluded.is_none() {
-            self.tt.record(hash_key, depth, -INFINITY, eval, self.ply, HashFlag::UpperBound, None, false);
+            self.tt.record(hash_key, depth, -INFINITY, self.ss[self.ply].raw_eval, self.ply, HashFlag::UpperBound, None, false);
         }
 
 
         // let mut best_value = -INFINITY;
-        let mut best_value = -INFINITY;
+        let mut best_value = tb_best_value;
         let mut best_mv: Option<Move> = None;
         let original_alpha = alpha;
         let killer_mvs = self.killer_moves.get_killers(self.ply).map(|m| if m == 0 {None} else {Some(Move::from(m))});
+        // The best ranked tablebase move is searched first at the root
+        let tt_move = if NT::ROOT { tt_move.or(self.root_moves.first().copied()) } else { tt_move };
         let mut mvs = MovePicker::new(0, tt_move, killer_mvs);
 
         if mvs.stage == Stage::Done {
             if stm_in_check { return  -INFINITY + self.ply as i32 }
-            else { return 0 }
+            else { return self.draw_score(position) }
         }
 
         // 30 is a practical maximum number of quiet moves that can be generated in a chess position (MidGame)
@@ -484,6 +778,12 @@ impl<'a> Search<'a> {
                 // mvs_searched += 1;
                 continue;
             }
+            if NT::ROOT && !self.root_moves.is_empty() && !self.root_moves.contains(&mv) {
+                continue;
+            }
+            if NT::ROOT && self.excluded_root_moves.contains(&mv) {
+                continue;
+            }
             let mut extension = 0;
             // if possibly_singular  && tt_move.is_some_and(|tt_mv| tt_mv == mv) {
             if possibly_singular && tt_move.is_some_and(|tt_mv| tt_mv == mv) {
@@ -493,7 +793,7 @@ impl<'a> Search<'a> {
                 let se_depth = (depth)/2;
             
                 self.ss[self.ply].excluded = Some(mv);
-                let value = self.negamax::<NotPv>(se_beta -1, se_beta, se_depth, position, opv, cutnode, t);
+                let value = self.negamax::<NotPv>(se_beta -1, se_beta, se_depth, position, opv, cutnode);
                 self.ss[self.ply].excluded = None;
             
                 if value < se_beta {
@@ -502,6 +802,14 @@ impl<'a> Search<'a> {
             }
             
             let move_count_pruning = depth < 16 && mvs_searched as i32 > FUTILITY_MOVE_COUNTS[improving as usize][depth as usize];
+
+            // SEE pruning: at low depths, skip moves that lose too much material in the exchange on their target square.
+            // The margins grow with depth, since deeper searches are more likely to find a compensation
+            // best_value stays at -INFINITY until the first move is searched, so at least one move is always searched
+            if !NT::ROOT && depth <= SEE_PRUNING_DEPTH && best_value > -TB_WIN_IN_MAX_PLY {
+                let threshold = if mv.is_quiet() { -see_quiet_margin() * depth as i32 } else { -see_capture_margin() * (depth as i32 * depth as i32) };
+                if !position.see(&mv, threshold) { continue; }
+            }
             
             let stat_score = self.conthist.get_conth_at(&position, &mv, 0) + self.conthist.get_conth_at(&position, &mv, 1);
             let moved_piece = position.piece_at(mv.get_src()).unwrap();
@@ -519,11 +827,11 @@ impl<'a> Search<'a> {
 
                 
                 let value = match mvs_searched {
-                    0 => -self.negamax::<NotPv>(-beta, -alpha, depth -1, &mut position, opv, false, t),
+                    0 => -self.negamax::<NotPv>(-beta, -alpha, depth -1, &mut position, opv, false),
                     _ => {
                         // https://web.archive.org/web/20150212051846/http://www.glaurungchess.com/lmr.html
                         // condition for Late Move Reduction
-                        let mut r = reduction::<Pv>(improving, depth as usize, mvs_searched); // reduce by
+                        let mut r = reduction::<NT>(improving, depth as usize, mvs_searched); // reduce by
                         r += i16::from(!improving); 
                         // r += i16::from(tt_move.is_some_and(|m| m.is_capture()));
                         r += i16::from(cutnode);
@@ -538,17 +846,17 @@ impl<'a> Search<'a> {
                         //  && !pv_node && !position.stm_in_check() && !is_killer_mv && !improving;
 
                         let mut result = if (mvs_searched as u8 >= FULL_DEPTH_MOVE) && (depth >= REDUCTION_LIMIT) && ok_to_reduce {
-                            -self.negamax::<NotPv>(-(alpha + 1), -alpha, depth-r as u8, position, opv, false, t) // we're reducing by a depth of 2 here
+                            -self.negamax::<NotPv>(-(alpha + 1), -alpha, depth-r as u8, position, opv, false) // we're reducing by a depth of 2 here
                         } else {
                             alpha + 1 // Hack to ensure that full-depth search is done
                         };
                         
                         if result > alpha {
-                            result = -self.negamax::<NotPv>(-(alpha - 1), -alpha, depth-1, position, opv, false, t);
-                            // result = -self.negamax::<NotPv>(-(alpha + 1), -alpha, depth-1, position, opv, false, t);
+                            result = -self.negamax::<NotPv>(-(alpha - 1), -alpha, depth-1, position, opv, false);
+                            // result = -self.negamax::<NotPv>(-(alpha + 1), -alpha, depth-1, position, opv, false);
                             
                             if (result > alpha) && result < beta {
-                                result = -self.negamax::<NotPv>(-beta, -alpha, depth-1, position, opv, false, t);
+                                result = -self.negamax::<NotPv>(-beta, -alpha, depth-1, position, opv, false);
                             }
                         }
                         
@@ -576,7 +884,6 @@ impl<'a> Search<'a> {
                             self.killer_moves.store(depth as usize, &mv);
                         }
                         self.update_stats(&position, &best_mv, &quiet_mvs, &captures, depth);
-                        t.update_stats(&position, &best_mv, &quiet_mvs, &captures, depth);
                         alpha = beta;
                         flag = HashFlag::LowerBound;
                         
@@ -587,7 +894,6 @@ impl<'a> Search<'a> {
                 if mv.is_quiet() {
                     quiet_mvs.push(mv);
                     self.history_table.update(moved_piece, mv.get_src(), depth);
-                    t.history_table.update(moved_piece, mv.get_src(), depth);
                 } else if mv.is_capture() {
                     captures.push((mv, flag));
                 }
@@ -595,8 +901,26 @@ impl<'a> Search<'a> {
             }
         }
         
+        // A TB loss is the best this node can do, regardless of what the search found
+        best_value = best_value.min(tb_max_value);
+        alpha = alpha.min(tb_max_value);
+
         let tt_flag = if best_value >= beta { HashFlag::LowerBound } else if best_value > original_alpha { HashFlag::Exact } else { HashFlag::UpperBound };
-        self.tt.record(hash_key, depth, best_value, self.ss[self.ply].eval, self.ply, tt_flag, best_mv, pv_node);
+
+        // The static eval was wrong by (best_value - eval). Only learn from bounds that say so, and not from tactical (capture) results
+        let eval_is_wrong = match tt_flag {
+            HashFlag::LowerBound => best_value > self.ss[self.ply].eval,
+            HashFlag::UpperBound => best_value < self.ss[self.ply].eval,
+            _ => true,
+        };
+        if !stm_in_check && !in_signular_search && eval_is_wrong && best_mv.is_none_or(|mv| mv.is_quiet()) && best_value.abs() < TB_WIN_IN_MAX_PLY {
+            self.corrhist.update(position, self.prev_move(), depth, best_value, self.ss[self.ply].raw_eval);
+        }
+
+        // Without its best moves, the root's result is not the root's value
+        if !NT::ROOT || self.excluded_root_moves.is_empty() {
+            self.tt.record(hash_key, depth, best_value, self.ss[self.ply].raw_eval, self.ply, tt_flag, best_mv, pv_node);
+        }
         self.ss[self.ply].best_move = best_mv;
         alpha
     }
@@ -614,3 +938,171 @@ impl<'a> Search<'a> {
 
 
 }
+
+
+#[cfg(test)]
+mod search_tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::board::state::board::Board;
+    use crate::constants::TRICKY_POSITION;
+    use crate::syzygy::probe::{StubTablebase, WDL};
+    use crate::search::signals::SearchSignals;
+    use crate::tt::table::TTable;
+    use crate::uci::counter::Counter;
+
+    #[test]
+    fn should_use_tablebase_results_inside_the_search() {
+        let mut position = Position::with(Board::try_from("8/8/4k3/8/8/8/2PPK3/8 w - - 0 1").unwrap());
+        let tb = SyzygyConfig { tablebase: Arc::new(StubTablebase { largest: 5, wdl: WDL::Draw }), ..SyzygyConfig::default() };
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+
+        let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), tb);
+        search.iterative_deepening(4, &mut position);
+
+        // every position after the root is a (tablebase) draw
+        assert!(search.tbhits > 0);
+        assert_eq!(search.eval(), 0);
+        assert!(search.best_move().is_some());
+    }
+
+    #[test]
+    fn pruned_search_should_still_find_a_winning_capture() {
+        // deep enough for ProbCut and IIR to kick in (when built with the probcut and iir features)
+        let mut position = Position::with(Board::try_from("r3k3/1p3ppp/8/3q4/8/8/1P1R1PPP/4K3 w - - 0 1").unwrap());
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+
+        let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+        search.iterative_deepening(7, &mut position);
+
+        assert_eq!(search.best_move().map(|mv| mv.to_uci()), Some(String::from("d2d5")));
+    }
+
+    #[test]
+    fn workers_should_keep_their_histories_until_a_new_game() {
+        use crate::{board::piece::PieceType, move_logic::bitmove::MoveType};
+
+        let position = Position::with(Board::try_from("r3k3/1p3ppp/8/3q4/8/8/1P1R1PPP/4K3 w - - 0 1").unwrap());
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+        let mut worker = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+
+        // Rxd5 fails high: the search updates the capture history of a cutoff like this
+        let capture = Move::new(Square::D2 as u8, Square::D5 as u8, MoveType::Capture);
+        assert_eq!(worker.caphist.get(WR, Square::D5, PieceType::Q), 0);
+        worker.update_stats(&position, &Some(capture), &vec![], &vec![(capture, HashFlag::LowerBound)], 6);
+        let learnt = worker.caphist.get(WR, Square::D5, PieceType::Q);
+        assert!(learnt > 0);
+
+        // the next go starts from scratch, but with the same histories
+        worker.new_search(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+        assert_eq!((worker.nodes, worker.depth, worker.best_move()), (0, 0, None));
+        assert_eq!(worker.caphist.get(WR, Square::D5, PieceType::Q), learnt);
+
+        worker.clear();
+        assert_eq!(worker.caphist.get(WR, Square::D5, PieceType::Q), 0);
+    }
+
+    #[test]
+    fn limited_strength_should_cap_the_depth_and_be_reproducible() {
+        let play = |seed| {
+            let mut position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+            let table = TTable::default();
+            let signals = Arc::new(SearchSignals::default());
+
+            let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+            search.set_skill(Skill::from_level(0, seed));
+            search.iterative_deepening(10, &mut position);
+            (search.depth, search.best_move())
+        };
+
+        let (depth, mv) = play(3);
+        // the weakest level is capped at depth 1
+        assert_eq!(depth, 1);
+        assert!(mv.is_some());
+        assert_eq!(play(3), (depth, mv));
+    }
+
+    #[test]
+    fn draws_should_be_scored_from_the_root_side() {
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+        let contempt = Contempt { value: 30, dynamic: false };
+
+        // insufficient material (or a KPK bitbase draw), whichever side is at the root dislikes the draw
+        for fen in ["8/8/4k3/8/8/4K3/8/8 w - - 0 1", "8/8/4k3/8/8/4K3/8/8 b - - 0 1", "k7/8/8/8/8/8/P7/K7 w - - 0 1", "k7/8/8/8/8/8/P7/K7 b - - 0 1"] {
+            let mut position = Position::with(Board::try_from(fen).unwrap());
+            let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+            search.set_contempt(contempt);
+            search.iterative_deepening(2, &mut position);
+            assert!((-31..=-29).contains(&search.eval()), "{fen}: {}", search.eval());
+        }
+
+        // on odd plies, the opponent of the root side is to move, and likes the draw
+        let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+        search.root_contempt = 30;
+        let position = Position::with(Board::try_from("8/8/4k3/8/8/4K3/8/8 b - - 0 1").unwrap());
+        assert!((29..=31).contains(&search.draw_score(&position)));
+        search.root_color = Black;
+        assert!((-31..=-29).contains(&search.draw_score(&position)));
+    }
+
+    #[test]
+    fn draw_scores_should_be_jittered() {
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+        let position = Position::with(Board::try_from("8/8/4k3/8/8/4K3/8/8 w - - 0 1").unwrap());
+
+        let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+        let scores = (0..4).map(|nodes| { search.nodes = nodes; search.draw_score(&position) }).collect::<Vec<_>>();
+        assert_eq!(scores, [1, 1, -1, -1]);
+    }
+
+    #[test]
+    fn signals_should_stop_an_infinite_search_from_another_thread() {
+        let mut position = Position::with(Board::try_from(TRICKY_POSITION).unwrap());
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+        let mut clock = Clock::new(Arc::clone(&signals));
+        clock.set_limit(Counter::Infinite, position.turn);
+        clock.start();
+
+        let start = Instant::now();
+        let mut search = SearchWorker::new(table.get(), clock, SyzygyConfig::default());
+        thread::scope(|s| {
+            s.spawn(|| { thread::sleep(Duration::from_millis(100)); signals.stop(); });
+            search.iterative_deepening(MAX_DEPTH, &mut position);
+        });
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(search.best_move().is_some());
+        // every completed iteration (and the last, stopped, one) adds its nodes
+        assert_eq!(signals.nodes(), search.nodes as u64);
+    }
+
+    fn quiescence(fen: &str) -> i32 {
+        let mut position = Position::with(Board::try_from(fen).unwrap());
+        let table = TTable::default();
+        let signals = Arc::new(SearchSignals::default());
+
+        let mut search = SearchWorker::new(table.get(), Clock::new(Arc::clone(&signals)), SyzygyConfig::default());
+        search.quiescence(-INFINITY, INFINITY, &mut position, 0)
+    }
+
+    #[test]
+    fn quiescence_should_score_checkmates() {
+        // white is mated (fool's mate), and has no evasion
+        assert_eq!(quiescence("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), -MATE_VALUE);
+    }
+
+    #[test]
+    fn quiescence_should_search_quiet_checks_on_the_first_ply() {
+        // Ra8# is a quiet move
+        assert_eq!(quiescence("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), MATE_VALUE - 1);
+    }
+}
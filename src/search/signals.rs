@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+
+/// The search control shared (in an Arc) by everyone involved in a search: the search threads read it, and the UCI loop (or the Engine) writes it,
+/// possibly from another thread.
+#[derive(Debug, Default)]
+pub(crate) struct SearchSignals {
+    /// Every search thread returns as soon as it sees this
+    stop: AtomicBool,
+    /// `go ponder`: the search is not limited by time (or nodes, or depth) until the ponderhit
+    ponder: AtomicBool,
+    /// The nodes of all the search threads, every thread adds its own at the end of each iteration
+    nodes: AtomicU64,
+}
+
+impl SearchSignals {
+    /// Prepares the signals for a new search
+    pub(crate) fn reset(&self, ponder: bool) {
+        self.stop.store(false, Ordering::SeqCst);
+        self.ponder.store(ponder, Ordering::SeqCst);
+        self.nodes.store(0, Ordering::Relaxed);
+    }
+
+    /// Prepares the signals for a new search, but keeps a stop that was already requested: that stop was meant for this search
+    pub(crate) fn start(&self, ponder: bool) {
+        self.ponder.store(ponder, Ordering::SeqCst);
+        self.nodes.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    /// The opponent played the expected move: the search goes on, with its limits
+    pub(crate) fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::SeqCst);
+    }
+
+    pub(crate) fn pondering(&self) -> bool {
+        self.ponder.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn add_nodes(&self, nodes: u64) {
+        self.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+}
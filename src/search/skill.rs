@@ -0,0 +1,162 @@
+use crate::{board::piece::Piece, move_logic::bitmove::Move, utils::prng::PRNG};
+
+
+/// Every level below full strength: (depth cap, node limit).
+/// The depth cap and the node limit both end the iterative deepening, and the move is then picked among the best MULTIPV root moves,
+/// with a weakness of 120 - 2 * level (see Skill::pick).
+///
+/// | Level | Depth |   Nodes | Weakness |
+/// |------:|------:|--------:|---------:|
+/// |     0 |     1 |     400 |      120 |
+/// |     1 |     1 |     800 |      118 |
+/// |     2 |     2 |    1200 |      116 |
+/// |     3 |     2 |    1600 |      114 |
+/// |     4 |     3 |    2500 |      112 |
+/// |     5 |     3 |    3500 |      110 |
+/// |     6 |     4 |    5000 |      108 |
+/// |     7 |     4 |    7000 |      106 |
+/// |     8 |     5 |   10000 |      104 |
+/// |     9 |     5 |   14000 |      102 |
+/// |    10 |     6 |   20000 |      100 |
+/// |    11 |     6 |   28000 |       98 |
+/// |    12 |     7 |   40000 |       96 |
+/// |    13 |     8 |   56000 |       94 |
+/// |    14 |     9 |   80000 |       92 |
+/// |    15 |    10 |  110000 |       90 |
+/// |    16 |    11 |  160000 |       88 |
+/// |    17 |    12 |  230000 |       86 |
+/// |    18 |    14 |  330000 |       84 |
+/// |    19 |    16 |  500000 |       82 |
+/// |    20 |     - |       - |        - |
+///
+/// The depth and node limits are estimates, no gauntlet has measured the strength of any level yet. That is why UCI_Elo (and UCI_LimitStrength)
+/// are not offered: a level only gets an Elo once it is measured (see src/notes/validation.md).
+const LEVELS: [(usize, usize); Skill::MAX_LEVEL as usize] = [
+    (1, 400), (1, 800), (2, 1200), (2, 1600), (3, 2500),
+    (3, 3500), (4, 5000), (4, 7000), (5, 10000), (5, 14000),
+    (6, 20000), (6, 28000), (7, 40000), (8, 56000), (9, 80000),
+    (10, 110000), (11, 160000), (12, 230000), (14, 330000), (16, 500000),
+];
+
+
+/// Strength limiting (the Skill Level option).
+/// Below full strength, the search is cut short by a depth cap and a node limit, and the played move is a weighted random choice among the
+/// near-best root moves. The choice is made with the PRNG, so that the same seed always plays the same moves.
+#[derive(Debug)]
+pub(crate) struct Skill {
+    level: u8,
+    rng: PRNG,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::from_level(Self::MAX_LEVEL, 1)
+    }
+}
+
+impl Skill {
+    /// Full strength
+    pub(crate) const MAX_LEVEL: u8 = 20;
+    /// The number of root moves the weaker move is picked from
+    pub(crate) const MULTIPV: usize = 4;
+
+    pub(crate) fn from_level(level: u8, seed: u32) -> Self {
+        // xorshift must never be seeded with 0
+        Self { level: level.min(Self::MAX_LEVEL), rng: PRNG::new(seed.max(1)) }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.level < Self::MAX_LEVEL
+    }
+
+    /// The maximum depth of the iterative deepening
+    pub(crate) fn depth(&self) -> Option<usize> {
+        self.enabled().then(|| LEVELS[self.level as usize].0)
+    }
+
+    /// No new iteration is started once the search has used this many nodes
+    pub(crate) fn nodes(&self) -> Option<usize> {
+        self.enabled().then(|| LEVELS[self.level as usize].1)
+    }
+
+    fn weakness(&self) -> i32 {
+        120 - 2 * self.level as i32
+    }
+
+    /// Picks the index of the move to play among the root moves (sorted best first, with their scores).
+    /// Every move gets a random bonus that grows with its distance to the best score, and with the weakness, so that weak levels often
+    /// pick the second or third best move, while strong levels rarely stray from the best one.
+    /// https://github.com/official-stockfish/Stockfish/blob/master/src/search.cpp (Skill::pick_best)
+    pub(crate) fn pick(&mut self, lines: &[(Move, i32)]) -> usize {
+        let top = lines[0].1;
+        let delta = (top - lines[lines.len() - 1].1).min(Piece::PIECE_VALUES[0]);
+        let weakness = self.weakness();
+
+        let mut best = (0, i32::MIN);
+        for (i, &(_, score)) in lines.iter().enumerate() {
+            let noise = (self.rng.get_random_u64() % weakness as u64) as i32;
+            let push = (weakness * (top - score) + delta * noise) / 128;
+
+            if score + push > best.1 {
+                best = (i, score + push);
+            }
+        }
+
+        best.0
+    }
+}
+
+
+#[cfg(test)]
+mod skill_tests {
+    use super::*;
+
+    #[test]
+    fn levels_should_get_stronger() {
+        for pair in LEVELS.windows(2) {
+            assert!(pair[0].0 <= pair[1].0 && pair[0].1 < pair[1].1);
+        }
+    }
+
+    #[test]
+    fn levels_should_cap_the_depth_and_nodes() {
+        assert_eq!(Skill::from_level(25, 1).level(), Skill::MAX_LEVEL);
+        assert!(!Skill::from_level(25, 1).enabled());
+        assert_eq!(Skill::from_level(25, 1).depth(), None);
+        assert_eq!(Skill::from_level(4, 1).depth(), Some(3));
+        assert_eq!(Skill::from_level(4, 1).nodes(), Some(2500));
+    }
+
+    #[test]
+    fn picks_should_be_reproducible_with_a_seed() {
+        let lines = [(Move::from(1u16), 50), (Move::from(2u16), 35), (Move::from(3u16), 20), (Move::from(4u16), -200)];
+        let picks = |seed| { let mut skill = Skill::from_level(0, seed); (0..50).map(|_| skill.pick(&lines)).collect::<Vec<_>>() };
+
+        assert_eq!(picks(7), picks(7));
+        assert_ne!(picks(7), picks(8));
+        // the weakest level does not always play the best move
+        assert!(picks(7).iter().any(|&i| i != 0));
+    }
+
+    #[test]
+    fn the_weakest_level_should_play_the_same_picks_with_the_same_seed() {
+        let lines = [(Move::from(1u16), 50), (Move::from(2u16), 35), (Move::from(3u16), 20), (Move::from(4u16), -200)];
+        let mut skill = Skill::from_level(0, 3);
+
+        let picks = (0..12).map(|_| skill.pick(&lines)).collect::<Vec<_>>();
+        assert_eq!(picks, [0, 3, 1, 1, 1, 3, 1, 2, 1, 0, 0, 2]);
+    }
+
+    #[test]
+    fn stronger_levels_should_pick_the_best_move_more_often() {
+        let lines = [(Move::from(1u16), 30), (Move::from(2u16), 20), (Move::from(3u16), 10), (Move::from(4u16), 0)];
+        let best_picks = |level| { let mut skill = Skill::from_level(level, 99); (0..200).filter(|_| skill.pick(&lines) == 0).count() };
+
+        assert!(best_picks(19) > best_picks(0));
+    }
+}
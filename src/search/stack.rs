@@ -6,7 +6,10 @@ use crate::{board::piece::Piece, constants::MAX_PLY, move_logic::bitmove::Move};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub(crate) struct StackItem {
+    /// The static evaluation, after correction history
     pub(crate) eval: i32,
+    /// The static evaluation, before correction history (this is what goes into the TT)
+    pub(crate) raw_eval: i32,
     pub(crate) best_move: Option<Move>,
     pub(crate) excluded: Option<Move>,
     /// The moved piece (from src)
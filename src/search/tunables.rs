@@ -0,0 +1,203 @@
+#[cfg(all(feature = "tune", not(test)))]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Where a `tune` build keeps the current value of a parameter.
+/// Tests keep one value per thread, so that a test that sets a parameter does not change the searches of the tests running next to it
+#[cfg(all(feature = "tune", not(test)))]
+type Value = AtomicI32;
+#[cfg(all(feature = "tune", test))]
+type Value = std::thread::LocalKey<std::cell::Cell<i32>>;
+
+
+/// Declares the search parameters that can be tuned with SPSA: `name: default, min, max, step;`
+/// Every parameter is read through a function with the same name (e.g `rfp_margin()`).
+/// In normal builds, those functions return the default (a constant), while `tune` builds read an atomic
+/// that is set with `setoption`, so that the tuner can change them between games
+macro_rules! tunables {
+    ($($name:ident: $default:expr, $min:expr, $max:expr, $step:expr;)*) => {
+        $(
+            #[cfg(not(feature = "tune"))]
+            #[inline(always)]
+            pub(crate) const fn $name() -> i32 { $default }
+
+            #[cfg(all(feature = "tune", not(test)))]
+            #[inline(always)]
+            pub(crate) fn $name() -> i32 { values::$name.load(Ordering::Relaxed) }
+
+            #[cfg(all(feature = "tune", test))]
+            pub(crate) fn $name() -> i32 { values::$name.get() }
+        )*
+
+        #[cfg(feature = "tune")]
+        #[allow(non_upper_case_globals)]
+        mod values {
+            #[cfg(not(test))]
+            use std::sync::atomic::AtomicI32;
+            $(#[cfg(not(test))] pub(super) static $name: AtomicI32 = AtomicI32::new($default);)*
+
+            #[cfg(test)]
+            thread_local! {
+                $(pub(super) static $name: std::cell::Cell<i32> = const { std::cell::Cell::new($default) };)*
+            }
+        }
+
+        #[cfg(any(test, feature = "tune"))]
+        pub(crate) const TUNABLES: &[Tunable] = &[
+            $(Tunable {
+                name: stringify!($name), default: $default, min: $min, max: $max, step: $step, value: $name,
+                #[cfg(feature = "tune")]
+                cell: &values::$name,
+            },)*
+        ];
+    };
+}
+
+
+tunables! {
+    // Aspiration windows
+    // (the window is opened fully once the delta reaches aspiration_max_delta)
+    aspiration_delta: 25, 10, 60, 5;
+    aspiration_max_delta: 975, 500, 1500, 50;
+
+    // Razoring: eval < alpha - razor_base - razor_depth * depth²
+    razor_base: 392, 200, 600, 20;
+    razor_depth: 297, 150, 450, 15;
+
+    // Reverse futility pruning: eval - (rfp_margin * depth - rfp_improving) >= beta
+    rfp_margin: 80, 40, 120, 5;
+    rfp_improving: 55, 20, 90, 5;
+
+    // Null move pruning: R = nmp_base + depth / nmp_depth_divisor
+    nmp_base: 4, 2, 6, 1;
+    nmp_depth_divisor: 4, 2, 8, 1;
+
+    // Futility pruning: eval - depth * (futility_base - futility_improving) >= beta
+    futility_base: 175, 100, 250, 10;
+    futility_improving: 50, 20, 80, 5;
+
+    // Quiescence delta pruning
+    delta_margin: 200, 100, 400, 20;
+
+    // SEE pruning: quiets below -see_quiet_margin * depth, and captures below -see_capture_margin * depth² are skipped
+    see_quiet_margin: 64, 30, 120, 5;
+    see_capture_margin: 19, 8, 40, 2;
+
+    // ProbCut: beta + probcut_margin - probcut_improving
+    probcut_margin: 200, 100, 350, 15;
+    probcut_improving: 50, 20, 100, 5;
+
+    // Late move reductions: log2(depth) * log2(moves) / (lmr_divisor / 100), at non-PV nodes
+    lmr_divisor: 195, 150, 300, 10;
+}
+
+
+/// A tunable search parameter, as described to the tuner
+#[cfg(any(test, feature = "tune"))]
+#[derive(Debug)]
+pub(crate) struct Tunable {
+    pub(crate) name: &'static str,
+    pub(crate) default: i32,
+    pub(crate) min: i32,
+    pub(crate) max: i32,
+    pub(crate) step: i32,
+    /// The current value
+    pub(crate) value: fn() -> i32,
+    #[cfg(feature = "tune")]
+    cell: &'static Value,
+}
+
+#[cfg(any(test, feature = "tune"))]
+impl Tunable {
+    /// Finds a parameter by its (case insensitive) UCI option name
+    pub(crate) fn find(name: &str) -> Option<&'static Tunable> {
+        TUNABLES.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Sets the parameter, values outside of [min, max] are clamped
+    #[cfg(all(feature = "tune", not(test)))]
+    pub(crate) fn set(&self, value: i32) {
+        self.cell.store(value.clamp(self.min, self.max), Ordering::Relaxed);
+    }
+
+    #[cfg(all(feature = "tune", test))]
+    pub(crate) fn set(&self, value: i32) {
+        self.cell.set(value.clamp(self.min, self.max));
+    }
+
+    pub(crate) fn uci_option(&self) -> String {
+        format!("option name {} type spin default {} min {} max {}", self.name, self.default, self.min, self.max)
+    }
+
+    /// OpenBench SPSA input: name, int, value, min, max, c_end, r_end
+    pub(crate) fn openbench(&self) -> String {
+        format!("{}, int, {}, {}, {}, {}, 0.002", self.name, (self.value)(), self.min, self.max, self.step)
+    }
+
+    /// Weather-Factory SPSA input (one entry of the config object)
+    pub(crate) fn weather_factory(&self) -> String {
+        format!("\"{}\": {{\"value\": {}, \"min_value\": {}, \"max_value\": {}, \"step\": {}}}", self.name, (self.value)(), self.min, self.max, self.step)
+    }
+}
+
+/// Every parameter, in the Weather-Factory config format
+#[cfg(any(test, feature = "tune"))]
+pub(crate) fn weather_factory() -> String {
+    format!("{{\n{}\n}}", TUNABLES.iter().map(|t| format!("    {}", t.weather_factory())).collect::<Vec<_>>().join(",\n"))
+}
+
+
+#[cfg(test)]
+mod tunables_tests {
+    use super::*;
+
+    #[test]
+    fn defaults_should_be_within_their_range() {
+        for tunable in TUNABLES {
+            assert!(tunable.min <= tunable.default && tunable.default <= tunable.max, "{}", tunable.name);
+            assert!(tunable.step > 0 && tunable.step <= tunable.max - tunable.min, "{}", tunable.name);
+        }
+    }
+
+    #[test]
+    fn names_should_be_unique() {
+        for (i, tunable) in TUNABLES.iter().enumerate() {
+            assert!(TUNABLES[i + 1..].iter().all(|t| !t.name.eq_ignore_ascii_case(tunable.name)), "{}", tunable.name);
+        }
+    }
+
+    #[test]
+    fn should_print_spsa_inputs() {
+        let rfp = Tunable::find("RFP_MARGIN").unwrap();
+        assert_eq!(rfp.uci_option(), "option name rfp_margin type spin default 80 min 40 max 120");
+        assert_eq!(rfp.openbench(), "rfp_margin, int, 80, 40, 120, 5, 0.002");
+        assert!(weather_factory().contains("\"rfp_margin\": {\"value\": 80, \"min_value\": 40, \"max_value\": 120, \"step\": 5}"));
+        assert!(Tunable::find("unknown").is_none());
+    }
+
+    #[cfg(feature = "tune")]
+    #[test]
+    fn should_set_and_clamp_values() {
+        let tunable = Tunable::find("razor_base").unwrap();
+        tunable.set(450);
+        assert_eq!(razor_base(), 450);
+        tunable.set(10_000);
+        assert_eq!(razor_base(), tunable.max);
+        tunable.set(tunable.default);
+    }
+
+    #[cfg(feature = "tune")]
+    #[test]
+    fn late_move_reductions_should_follow_lmr_divisor() {
+        use crate::{search::constants::{NotPv, Pv}, utils::lmr::reduction};
+
+        let tunable = Tunable::find("lmr_divisor").unwrap();
+        tunable.set(tunable.min);
+        let aggressive = reduction::<NotPv>(true, 20, 30);
+        tunable.set(tunable.max);
+        assert!(reduction::<NotPv>(true, 20, 30) < aggressive);
+        tunable.set(tunable.default);
+
+        // PV nodes are never reduced by the table
+        assert_eq!(reduction::<Pv>(true, 20, 30), 0);
+    }
+}
@@ -0,0 +1,6 @@
+#include "./imports/fathom/src/tbprobe.h"
+
+// Static wrappers
+
+unsigned int tb_probe_wdl__extern(uint64_t _white, uint64_t _black, uint64_t _kings, uint64_t _queens, uint64_t _rooks, uint64_t _bishops, uint64_t _knights, uint64_t _pawns, unsigned int _rule50, unsigned int _castling, unsigned int _ep, bool _turn) { return tb_probe_wdl(_white, _black, _kings, _queens, _rooks, _bishops, _knights, _pawns, _rule50, _castling, _ep, _turn); }
+unsigned int tb_probe_root__extern(uint64_t _white, uint64_t _black, uint64_t _kings, uint64_t _queens, uint64_t _rooks, uint64_t _bishops, uint64_t _knights, uint64_t _pawns, unsigned int _rule50, unsigned int _castling, unsigned int _ep, bool _turn, unsigned int *_results) { return tb_probe_root(_white, _black, _kings, _queens, _rooks, _bishops, _knights, _pawns, _rule50, _castling, _ep, _turn, _results); }
@@ -0,0 +1,349 @@
+use std::ffi::CString;
+use std::fmt::Debug;
+use std::ptr;
+
+use crate::move_logic::move_stack::MoveStack;
+use crate::move_scope::MoveScope;
+use crate::squares::Square;
+use crate::{move_logic::bitmove::Move, board::position::Position};
+use crate::board::piece::{Piece::*, PieceType};
+use crate::color::Color::{self, *};
+
+use super::bindings::{tb_probe_root, TB_LARGEST, TB_MAX_MOVES, TB_PROMOTES_BISHOP, TB_PROMOTES_KNIGHT, TB_PROMOTES_QUEEN, TB_PROMOTES_ROOK, TB_RESULT_DTZ_MASK, TB_RESULT_DTZ_SHIFT, TB_RESULT_FAILED, TB_RESULT_FROM_MASK, TB_RESULT_FROM_SHIFT, TB_RESULT_PROMOTES_MASK, TB_RESULT_PROMOTES_SHIFT, TB_RESULT_TO_MASK, TB_RESULT_TO_SHIFT, TB_RESULT_WDL_MASK, TB_RESULT_WDL_SHIFT};
+use super::{bindings::{tb_init, tb_probe_wdl, TB_BLESSED_LOSS, TB_CURSED_WIN, TB_DRAW, TB_LOSS, TB_WIN}, SyZyGyBoard};
+use super::probe::{TBRootMove, Tablebase, WDL};
+
+
+impl TryFrom<u32> for WDL {
+    type Error = &'static str;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            TB_WIN => Ok(WDL::Win),
+            TB_CURSED_WIN => Ok(WDL::CursedWin),
+            TB_DRAW => Ok(WDL::Draw),
+            TB_BLESSED_LOSS => Ok(WDL::BlessedLoss),
+            TB_LOSS => Ok(WDL::Loss),
+            _ => Err("Unrecognized value for win_draw_loss"),
+        }
+    }
+}
+
+/// A position, the way Fathom expects it: one bitboard per colour and per piece type (square 0 is a1, just like Square),
+/// the halfmove clock, castling rights, the enpassant square (0 if none), and the side to move (true for white)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FathomPosition {
+    white: u64, black: u64, kings: u64, queens: u64, rooks: u64, bishops: u64, knights: u64, pawns: u64,
+    rule50: u32,
+    castling: u32,
+    ep: u32,
+    turn: bool,
+}
+
+impl<'a> From<&SyZyGyBoard<'a>> for FathomPosition {
+    fn from(board: &SyZyGyBoard<'a>) -> Self {
+        Self {
+            white: board.get_occupancy(White),
+            black: board.get_occupancy(Black),
+            kings: *board[WK] | *board[BK],
+            queens: *board[WQ] | *board[BQ],
+            rooks: *board[WR] | *board[BR],
+            bishops: *board[WB] | *board[BB],
+            knights: *board[WN] | *board[BN],
+            pawns: *board[WP] | *board[BP],
+            rule50: board.fifty.iter().map(|&f| f as u32).sum(),
+            castling: board.castling_rights.bits() as u32,
+            ep: board.enpassant.map(|sq| sq as u32).unwrap_or(0),
+            turn: board.turn == White,
+        }
+    }
+}
+
+
+/// The probing functions of Fathom.
+/// Fathom is the only real implementation, tests use a mock so that they can run without tablebase files
+pub(crate) trait Prober {
+    /// The number of pieces of the largest available tablebase (0 if none has been loaded)
+    fn largest(&self) -> u32;
+    /// One of TB_LOSS..=TB_WIN, or TB_RESULT_FAILED
+    fn probe_wdl(&self, pos: &FathomPosition) -> u32;
+    /// The best move (TB_RESULT), and optionally the result of every legal move (terminated by TB_RESULT_FAILED)
+    fn probe_root(&self, pos: &FathomPosition, results: Option<&mut [u32; TB_MAX_MOVES as usize]>) -> u32;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Fathom;
+
+impl Prober for Fathom {
+    fn largest(&self) -> u32 {
+        unsafe { TB_LARGEST }
+    }
+
+    fn probe_wdl(&self, pos: &FathomPosition) -> u32 {
+        unsafe { tb_probe_wdl(pos.white, pos.black, pos.kings, pos.queens, pos.rooks, pos.bishops, pos.knights, pos.pawns, pos.rule50, pos.castling, pos.ep, pos.turn) }
+    }
+
+    fn probe_root(&self, pos: &FathomPosition, results: Option<&mut [u32; TB_MAX_MOVES as usize]>) -> u32 {
+        let results = results.map(|r| r.as_mut_ptr()).unwrap_or(ptr::null_mut());
+        unsafe { tb_probe_root(pos.white, pos.black, pos.kings, pos.queens, pos.rooks, pos.bishops, pos.knights, pos.pawns, pos.rule50, pos.castling, pos.ep, pos.turn, results) }
+    }
+}
+
+
+/// Syzygy tablebases, probed through Fathom
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FathomTablebase<P: Prober = Fathom> {
+    prober: P,
+}
+
+impl Default for FathomTablebase {
+    fn default() -> Self {
+        Self::with(Fathom)
+    }
+}
+
+impl FathomTablebase {
+    pub(crate) fn init(path: &str) -> Self {
+        unsafe {
+            let p = CString::new(path).unwrap();
+            let res = tb_init(p.as_ptr());
+            assert!(res, "Could not initialize Syzygy tablebase at: {path}");
+        }
+
+        Self::default()
+    }
+}
+
+impl<P: Prober> FathomTablebase<P> {
+    pub(crate) fn with(prober: P) -> Self {
+        Self { prober }
+    }
+
+    /// Any castling-free position, with no more pieces than the largest available tablebase
+    fn board<'b>(&self, position: &'b Position) -> Option<SyZyGyBoard<'b>> {
+        let board = SyZyGyBoard::try_from(position).ok()?;
+        (board.get_occupancy(Both).count_ones() <= self.prober.largest()).then_some(board)
+    }
+
+    /// tb_probe_wdl probes the Win-Draw-Loss (WDL) table for a given position.
+    pub(crate) fn probe_wdl(&self, board: &Position) -> Option<WDL> {
+        let board = self.board(board)?;
+        // Fathom only probes the WDL tables with a zero halfmove clock (the result is exact right after a capture/pawn move).
+        // Anywhere else, a win could still be cursed by the 50-move rule, which only the DTZ tables (at the root) can tell
+        let pos = FathomPosition { rule50: 0, ..FathomPosition::from(&board) };
+
+        WDL::try_from(self.prober.probe_wdl(&pos)).ok()
+    }
+
+    /// Ranks the legal moves at the root: Only the moves that keep the best outcome are returned, best first.
+    /// The DTZ tables are used when available (shortest win, longest loss first), and the WDL tables otherwise (unordered)
+    pub(crate) fn rank_root_moves(&self, board: &Position) -> Option<Vec<TBRootMove>> {
+        let moves = self.root_moves_dtz(board).or_else(|| self.root_moves_wdl(board))?;
+        let ranked = TBRootMove::rank(moves);
+
+        (!ranked.is_empty()).then_some(ranked)
+    }
+
+    fn root_moves_dtz(&self, board: &Position) -> Option<Vec<TBRootMove>> {
+        let board = self.board(board)?;
+        let mut results = [TB_RESULT_FAILED; TB_MAX_MOVES as usize];
+        if self.prober.probe_root(&FathomPosition::from(&board), Some(&mut results)) == TB_RESULT_FAILED { return None }
+
+        results.iter().take_while(|&&result| result != TB_RESULT_FAILED)
+            .map(|&result| TBResult::try_from((board, result)).ok().map(|r| TBRootMove { mv: r.mv, wdl: r.wdl, dtz: Some(r.dtz) }))
+            .collect()
+    }
+
+    /// Fallback for missing DTZ tables: every legal move is probed in the WDL tables
+    fn root_moves_wdl(&self, board: &Position) -> Option<Vec<TBRootMove>> {
+        self.board(board)?;
+        let mut position = board.clone();
+        let mut moves = MoveStack::<Move>::new();
+        board.gen_movement::<{MoveScope::ALL}, Move>(&mut moves);
+
+        let mut root_moves = Vec::with_capacity(moves.count_mvs());
+        for mv in moves {
+            if !position.make_move(mv, MoveScope::AllMoves) { continue }
+            let wdl = self.probe_wdl(&position);
+            position.undo_move(true);
+
+            // the probe is from the opponent's perspective
+            root_moves.push(TBRootMove { mv, wdl: wdl?.flip(), dtz: None });
+        }
+
+        Some(root_moves)
+    }
+}
+
+impl<P: Prober + Debug + Send + Sync> Tablebase for FathomTablebase<P> {
+    fn largest(&self) -> u32 {
+        self.prober.largest()
+    }
+
+    fn probe_wdl(&self, board: &Position) -> Option<WDL> {
+        FathomTablebase::probe_wdl(self, board)
+    }
+
+    fn probe_root(&self, board: &Position) -> Option<Vec<TBRootMove>> {
+        self.rank_root_moves(board)
+    }
+}
+
+pub(crate) struct TBResult {
+    pub(crate) mv: Move, pub(crate) wdl: WDL, pub(crate) dtz: u32
+}
+
+
+
+impl<'a> TryFrom<(SyZyGyBoard<'a>, u32)> for TBResult {
+    type Error = &'static str;
+
+    fn try_from((board, result): (SyZyGyBoard, u32)) -> Result<Self, Self::Error> {
+        if result == TB_RESULT_FAILED {
+            return Err("Probe failed")
+        }
+
+        let pre_wdl = (result & TB_RESULT_WDL_MASK) >> TB_RESULT_WDL_SHIFT;
+        let wdl = WDL::try_from(pre_wdl).unwrap_or(WDL::Draw);
+
+        let from = Square::from(((result & TB_RESULT_FROM_MASK) >> TB_RESULT_FROM_SHIFT) as u64);
+        let to = Square::from(((result & TB_RESULT_TO_MASK) >> TB_RESULT_TO_SHIFT) as u64);
+        let promotion = (result & TB_RESULT_PROMOTES_MASK) >> TB_RESULT_PROMOTES_SHIFT;
+
+        let promoted_to = match promotion {
+            TB_PROMOTES_QUEEN => Some(PieceType::Q),
+            TB_PROMOTES_ROOK => Some(PieceType::R),
+            TB_PROMOTES_BISHOP => Some(PieceType::B),
+            TB_PROMOTES_KNIGHT => Some(PieceType::N),
+            _ => None
+        };
+
+        let mut moves = MoveStack::<Move>::new();
+        board.gen_movement::<{MoveScope::ALL}, Move>(&mut moves);
+        let dtz = (result & TB_RESULT_DTZ_MASK) >> TB_RESULT_DTZ_SHIFT;
+
+        moves.into_iter().find(|m| m.get_src() == from && m.get_target() == to && 
+            m.get_promotion() == promoted_to
+        ).map(|mv| Self {mv, wdl, dtz}).ok_or("")
+    }
+}
+
+
+#[cfg(test)]
+mod fathom_tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::board::state::board::Board;
+    use crate::tt::flag::HashFlag;
+
+    #[test]
+    fn should_keep_cursed_wins_and_blessed_losses_apart_from_draws() {
+        assert_eq!(WDL::try_from(TB_CURSED_WIN), Ok(WDL::CursedWin));
+        assert_eq!(WDL::try_from(TB_BLESSED_LOSS), Ok(WDL::BlessedLoss));
+        assert_eq!(HashFlag::from(WDL::CursedWin), HashFlag::Exact);
+    }
+
+
+    /// Answers every probe with the same results, and remembers the last position it was asked about
+    #[derive(Debug)]
+    struct MockProber {
+        largest: u32,
+        wdl: u32,
+        /// The (per move) results of probe_root, when the DTZ tables are "available"
+        root: Option<Vec<u32>>,
+        last: Cell<Option<FathomPosition>>,
+    }
+
+    impl MockProber {
+        fn new(wdl: u32, root: Option<Vec<u32>>) -> Self {
+            Self { largest: 5, wdl, root, last: Cell::new(None) }
+        }
+    }
+
+    impl Prober for MockProber {
+        fn largest(&self) -> u32 {
+            self.largest
+        }
+
+        fn probe_wdl(&self, pos: &FathomPosition) -> u32 {
+            self.last.set(Some(*pos));
+            self.wdl
+        }
+
+        fn probe_root(&self, pos: &FathomPosition, results: Option<&mut [u32; TB_MAX_MOVES as usize]>) -> u32 {
+            self.last.set(Some(*pos));
+            let Some(root) = &self.root else { return TB_RESULT_FAILED };
+
+            if let Some(results) = results {
+                results[..root.len()].copy_from_slice(root);
+                results[root.len()] = TB_RESULT_FAILED;
+            }
+            root[0]
+        }
+    }
+
+    fn position(fen: &str, fifty: [u8; 2]) -> Position {
+        let mut board = Board::try_from(fen).unwrap();
+        board.fifty = fifty;
+        Position::with(board)
+    }
+
+    /// A TB_RESULT for the move src -> tgt
+    fn result(wdl: u32, src: Square, tgt: Square, dtz: u32) -> u32 {
+        wdl << TB_RESULT_WDL_SHIFT | (tgt as u32) << TB_RESULT_TO_SHIFT | (src as u32) << TB_RESULT_FROM_SHIFT | dtz << TB_RESULT_DTZ_SHIFT
+    }
+
+    #[test]
+    fn should_pass_the_real_bitboards_to_fathom() {
+        let position = position("8/8/4k3/8/3pP3/8/4K3/8 b - e3 0 1", [3, 4]);
+        let board = SyZyGyBoard::try_from(&position).unwrap();
+        let pos = FathomPosition::from(&board);
+
+        assert_eq!(pos.white, (1 << Square::E2 as u64) | (1 << Square::E4 as u64));
+        assert_eq!(pos.black, (1 << Square::E6 as u64) | (1 << Square::D4 as u64));
+        assert_eq!(pos.kings, (1 << Square::E2 as u64) | (1 << Square::E6 as u64));
+        assert_eq!(pos.pawns, (1 << Square::E4 as u64) | (1 << Square::D4 as u64));
+        assert_eq!(pos.queens | pos.rooks | pos.bishops | pos.knights, 0);
+        // Fathom numbers squares from a1 (0) to h8 (63), just like Square
+        assert_eq!(pos.ep, Square::E3 as u32);
+        assert_eq!(pos.rule50, 7);
+        assert!(!pos.turn);
+    }
+
+    #[test]
+    fn should_probe_castling_free_positions_with_a_nonzero_halfmove_clock() {
+        let tb = FathomTablebase::with(MockProber::new(TB_WIN, None));
+
+        assert_eq!(tb.probe_wdl(&position("8/8/4k3/8/8/8/3PK3/8 w - - 0 1", [0, 9])), Some(WDL::Win));
+        // Fathom's WDL probe fails with a non-zero halfmove clock
+        assert_eq!(tb.prober.last.get().unwrap().rule50, 0);
+
+        assert_eq!(tb.probe_wdl(&position("4k3/8/8/8/8/8/3P4/4K2R w K - 0 1", [0, 0])), None);
+        // more pieces than the largest tablebase
+        assert_eq!(tb.probe_wdl(&position("4k3/8/8/8/8/8/1PPP4/4K2R w - - 0 1", [0, 0])), None);
+    }
+
+    #[test]
+    fn should_pass_the_halfmove_clock_to_the_root_probe() {
+        let root = vec![result(TB_DRAW, Square::E2, Square::E1, 0), result(TB_WIN, Square::D2, Square::D4, 1), result(TB_WIN, Square::E2, Square::F3, 9)];
+        let tb = FathomTablebase::with(MockProber::new(TB_WIN, Some(root)));
+
+        let ranked = tb.rank_root_moves(&position("8/8/4k3/8/8/8/3PK3/8 w - - 0 1", [12, 20])).unwrap();
+        assert_eq!(tb.prober.last.get().unwrap().rule50, 32);
+        assert_eq!(ranked.iter().map(|m| (m.mv.get_src(), m.mv.get_target(), m.dtz)).collect::<Vec<_>>(),
+            vec![(Square::D2, Square::D4, Some(1)), (Square::E2, Square::F3, Some(9))]);
+    }
+
+    #[test]
+    fn should_rank_root_moves_with_the_wdl_tables_when_dtz_is_missing() {
+        // every reply is lost for the opponent
+        let tb = FathomTablebase::with(MockProber::new(TB_LOSS, None));
+        let position = position("8/8/4k3/8/8/8/3PK3/8 w - - 0 1", [0, 0]);
+
+        let ranked = tb.rank_root_moves(&position).unwrap();
+        assert!(ranked.iter().all(|m| m.wdl == WDL::Win && m.dtz.is_none()));
+        // 2 pawn moves, and 7 king moves
+        assert_eq!(ranked.len(), 9);
+    }
+}
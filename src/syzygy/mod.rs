@@ -1,17 +1,23 @@
+#[cfg(feature = "syzygy")]
 use std::ops::Deref;
 
-use bindings::TB_LARGEST;
-
-use crate::{board::position::Position, color::Color};
+#[cfg(feature = "syzygy")]
+use crate::board::position::Position;
 
+/// Generated by bindgen (only regenerated with the `bindgen` feature)
+#[cfg(feature = "syzygy")]
 #[allow(dead_code, non_camel_case_types, non_upper_case_globals, non_snake_case)]
 mod bindings;
+#[cfg(feature = "syzygy")]
+pub(crate) mod fathom;
 pub(crate) mod probe;
 
-#[derive(Debug)]
+#[cfg(feature = "syzygy")]
+#[derive(Debug, Clone, Copy)]
 struct SyZyGyBoard<'a>(&'a Position);
 
 
+#[cfg(feature = "syzygy")]
 impl<'a> Deref for SyZyGyBoard<'a> {
     type Target = Position;
 
@@ -21,13 +27,13 @@ impl<'a> Deref for SyZyGyBoard<'a> {
 }
 
 
+#[cfg(feature = "syzygy")]
 impl<'a> TryFrom <&'a Position> for SyZyGyBoard<'a> {
     type Error = &'static str;
 
     fn try_from(value: &'a Position) -> Result<Self, Self::Error> {
-        // Maximum number of pieces supported for this Syzygy tablebase
-        let max_pieces = unsafe { TB_LARGEST };
-        if value.fifty.iter().sum::<u8>() == 0 && value.castling_rights.is_empty() && value.get_occupancy(Color::Both).count_ones() <= max_pieces {
+        // Syzygy tablebases do not cover positions with castling rights (the piece limit depends on the loaded tables, see TableBase)
+        if value.castling_rights.is_empty() {
             return Ok(SyZyGyBoard(value))
         }
 
@@ -1,153 +1,207 @@
-use std::ffi::CString;
-use std::ptr;
+use std::fmt::Debug;
+use std::sync::Arc;
 
-use crate::constants::MATE_VALUE;
-use crate::move_logic::move_stack::MoveStack;
-use crate::move_scope::MoveScope;
-use crate::squares::Square;
+use crate::constants;
 use crate::{move_logic::bitmove::Move, board::position::Position};
-use crate::board::piece::{Piece::*, PieceType};
-use crate::color::Color::{self, *};
+use crate::color::Color::*;
 
-use super::bindings::{tb_probe_root, TB_PROMOTES_BISHOP, TB_PROMOTES_KNIGHT, TB_PROMOTES_QUEEN, TB_PROMOTES_ROOK, TB_RESULT_DTZ_MASK, TB_RESULT_DTZ_SHIFT, TB_RESULT_FAILED, TB_RESULT_FROM_MASK, TB_RESULT_FROM_SHIFT, TB_RESULT_PROMOTES_MASK, TB_RESULT_PROMOTES_SHIFT, TB_RESULT_TO_MASK, TB_RESULT_TO_SHIFT, TB_RESULT_WDL_MASK, TB_RESULT_WDL_SHIFT};
-use super::{bindings::{tb_init, tb_probe_wdl, TB_BLESSED_LOSS, TB_CURSED_WIN, TB_DRAW, TB_LOSS, TB_WIN}, SyZyGyBoard};
-
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// Win/Draw/Loss from the side to move's perspective, ordered from worst to best.
+/// Cursed wins and blessed losses are wins/losses that the 50-move rule turns into draws.
+/// Only Fathom (and the test stubs) produce results, so the variants are unused without the `syzygy` feature
+#[cfg_attr(not(any(test, feature = "syzygy")), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum WDL {
-    Win, Loss, Draw
-}
-
-impl TryFrom<u32> for WDL {
-    type Error = &'static str;
-
-    fn try_from(value: u32) -> Result<Self, Self::Error> {
-        match value {
-            TB_WIN => Ok(WDL::Win),
-            TB_LOSS => Ok(WDL::Loss),
-            TB_DRAW | TB_CURSED_WIN | TB_BLESSED_LOSS => Ok(WDL::Draw),
-            _ => Err("Unrecognized value for win_draw_loss"),
-        }
-    }
+    Loss, BlessedLoss, Draw, CursedWin, Win
 }
 
 impl WDL {
+    /// The score of this result (for the side to move), ply plies away from the root.
+    /// Wins/Losses are scored as TB wins/losses (not mates), so that shorter TB wins are preferred, and real mates are preferred over all of them
     pub(crate) fn eval(&self, ply: usize) -> i32 {
         let ply = ply as i32;
         match self {
-            Self::Win => MATE_VALUE - ply,
-            Self::Loss => -MATE_VALUE + ply,
+            Self::Win => constants::TB_WIN - ply,
+            Self::Loss => -constants::TB_WIN + ply,
+            // Draws by the 50-move rule, but still slightly better/worse than a real draw
+            Self::CursedWin => 1,
+            Self::BlessedLoss => -1,
             Self::Draw => 0,
         }
     }
+
+    /// The same result, from the opponent's perspective
+    #[cfg(any(test, feature = "syzygy"))]
+    pub(crate) fn flip(&self) -> Self {
+        match self {
+            Self::Win => Self::Loss,
+            Self::CursedWin => Self::BlessedLoss,
+            Self::Draw => Self::Draw,
+            Self::BlessedLoss => Self::CursedWin,
+            Self::Loss => Self::Win,
+        }
+    }
 }
 
-/// SyZyGy Tablase
-#[derive(Debug, Default, Clone, Copy)]
-pub(crate) struct TableBase;
-
-impl TableBase {
-    pub(crate) fn init(path: &str) -> Self {
-        unsafe {
-            let p = CString::new(path).unwrap();
-            let res = tb_init(p.as_ptr());
-            assert!(res, "Could not initialize Syzygy tablebase at: {path}");
+/// A legal root move, and its outcome for the side to move at the root
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TBRootMove {
+    pub(crate) mv: Move,
+    pub(crate) wdl: WDL,
+    /// None, when ranked with the WDL tables (missing DTZ)
+    pub(crate) dtz: Option<u32>,
+}
+
+impl TBRootMove {
+    /// Keeps the moves with the best WDL outcome, so that a won position is never thrown away (and a drawn one never lost).
+    /// Wins are sorted by the shortest distance to zeroing (progress), and losses by the longest (resistance)
+    #[cfg(any(test, feature = "syzygy"))]
+    pub(crate) fn rank(mut moves: Vec<Self>) -> Vec<Self> {
+        let Some(best) = moves.iter().map(|m| m.wdl).max() else { return moves };
+        moves.retain(|m| m.wdl == best);
+
+        match best {
+            WDL::Win | WDL::CursedWin => moves.sort_by_key(|m| m.dtz.unwrap_or(u32::MAX)),
+            WDL::Loss | WDL::BlessedLoss => moves.sort_by_key(|m| std::cmp::Reverse(m.dtz.unwrap_or(0))),
+            WDL::Draw => {}
         }
 
-        Self
+        moves
     }
+}
 
-    /// tb_probe_wdl probes the Win-Draw-Loss (WDL) table for a given position.
-    pub(crate) fn probe_wdl(&self, board: &Position) -> Option<WDL> {
-        let Some(board) =  SyZyGyBoard::try_from(board).ok() else {return None};
-
-        // enpassant square
-        let enp_sq = if let Some(sq) = board.enpassant {sq.flipv() as u32} else {0};
-
-        let wdl = 
-        unsafe { tb_probe_wdl(
-        board.get_occupancy(White).count_ones() as u64,
-        board.get_occupancy(Black).count_ones() as u64,
-        (board[WK].count_ones() + board[BK].count_ones()) as u64,
-        (board[WQ].count_ones() + board[BQ].count_ones()) as u64,
-        (board[WR].count_ones() + board[BR].count_ones()) as u64,
-        (board[WB].count_ones() + board[BB].count_ones()) as u64,
-        (board[WN].count_ones() + board[BN].count_ones()) as u64,
-        (board[WP].count_ones() + board[BP].count_ones()) as u64,
-        0, 0, enp_sq, board.turn == White)};
-
-        WDL::try_from(wdl).ok()
-    }
 
-    /// Given a position, it probes the DTZ(Distance to zero) table, and returns the bestMove, and possible consequence
-    pub(crate) fn proble_root(&self, board: &Position ) -> Option<TBResult> {
-        let Some(board) = SyZyGyBoard::try_from(board).ok() else {return None};
-
-        let enp_sq = if let Some(sq) = board.enpassant {sq.flipv() as u32} else {0};
-
-        let value = unsafe {tb_probe_root(
-            board.get_occupancy(White).count_ones() as u64, 
-            board.get_occupancy(Black).count_ones() as u64,
-            (board[WK].count_ones() + board[BK].count_ones()) as u64,
-            (board[WQ].count_ones() + board[BQ].count_ones()) as u64,
-            (board[WR].count_ones() + board[BR].count_ones()) as u64,
-            (board[WB].count_ones() + board[BB].count_ones()) as u64,
-            (board[WN].count_ones() + board[BN].count_ones()) as u64,
-            (board[WP].count_ones() + board[BP].count_ones()) as u64,
-            0, 0, enp_sq, board.turn == White, ptr::null_mut())
-        };
-
-        TBResult::try_from((board, value)).ok()
-    }
+/// Everything the search needs from a tablebase.
+/// Implemented by NoTablebase, Syzygy (FathomTablebase, with the `syzygy` feature), and an in-memory stub in tests
+pub(crate) trait Tablebase: Debug + Send + Sync {
+    /// The number of pieces of the largest available table (0 if none)
+    fn largest(&self) -> u32;
+    /// The result of this position for the side to move
+    fn probe_wdl(&self, board: &Position) -> Option<WDL>;
+    /// The legal root moves that keep the best outcome, best first (see TBRootMove::rank)
+    fn probe_root(&self, board: &Position) -> Option<Vec<TBRootMove>>;
+}
 
-    pub(crate) fn wdl_white(&self, board: &Position) -> Option<WDL> {
-        let stm = board.turn == Color::White;
-        let r = self.proble_root(board)?;
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct NoTablebase;
 
-        let rr = match r.wdl {
-            WDL::Draw => WDL::Draw,
-            WDL::Win => if stm {WDL::Win} else {WDL::Loss},
-            WDL::Loss => if stm {WDL::Loss} else {WDL::Win}
-        };
+impl Tablebase for NoTablebase {
+    fn largest(&self) -> u32 { 0 }
 
-        Some(rr)
+    fn probe_wdl(&self, _board: &Position) -> Option<WDL> { None }
+
+    fn probe_root(&self, _board: &Position) -> Option<Vec<TBRootMove>> { None }
+}
+
+/// Every position (up to `largest` pieces) has the same result, and the root is never ranked
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct StubTablebase {
+    pub(crate) largest: u32,
+    pub(crate) wdl: WDL,
+}
+
+#[cfg(test)]
+impl Tablebase for StubTablebase {
+    fn largest(&self) -> u32 { self.largest }
+
+    fn probe_wdl(&self, board: &Position) -> Option<WDL> {
+        (board.get_occupancy(Both).count_ones() <= self.largest).then_some(self.wdl)
     }
+
+    fn probe_root(&self, _board: &Position) -> Option<Vec<TBRootMove>> { None }
 }
 
-pub(crate) struct TBResult {
-    pub(crate) mv: Move, pub(crate) wdl: WDL, pub(crate) dtz: u32
+
+/// The tablebase used by the search, and when to probe it
+#[derive(Debug, Clone)]
+pub(crate) struct SyzygyConfig {
+    pub(crate) tablebase: Arc<dyn Tablebase>,
+    /// SyzygyProbeDepth: The minimum (remaining) depth at which positions with exactly `probe_limit` pieces are probed during the search
+    pub(crate) probe_depth: u8,
+    /// SyzygyProbeLimit: The maximum number of pieces probed during the search
+    pub(crate) probe_limit: u32,
 }
 
+impl Default for SyzygyConfig {
+    fn default() -> Self {
+        Self { tablebase: Arc::new(NoTablebase), probe_depth: 1, probe_limit: 7 }
+    }
+}
 
+impl SyzygyConfig {
+    /// Whether the WDL tables should be probed for this position (inside the search).
+    /// Positions with fewer pieces than the limit are always probed, and those with exactly as many only when the depth is high enough
+    pub(crate) fn can_probe(&self, board: &Position, depth: u8) -> bool {
+        let limit = self.probe_limit.min(self.tablebase.largest());
+        let pieces = board.get_occupancy(Both).count_ones();
 
-impl<'a> TryFrom<(SyZyGyBoard<'a>, u32)> for TBResult {
-    type Error = &'static str;
+        pieces < limit || (pieces == limit && depth >= self.probe_depth)
+    }
+}
 
-    fn try_from((board, result): (SyZyGyBoard, u32)) -> Result<Self, Self::Error> {
-        if result == TB_RESULT_FAILED {
-            return Err("Probe failed")
+
+#[cfg(test)]
+mod probe_tests {
+    use super::*;
+    use crate::board::state::board::Board;
+    use crate::constants::{LONGEST_TB_MATE, MATE_IN_MAX_PLY, TB_WIN_IN_MAX_PLY};
+    use crate::move_logic::bitmove::MoveType;
+
+    #[test]
+    fn tb_scores_should_be_distinguishable_from_mate_scores() {
+        for ply in [0, 1, 40, crate::constants::params::MAX_DEPTH] {
+            let win = WDL::Win.eval(ply);
+            assert!(win < LONGEST_TB_MATE && win >= TB_WIN_IN_MAX_PLY);
+            // still adjusted by ply in the transposition table
+            assert!(win >= MATE_IN_MAX_PLY);
+            assert_eq!(WDL::Loss.eval(ply), -win);
         }
+        assert!(WDL::Win.eval(3) > WDL::Win.eval(5));
+        assert_eq!(WDL::Draw.eval(10), 0);
+    }
 
-        let pre_wdl = (result & TB_RESULT_WDL_MASK) >> TB_RESULT_WDL_SHIFT;
-        let wdl = WDL::try_from(pre_wdl).unwrap_or(WDL::Draw);
+    #[test]
+    fn results_should_be_ordered_from_worst_to_best() {
+        assert!(WDL::Loss < WDL::BlessedLoss && WDL::BlessedLoss < WDL::Draw && WDL::Draw < WDL::CursedWin && WDL::CursedWin < WDL::Win);
+        assert_eq!(WDL::CursedWin.flip(), WDL::BlessedLoss);
+        assert_eq!(WDL::Win.flip().flip(), WDL::Win);
+    }
 
-        let from = Square::from(((result & TB_RESULT_FROM_MASK) >> TB_RESULT_FROM_SHIFT) as u64);
-        let to = Square::from(((result & TB_RESULT_TO_MASK) >> TB_RESULT_TO_SHIFT) as u64);
-        let promotion = (result & TB_RESULT_PROMOTES_MASK) >> TB_RESULT_PROMOTES_SHIFT;
+    #[test]
+    fn ranking_should_only_keep_the_moves_with_the_best_outcome() {
+        let root_move = |src: u64, wdl, dtz| TBRootMove { mv: Move::new(src as u8, src as u8 + 8, MoveType::Quiet), wdl, dtz };
+        let moves = vec![
+            root_move(8, WDL::Draw, Some(0)), root_move(9, WDL::Win, Some(7)), root_move(10, WDL::CursedWin, Some(3)),
+            root_move(11, WDL::Win, Some(2)), root_move(12, WDL::Loss, Some(20)),
+        ];
 
-        let promoted_to = match promotion {
-            TB_PROMOTES_QUEEN => Some(PieceType::Q),
-            TB_PROMOTES_ROOK => Some(PieceType::R),
-            TB_PROMOTES_BISHOP => Some(PieceType::B),
-            TB_PROMOTES_KNIGHT => Some(PieceType::N),
-            _ => None
-        };
+        let ranked = TBRootMove::rank(moves);
+        assert_eq!(ranked.iter().map(|m| m.mv.get_src() as u64).collect::<Vec<_>>(), vec![11, 9]);
 
-        let mut moves = MoveStack::<Move>::new();
-        board.gen_movement::<{MoveScope::ALL}, Move>(&mut moves);
-        let dtz = (result & TB_RESULT_DTZ_MASK) >> TB_RESULT_DTZ_SHIFT;
+        // lost: resist for as long as possible
+        let ranked = TBRootMove::rank(vec![root_move(8, WDL::Loss, Some(4)), root_move(9, WDL::Loss, Some(30))]);
+        assert_eq!(ranked[0].dtz, Some(30));
+
+        // wdl fallback (no dtz): every move that keeps the draw
+        let ranked = TBRootMove::rank(vec![root_move(8, WDL::Loss, None), root_move(9, WDL::Draw, None), root_move(10, WDL::Draw, None)]);
+        assert_eq!(ranked.len(), 2);
+    }
 
-        moves.into_iter().find(|m| m.get_src() == from && m.get_target() == to && 
-            m.get_promotion() == promoted_to
-        ).map(|mv| Self {mv, wdl, dtz}).ok_or("")
+    #[test]
+    fn should_not_probe_without_a_tablebase() {
+        let position = Position::with(Board::try_from("8/8/4k3/8/8/8/3PK3/8 w - - 0 1").unwrap());
+        assert!(!SyzygyConfig::default().can_probe(&position, 10));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_respect_the_probe_limit_and_depth() {
+        let position = Position::with(Board::try_from("8/8/4k3/8/8/8/3PK3/8 w - - 0 1").unwrap());
+        let mut config = SyzygyConfig { tablebase: Arc::new(StubTablebase { largest: 5, wdl: WDL::Draw }), ..SyzygyConfig::default() };
+        assert!(config.can_probe(&position, 0));
+
+        config.probe_limit = 3;
+        config.probe_depth = 4;
+        assert!(!config.can_probe(&position, 3));
+        assert!(config.can_probe(&position, 4));
+    }
+}
@@ -31,7 +31,7 @@ impl From<WDL> for HashFlag {
     fn from(value: WDL) -> Self {
         match value {
             WDL::Win => HashFlag::LowerBound,
-            WDL::Draw => HashFlag::Exact,
+            WDL::Draw | WDL::CursedWin | WDL::BlessedLoss => HashFlag::Exact,
             WDL::Loss => HashFlag::UpperBound,
         }
     }
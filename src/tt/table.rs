@@ -1,4 +1,4 @@
-use std::borrow::BorrowMut;
+use std::{borrow::BorrowMut, sync::Arc};
 
 use super::{entry::TTEntry, tpt::TPT};
 
@@ -24,7 +24,7 @@ pub(crate) const TOTAL_SIZE: usize = 0x10000; // 1MB
 /// Transposition Table
 #[derive(Debug)]
 pub(crate) struct TTable {
-    table: Vec<TTEntry>,
+    table: Arc<[TTEntry]>,
     age: u8,
 }
 
@@ -35,7 +35,7 @@ pub(crate) struct TTable {
 impl Default for TTable {
    fn default() -> Self {
         let max = TOTAL_SIZE;
-        let table = (0..max).map(|_| TTEntry::default()).collect::<Vec<_>>();
+        let table = (0..max).map(|_| TTEntry::default()).collect::<Arc<[_]>>();
         Self { table, age: 0}
    }
 }
@@ -43,7 +43,7 @@ impl Default for TTable {
 
 impl TTable {
    pub(crate) fn get(&self) -> TPT {
-        TPT { table: &self.table, age: 0 }
+        TPT { table: Arc::clone(&self.table), age: 0 }
    }
 
    pub(crate) fn increase_age(&mut self) {
@@ -1,19 +1,19 @@
-use std::sync::atomic::Ordering;
+use std::sync::{atomic::Ordering, Arc};
 
 use crate::move_logic::bitmove::Move;
 
 use super::{entry::{to_tt, TTData, TTEntry}, flag::HashFlag, table::TOTAL_SIZE};
 
 /// TPT => Transposition Table (derived)
-/// This is the exposed derivation of the original Transposition Table (TT)
+/// This is the exposed derivation of the original Transposition Table (TT), every search worker holds one (they all share the same entries)
 #[derive(Debug, Clone)]
-pub(crate) struct TPT<'a> {
-    pub(crate) table : &'a [TTEntry],
+pub(crate) struct TPT {
+    pub(crate) table : Arc<[TTEntry]>,
     pub(crate) age: u8
 }
 
 
-impl<'a> TPT<'a> {
+impl TPT {
     const  TT_REPLACE_OFFSET: u8 = 4;
 
     pub(crate) fn record(&self, zobrist_key: u64, depth: u8, score: i32, eval: i32, ply: usize, flag: HashFlag, mv: Option<Move>, pv: bool) {
@@ -0,0 +1,375 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::board::state::board::Board;
+use crate::search::evaluation::{terms, Evaluation, S, WEIGHTS};
+
+
+/// Texel tuning of the handcrafted evaluation: https://www.chessprogramming.org/Texel%27s_Tuning_Method
+/// usage: tune-hce data PATH [epochs N] [lr X] [out PATH]
+///
+/// The dataset has one position per line, either in the datagen text format (FEN | score | result),
+/// or as an EPD with the result at the end (FEN [1.0], FEN "1-0", or FEN 1-0).
+/// The tuned weights are written to `out` as a replacement for src/search/hce_weights.rs
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TexelTuner {
+    data: String,
+    epochs: usize,
+    /// Adam's learning rate (in centipawns)
+    lr: f64,
+    out: String,
+}
+
+impl Default for TexelTuner {
+    fn default() -> Self {
+        Self { data: String::new(), epochs: 1000, lr: 1.0, out: String::from("hce_weights.rs") }
+    }
+}
+
+/// A position of the dataset, reduced to what the (linear) evaluation needs
+#[derive(Debug, Clone)]
+struct Entry {
+    /// The non-zero (term, white relative count) pairs of the evaluation
+    coefficients: Vec<(u16, i16)>,
+    /// The share of the middle game score in the tapered evaluation (the end game gets the rest)
+    mg: f64,
+    /// 1.0 white wins, 0.5 draw, 0.0 black wins
+    result: f64,
+}
+
+/// The weights being tuned, as floats: [term][mg, eg]
+type Params = Vec<[f64; 2]>;
+
+impl TexelTuner {
+    /// Checkpoints (the error, and the weights so far) are written every this many epochs
+    const REPORT_EVERY: usize = 50;
+
+    pub(crate) fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut tuner = Self::default();
+
+        while let Some(key) = args.next() {
+            let Some(value) = args.next() else { return Err(format!("No value provided for {key}")) };
+
+            match key {
+                "data" => tuner.data = value.to_string(),
+                "epochs" => tuner.epochs = value.parse().map_err(|_| format!("Invalid value for {key}: {value}"))?,
+                "lr" => tuner.lr = value.parse().map_err(|_| format!("Invalid value for {key}: {value}"))?,
+                "out" => tuner.out = value.to_string(),
+                _ => return Err(format!("Unrecognized tune-hce option {key}")),
+            }
+        }
+
+        if tuner.data.is_empty() { return Err(String::from("No dataset provided (data PATH)")) }
+        Ok(tuner)
+    }
+
+    pub(crate) fn run(&self) -> std::io::Result<()> {
+        let entries = Self::load(&self.data)?;
+        println!("tune-hce: {} positions loaded", entries.len());
+        if entries.is_empty() { return Ok(()) }
+
+        let mut params: Params = WEIGHTS.iter().map(|w| [w.0 as f64, w.1 as f64]).collect();
+        let k = optimal_k(&entries, &params);
+        println!("tune-hce: K = {k:.4}, initial error = {:.6}", error(&entries, &params, k));
+
+        let mut adam = Adam::new(params.len(), self.lr);
+        for epoch in 1..=self.epochs {
+            let gradient = gradient(&entries, &params, k);
+            adam.step(&mut params, &gradient);
+
+            if epoch % Self::REPORT_EVERY == 0 || epoch == self.epochs {
+                println!("tune-hce: epoch {epoch}, error = {:.6}", error(&entries, &params, k));
+                std::fs::write(&self.out, write_weights(&rounded(&params)))?;
+            }
+        }
+
+        println!("tune-hce: weights written to {}", self.out);
+        Ok(())
+    }
+
+    fn load(path: &str) -> std::io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+
+        for line in BufReader::new(File::open(path)?).lines() {
+            match parse_line(&line?) {
+                Some((board, result)) => entries.push(Entry::new(&board, result)),
+                None => skipped += 1,
+            }
+        }
+
+        if skipped > 0 { eprintln!("tune-hce: {skipped} lines could not be parsed") }
+        Ok(entries)
+    }
+}
+
+
+impl Entry {
+    fn new(board: &Board, result: f64) -> Self {
+        let mut counts = vec![0; terms::COUNT];
+        Evaluation::score(board, &mut counts);
+
+        let coefficients = counts.into_iter().enumerate().filter(|(_, c)| *c != 0).map(|(term, c)| (term as u16, c as i16)).collect();
+        let mg = Evaluation::phase(board) as f64 / Evaluation::MAX_PHASE as f64;
+
+        Self { coefficients, mg, result }
+    }
+
+    /// The white relative (tapered) evaluation with these weights
+    fn evaluate(&self, params: &Params) -> f64 {
+        let (mg, eg) = self.coefficients.iter().fold((0.0, 0.0), |(mg, eg), &(term, c)| {
+            let [w_mg, w_eg] = params[term as usize];
+            (mg + w_mg * c as f64, eg + w_eg * c as f64)
+        });
+        mg * self.mg + eg * (1.0 - self.mg)
+    }
+}
+
+
+/// The expected result of a white relative evaluation
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// Mean squared error between the results and the predictions of the evaluation
+fn error(entries: &[Entry], params: &Params, k: f64) -> f64 {
+    entries.iter().map(|e| (e.result - sigmoid(e.evaluate(params), k)).powi(2)).sum::<f64>() / entries.len() as f64
+}
+
+/// The gradient of the error, for every weight
+fn gradient(entries: &[Entry], params: &Params, k: f64) -> Params {
+    let mut gradient = vec![[0.0; 2]; params.len()];
+
+    for entry in entries {
+        let s = sigmoid(entry.evaluate(params), k);
+        // d(error)/d(eval), with the constant factors (2, K * ln(10) / 400) left to the learning rate
+        let d = (s - entry.result) * s * (1.0 - s);
+
+        for &(term, c) in &entry.coefficients {
+            gradient[term as usize][0] += d * c as f64 * entry.mg;
+            gradient[term as usize][1] += d * c as f64 * (1.0 - entry.mg);
+        }
+    }
+
+    gradient.iter_mut().flatten().for_each(|g| *g /= entries.len() as f64);
+    gradient
+}
+
+/// The scaling constant K that fits the current weights best (the error is convex in K, so a ternary search finds it)
+fn optimal_k(entries: &[Entry], params: &Params) -> f64 {
+    let (mut lo, mut hi) = (0.0, 10.0);
+    for _ in 0..100 {
+        let (a, b) = (lo + (hi - lo) / 3.0, hi - (hi - lo) / 3.0);
+        if error(entries, params, a) < error(entries, params, b) { hi = b } else { lo = a }
+    }
+    (lo + hi) / 2.0
+}
+
+
+/// https://arxiv.org/abs/1412.6980
+struct Adam {
+    lr: f64,
+    m: Params,
+    v: Params,
+    t: i32,
+}
+
+impl Adam {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    fn new(size: usize, lr: f64) -> Self {
+        Self { lr, m: vec![[0.0; 2]; size], v: vec![[0.0; 2]; size], t: 0 }
+    }
+
+    fn step(&mut self, params: &mut Params, gradient: &Params) {
+        self.t += 1;
+        let (bias1, bias2) = (1.0 - Self::BETA1.powi(self.t), 1.0 - Self::BETA2.powi(self.t));
+
+        for (i, phase) in (0..params.len()).flat_map(|i| [(i, 0), (i, 1)]) {
+            let g = gradient[i][phase];
+            self.m[i][phase] = Self::BETA1 * self.m[i][phase] + (1.0 - Self::BETA1) * g;
+            self.v[i][phase] = Self::BETA2 * self.v[i][phase] + (1.0 - Self::BETA2) * g * g;
+            params[i][phase] -= self.lr * (self.m[i][phase] / bias1) / ((self.v[i][phase] / bias2).sqrt() + Self::EPSILON);
+        }
+    }
+}
+
+
+/// Splits a dataset line into a position and its (white relative) result
+fn parse_line(line: &str) -> Option<(Board, f64)> {
+    let line = line.trim();
+    if line.is_empty() { return None }
+
+    let (fen, result) = if line.contains('|') {
+        let mut fields = line.split('|');
+        (fields.next()?, fields.next_back()?)
+    } else if let Some((fen, result)) = line.split_once('[') {
+        (fen, result.trim_end_matches(']'))
+    } else if let Some((fen, result)) = line.split_once('"') {
+        (fen, result.trim_end_matches(';').trim_end_matches('"'))
+    } else {
+        line.rsplit_once(' ')?
+    };
+
+    let result = match result.trim() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        value => value.parse::<f64>().ok().filter(|r| (0.0..=1.0).contains(r))?,
+    };
+
+    Some((Board::try_from(fen.trim()).ok()?, result))
+}
+
+fn rounded(params: &Params) -> Vec<S> {
+    params.iter().map(|[mg, eg]| S(mg.round() as i32, eg.round() as i32)).collect()
+}
+
+/// The constants of src/search/hce_weights.rs: (name, doc, offset in `terms`, size)
+const LAYOUT: [(&str, &str, usize, usize); 21] = [
+    ("MATERIAL", "pawn, knight, bishop, rook, queen", terms::MATERIAL, 5),
+    ("PSQT", "pawn, knight, bishop, rook, queen, king (from white's perspective, with a8 first)", terms::PSQT, 6 * 64),
+    ("KNIGHT_MOBILITY", "by the number of safe squares attacked (not occupied by friendly pieces, nor attacked by enemy pawns)", terms::KNIGHT_MOBILITY, 9),
+    ("BISHOP_MOBILITY", "by the number of safe squares attacked", terms::BISHOP_MOBILITY, 14),
+    ("ROOK_MOBILITY", "by the number of safe squares attacked", terms::ROOK_MOBILITY, 15),
+    ("QUEEN_MOBILITY", "by the number of safe squares attacked", terms::QUEEN_MOBILITY, 28),
+    ("DOUBLE_PAWN", "pawns with a friendly pawn in front of them, on the same file", terms::DOUBLE_PAWN, 1),
+    ("ISOLATED_PAWN", "no friendly pawns on the adjacent files", terms::ISOLATED_PAWN, 1),
+    ("PASSED_PAWN", "passed pawns, by relative rank", terms::PASSED_PAWN, 8),
+    ("PASSED_BLOCKED", "passed pawns that cannot be pushed (the square in front is occupied), by relative rank", terms::PASSED_BLOCKED, 8),
+    ("ROOK_OPEN_FILE", "rooks on files without pawns", terms::ROOK_OPEN_FILE, 1),
+    ("ROOK_SEMI_OPEN_FILE", "rooks on files with enemy pawns only", terms::ROOK_SEMI_OPEN_FILE, 1),
+    ("ROOK_ON_SEVENTH", "rooks on the (relative) 7th rank", terms::ROOK_ON_SEVENTH, 1),
+    ("KING_OPEN_FILE", "kings on files without pawns", terms::KING_OPEN_FILE, 1),
+    ("KING_SEMI_OPEN_FILE", "kings on files with enemy pawns only", terms::KING_SEMI_OPEN_FILE, 1),
+    ("KING_SHIELD", "friendly pawns on the two ranks in front of the king (on its file and the adjacent ones)", terms::KING_SHIELD, 1),
+    ("KING_ATTACK", "attack units of knights, bishops, rooks and queens: for every attacked square of the enemy king zone (with 2 or more attackers)", terms::KING_ATTACK, 4),
+    ("BISHOP_PAIR", "two or more bishops", terms::BISHOP_PAIR, 1),
+    ("THREAT_BY_PAWN", "enemy pieces (not pawns) attacked by pawns", terms::THREAT_BY_PAWN, 1),
+    ("THREAT_BY_MINOR", "enemy rooks and queens attacked by knights and bishops", terms::THREAT_BY_MINOR, 1),
+    ("THREAT_BY_ROOK", "enemy queens attacked by rooks", terms::THREAT_BY_ROOK, 1),
+];
+
+/// The source of src/search/hce_weights.rs, with these weights
+fn write_weights(weights: &[S]) -> String {
+    let row = |weights: &[S]| weights.iter().map(|w| w.to_string()).collect::<Vec<_>>().join(", ");
+    let mut out = String::new();
+
+    writeln!(out, "//! The weights of the handcrafted evaluation (search::evaluation), as (middle game, end game) pairs.").unwrap();
+    writeln!(out, "//! This file is written by the Texel tuner (`chess tune-hce`), so changes to the layout belong in src/tuner").unwrap();
+    writeln!(out, "use super::evaluation::S;").unwrap();
+    writeln!(out).unwrap();
+
+    for (name, doc, offset, size) in LAYOUT {
+        let table = &weights[offset..offset + size];
+        writeln!(out, "/// {doc}").unwrap();
+
+        match size {
+            _ if name == "PSQT" => {
+                writeln!(out, "pub(crate) const PSQT: [[S; 64]; 6] = [").unwrap();
+                for piece in table.chunks(64) {
+                    writeln!(out, "    [").unwrap();
+                    for rank in piece.chunks(8) { writeln!(out, "        {},", row(rank)).unwrap(); }
+                    writeln!(out, "    ],").unwrap();
+                }
+                writeln!(out, "];").unwrap();
+            }
+            1 => writeln!(out, "pub(crate) const {name}: S = {};", table[0]).unwrap(),
+            2..=8 => writeln!(out, "pub(crate) const {name}: [S; {size}] = [{}];", row(table)).unwrap(),
+            _ => {
+                writeln!(out, "pub(crate) const {name}: [S; {size}] = [").unwrap();
+                for chunk in table.chunks(8) { writeln!(out, "    {},", row(chunk)).unwrap(); }
+                writeln!(out, "];").unwrap();
+            }
+        }
+    }
+
+    out
+}
+
+
+#[cfg(test)]
+mod tuner_tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::constants::{CMK_POSITION, START_POSITION, TRICKY_POSITION};
+
+    #[test]
+    fn written_weights_should_match_the_current_source() {
+        assert_eq!(write_weights(&WEIGHTS), include_str!("../search/hce_weights.rs"));
+    }
+
+    #[test]
+    fn linear_evaluation_should_match_the_handcrafted_evaluation() {
+        let params: Params = WEIGHTS.iter().map(|w| [w.0 as f64, w.1 as f64]).collect();
+
+        for fen in [START_POSITION, TRICKY_POSITION, CMK_POSITION, "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R b KQ - 0 8"] {
+            let board = Board::try_from(fen).unwrap();
+            let entry = Entry::new(&board, 0.5);
+
+            let hce = Evaluation::evaluate(&board);
+            let white = if board.turn == Color::White { hce } else { -hce };
+            // integer division in the engine's taper
+            assert!((entry.evaluate(&params) - white as f64).abs() <= 1.0, "{fen}: {} vs {white}", entry.evaluate(&params));
+        }
+    }
+
+    #[test]
+    fn layout_should_cover_every_term() {
+        let mut next = 0;
+        for (name, _, offset, size) in LAYOUT {
+            assert_eq!(offset, next, "{name}");
+            next += size;
+        }
+        assert_eq!(next, terms::COUNT);
+    }
+
+    #[test]
+    fn should_parse_the_supported_dataset_formats() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        assert_eq!(parse_line(&format!("{fen} | 35 | 1.0")).map(|(_, r)| r), Some(1.0));
+        assert_eq!(parse_line(&format!("{fen} [0.5]")).map(|(_, r)| r), Some(0.5));
+        assert_eq!(parse_line(&format!("{fen} \"0-1\";")).map(|(_, r)| r), Some(0.0));
+        assert_eq!(parse_line(&format!("{fen} 1/2-1/2")).map(|(_, r)| r), Some(0.5));
+        assert!(parse_line(&format!("{fen} | 35 | 2.0")).is_none());
+        assert!(parse_line("not a fen | 0 | 1.0").is_none());
+        assert!(parse_line("").is_none());
+
+        let (board, _) = parse_line(&format!("{fen} [1.0]")).unwrap();
+        assert_eq!(board.to_fen(), Board::try_from(fen).unwrap().to_fen());
+    }
+
+    #[test]
+    fn tuning_should_reduce_the_error() {
+        // white is a knight up in every position but only ever draws, so the knight is overvalued
+        let fens = ["4k3/pppp4/8/8/8/8/PPPP4/1N2K3 w - - 0 1", "4k3/4pppp/8/8/8/5N2/4PPPP/4K3 b - - 0 1", "2k5/ppp5/8/8/3N4/8/PPP5/2K5 w - - 0 1"];
+        let entries = fens.iter().map(|fen| Entry::new(&Board::try_from(*fen).unwrap(), 0.5)).collect::<Vec<_>>();
+
+        let mut params: Params = WEIGHTS.iter().map(|w| [w.0 as f64, w.1 as f64]).collect();
+        let k = 1.0;
+        let before = error(&entries, &params, k);
+
+        let mut adam = Adam::new(params.len(), 5.0);
+        for _ in 0..50 {
+            let gradient = gradient(&entries, &params, k);
+            adam.step(&mut params, &gradient);
+        }
+
+        assert!(error(&entries, &params, k) < before / 2.0);
+        assert!(params[terms::MATERIAL + 1][1] < WEIGHTS[terms::MATERIAL + 1].1 as f64);
+    }
+
+    #[test]
+    fn should_parse_tuner_arguments() {
+        let tuner = TexelTuner::parse(["data", "games.txt", "epochs", "20", "lr", "0.5", "out", "tuned.rs"].into_iter()).unwrap();
+        assert_eq!(tuner, TexelTuner { data: String::from("games.txt"), epochs: 20, lr: 0.5, out: String::from("tuned.rs") });
+
+        assert!(TexelTuner::parse(["epochs", "20"].into_iter()).is_err());
+        assert!(TexelTuner::parse(["data"].into_iter()).is_err());
+        assert!(TexelTuner::parse(["data", "x", "speed", "2"].into_iter()).is_err());
+    }
+}
@@ -1,6 +1,6 @@
-use std::{cmp, sync::atomic::{AtomicBool, Ordering}, time::{Duration, Instant}};
+use std::{cmp, sync::Arc, time::{Duration, Instant}};
 
-use crate::color::Color;
+use crate::{color::Color, search::signals::SearchSignals};
 
 use super::counter::Counter;
 
@@ -38,20 +38,17 @@ pub(crate) struct Clock {
     limit: Counter,
     max_time: Duration,
     opt_time: Duration,
-    stop: *const AtomicBool
+    signals: Arc<SearchSignals>,
 }
 
-unsafe impl Send for Clock {}
-unsafe impl Sync for Clock {}
-
 impl Clock {
-    pub(crate) fn new(stop: *const AtomicBool) -> Self {
+    pub(crate) fn new(signals: Arc<SearchSignals>) -> Self {
         Self {
             start_time: Instant::now(),
             limit: Counter::default(),
             max_time: Duration::from_secs(0),
             opt_time: Duration::from_secs(0),
-            stop
+            signals
         }
     }
 
@@ -106,17 +103,23 @@ impl Clock {
     //     let move_importance = 
     // }
 
+    pub(crate) fn signals(&self) -> &SearchSignals {
+        &self.signals
+    }
+
     pub(crate) fn limit(&self) -> &Counter {
         &self.limit
     }
 
     pub(crate) fn stop(&self, nodes: u64, depth: u8) -> bool {
-        let global_stop = unsafe{self.stop.read()}.load(Ordering::SeqCst);
-        if global_stop { return true };
+        if self.signals.stopped() { return true };
+        // the limits only apply once the opponent plays the expected move
+        if self.signals.pondering() { return false };
 
         match self.limit {
             Counter::Depth(d) => depth > d,
-            Counter::Mate(ply) => ply/2 > (depth as u64),
+            // the MateSearch is bounded by the number of moves itself
+            Counter::Mate(_) => false,
             Counter::Nodes(n) => nodes > n,
             Counter::Time(t) => {
                 let elapsed = self.start_time.elapsed().as_millis() as u64;
@@ -1,4 +1,4 @@
-use std::{io::{stdout, Write}, str::SplitWhitespace, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, thread};
+use std::{fmt::{Debug, Display}, io::{stdout, Write}, str::SplitWhitespace, sync::{Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, SystemTime, UNIX_EPOCH}};
 
 use clock::Clock;
 use counter::Counter;
@@ -6,12 +6,15 @@ use thiserror::Error;
 
 pub(crate) mod clock;
 
-use crate::{board::{position::Position, state::board::Board}, constants::START_POSITION, move_logic::{bitmove::Move, move_stack::MoveStack}, move_scope::MoveScope, search::{control::Control, search::Search, threads::Thread}, tt::table::TTable};
+use crate::{board::{position::Position, state::board::Board}, engine::Engine, constants::{params::MAX_DEPTH, START_POSITION}, move_logic::{bitmove::Move, move_stack::MoveStack}, move_scope::MoveScope, nnue::{trace::EvalTrace, EMBEDDED_NET}, syzygy::probe::SyzygyConfig, search::{contempt::Contempt, control::Control, mate::MateSearch, search::SearchWorker, signals::SearchSignals, skill::Skill}, tt::table::TTable, utils::prng::PRNG};
+
+#[cfg(feature = "tune")]
+use crate::search::tunables::{self, Tunable, TUNABLES};
 
 #[cfg(test)]
 #[path = "./uci.tests.rs"]
 mod uci_tests;
-mod counter;
+pub(crate) mod counter;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum UciError {
@@ -25,22 +28,74 @@ pub enum UciError {
     NoValue(&'static str),
     #[error("Empty Argument")]
     EmptyArgument,
+    #[error("{0} is not supported")]
+    Unsupported(&'static str),
+    #[error("Expected true or false but got: {0}")]
+    InvalidBooleanArgument(String),
 }
 
+/// The strength limiting options
 #[derive(Debug)]
-pub(crate) struct UCI { position: Option<Position>, tt: TTable, options: Vec<(String, String)>, clock: Clock, stop: AtomicBool }
+struct Strength { level: u8, rng: PRNG }
 
-impl Default for UCI {
+impl Default for Strength {
     fn default() -> Self {
-        let stop = AtomicBool::new(false);
-        let stop_ptr: *const AtomicBool = &stop;
+        // a different game every time, unless a SkillSeed is set
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |time| time.subsec_nanos());
+        Self { level: Skill::MAX_LEVEL, rng: PRNG::new(seed.max(1)) }
+    }
+}
 
-        let clock = Clock::new(stop_ptr);
-        Self { position: None, tt: TTable::default(), options: vec![], clock, stop }
+impl Strength {
+    /// The skill of the next search, every search gets its own seed from the rng
+    fn skill(&mut self) -> Skill {
+        let seed = self.rng.get_random_u64() as u32;
+        Skill::from_level(self.level, seed)
+    }
+}
+
+/// Where the search thread writes its info and bestmove lines (stdout, or a buffer in the tests)
+#[derive(Clone)]
+struct Output(Arc<Mutex<dyn Write + Send>>);
+
+impl Default for Output {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(stdout())))
+    }
+}
+
+impl Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Output")
+    }
+}
+
+impl Output {
+    fn line(&self, line: impl Display) {
+        let mut out = self.0.lock().unwrap();
+        let _ = writeln!(out, "{line}");
+        let _ = out.flush();
+    }
+}
+
+/// The workers are owned by the search thread during a search (`search`), and given back when it ends (see UCI::wait)
+#[derive(Debug)]
+pub(crate) struct UCI { position: Option<Position>, tt: TTable, options: Vec<(String, String)>, clock: Clock, signals: Arc<SearchSignals>, tb: SyzygyConfig, use_nnue: bool, mate_checks_only: bool, strength: Strength, contempt: Contempt, workers: Vec<SearchWorker>, search: Option<JoinHandle<Vec<SearchWorker>>>, output: Output }
+
+impl Default for UCI {
+    fn default() -> Self {
+        let signals = Arc::new(SearchSignals::default());
+        let clock = Clock::new(Arc::clone(&signals));
+        let (tt, tb) = (TTable::default(), SyzygyConfig::default());
+        let workers = (0..Self::THREADS).map(|_| SearchWorker::new(tt.get(), clock.clone(), tb.clone())).collect();
+        Self { position: None, tt, options: vec![], clock, signals, tb, use_nnue: EMBEDDED_NET, mate_checks_only: false, strength: Strength::default(), contempt: Contempt::default(), workers, search: None, output: Output::default() }
     }
 }
 
 impl UCI {
+    /// The number of search threads (every thread has its own worker)
+    const THREADS: usize = 2;
+
     pub(crate) fn update_board_to(&mut self, board: Position) {
         self.position = Some(board);
     }
@@ -48,7 +103,6 @@ impl UCI {
     pub(crate) fn process_input<W: Write>(&mut self, input: String, mut writer: W) -> std::io::Result<bool> {
         let mut input = input.trim().split_whitespace();
         // let tb = TableBase::default();
-        let mut table = TTable::default();
 
         
         match input.next() {
@@ -66,31 +120,92 @@ impl UCI {
                 }
             }
             Some("ucinewgame") => {
+                self.wait();
                 self.update_board_to(Position::with(Board::try_from(START_POSITION).unwrap()));
                 self.tt = TTable::default();
+                self.workers.iter_mut().for_each(SearchWorker::clear);
                 self.options = vec![];
                 write!(writer, "{}", self.position.as_ref().unwrap().to_string())?;
             }
             Some("go") => {
+                self.wait();
+                // `go ponder <limits>`: the limits only apply after the ponderhit
+                let ponder = input.clone().next() == Some("ponder");
+                if ponder { input.next(); }
+
                 match Counter::try_from(input) {
+                    Ok(Counter::Mate(plies)) if self.position.is_some() => {
+                        let board = self.position.as_ref().unwrap().board;
+                        self.signals.reset(false);
+                        let moves = (plies / 2).min(MAX_DEPTH as u64 / 2) as u8;
+                        self.clock.set_limit(Counter::Mate(plies), board.turn);
+                        self.clock.start();
+
+                        // a long mate search must not block the loop either: `stop` ends it through the signals (see Clock::stop)
+                        let mut search = MateSearch::new(self.clock.clone(), self.mate_checks_only);
+                        let (workers, signals, output, clock) = (std::mem::take(&mut self.workers), Arc::clone(&self.signals), self.output.clone(), self.clock.clone());
+                        self.search = Some(thread::spawn(move || {
+                            match search.find(&board, moves) {
+                                Some(mate) => {
+                                    let pv = mate.pv.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>().join(" ");
+                                    output.line(format!("info depth {} nodes {} time {} score mate {} pv {pv}", mate.pv.len(), search.nodes(), clock.elapsed().as_millis(), mate.moves));
+                                    output.line(format!("bestmove {}", mate.pv[0].to_uci()));
+                                }
+                                None => {
+                                    if !signals.stopped() { output.line(format!("info string no mate in {moves}")); }
+                                    output.line("bestmove 0000");
+                                }
+                            }
+                            workers
+                        }));
+                    }
                     Ok(counter) if self.position.is_some() => {
-                        let board = self.position.clone().unwrap(); // this would be fixed later
-                        self.clock.set_limit(counter, board.turn);
+                        let mut root = self.position.clone().unwrap();
+                        root.use_nnue = self.use_nnue;
+                        self.signals.reset(ponder);
+                        // like a pondering search, `go infinite` only prints its bestmove once it is stopped
+                        let infinite = counter == Counter::Infinite;
+                        let depth = match counter { Counter::Depth(depth) => depth as usize, _ => MAX_DEPTH };
+                        self.clock.set_limit(counter, root.turn);
                         self.tt.increase_age();
                         self.clock.start();
-                        let thread = Thread::new(30, table.get(), 0); // SHOULD BE REMOVED LATER (THIS SERVES NO SERIOUS PURPOSE YET -0->> MORE LIKE A DUPLICATION)
-
-                        let mut negamax = (0..2).map(|_i| Search::new(self.tt.get(), self.clock.clone())).collect::<Vec<_>>();
-                        thread::scope(|s| {
 
-                            for negamax in negamax.iter_mut() {
-                                let mut bb = board.clone();
-                                let mut th = thread.clone();
-                                s.spawn(move || {
-                                    negamax.iterative_deepening(10, &mut bb, &mut th);
-                                });
+                        // A limited strength search runs alone, a helper thread (at full strength) would share its results through the TT
+                        let skill = self.strength.skill();
+                        let threads = if skill.enabled() { 1 } else { self.workers.len() };
+                        for worker in self.workers.iter_mut() {
+                            worker.new_search(self.tt.get(), self.clock.clone(), self.tb.clone());
+                            worker.set_contempt(self.contempt);
+                        }
+                        self.workers[0].set_skill(skill);
+
+                        // the search runs on its own thread, so that stop and ponderhit are read while it runs
+                        let (mut workers, signals, output) = (std::mem::take(&mut self.workers), Arc::clone(&self.signals), self.output.clone());
+                        self.search = Some(thread::spawn(move || {
+                            let (main, helpers) = workers.split_first_mut().unwrap();
+                            thread::scope(|s| {
+                                for helper in helpers.iter_mut().take(threads - 1) {
+                                    let mut root = root.clone();
+                                    s.spawn(move || helper.iterative_deepening_with(depth, &mut root, &mut |_| {}));
+                                }
+
+                                main.iterative_deepening_with(depth, &mut root.clone(), &mut |info| output.line(info));
+                                while !signals.stopped() && (signals.pondering() || infinite) {
+                                    thread::sleep(Duration::from_millis(1));
+                                }
+                                // the helpers only fill the TT for the main thread
+                                signals.stop();
+                            });
+
+                            let pv = workers[0].pv();
+                            // stopped before the first iteration: any legal move is better than none
+                            match (pv.first().copied().or_else(|| Engine::legal_moves(&root).first().copied()), pv.get(1)) {
+                                (Some(best), Some(ponder)) => output.line(format!("bestmove {} ponder {}", best.to_uci(), ponder.to_uci())),
+                                (Some(best), None) => output.line(format!("bestmove {}", best.to_uci())),
+                                (None, _) => output.line("bestmove 0000"),
                             }
-                        });
+                            workers
+                        }));
                     }
                     Err(e) => {write!(writer, "{}", e)?;}
                     _ => {}
@@ -131,20 +246,38 @@ impl UCI {
                 // }
             }
             Some("quit") => { 
-                self.stop.store(true, Ordering::SeqCst);
+                self.signals.stop();
+                self.wait();
                 return Ok(false);
              }
             Some("isready") => {writeln!(writer, "readyok")?;}
             Some("uci") => {
             for data in Self::identify() {
+                    if data == "uciok" {
+                        for option in Self::options() { writeln!(writer, "{option}")?; }
+                        #[cfg(feature = "tune")]
+                        for tunable in TUNABLES { writeln!(writer, "{}", tunable.uci_option())?; }
+                    }
                     writeln!(writer, "{}", data)?;
                 }
             }
+            #[cfg(feature = "tune")]
+            Some("spsa") => {
+                // OpenBench by default, `spsa json` for Weather-Factory
+                match input.next() {
+                    Some("json") => writeln!(writer, "{}", tunables::weather_factory())?,
+                    _ => for tunable in TUNABLES { writeln!(writer, "{}", tunable.openbench())?; },
+                }
+            }
             Some("d") => {writeln!(writer, "{}", self.position.as_ref().unwrap().to_string())?;},
-            Some("stop") => {
-                self.stop.store(true, Ordering::SeqCst);
-                return Ok(false);
-            },
+            Some("eval") => {
+                match self.position.as_ref() {
+                    Some(position) => write!(writer, "{}", EvalTrace::new(&position.board))?,
+                    None => writeln!(writer, "No position has been set")?,
+                }
+            }
+            Some("stop") => self.signals.stop(),
+            Some("ponderhit") => self.signals.ponderhit(),
             Some("setoption") => {
                 if input.next() == Some("name") {
                     let mut option_name = String::new();
@@ -172,11 +305,13 @@ impl UCI {
                         }
                     }
 
-                    if !error_found {
+                    if error_found {
+                        writeln!(writer, "error found! Please ensure that the provided 'name' and 'value' match the UCI name/value recommendations")?;
+                    } else if let Err(e) = self.set_option(option_name.trim(), option_value.trim()) {
+                        writeln!(writer, "{}", e)?;
+                    } else {
                         self.options.push((option_name, option_value));
                     }
-                    
-                    writeln!(writer, "error found! Please ensure that the provided 'name' and 'value' match the UCI name/value recommendations")?;
                 }
             }
             _ => {}
@@ -185,6 +320,13 @@ impl UCI {
         Ok(true)
     }
 
+    /// Waits for the running search (if any) to print its bestmove, and takes its workers back
+    fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.workers = search.join().expect("the search thread panicked");
+        }
+    }
+
     pub(crate) fn reader(&mut self) -> std::io::Result<()> {
         loop {
             let mut buffer = String::new();
@@ -201,10 +343,63 @@ impl UCI {
     }
 
 
+    /// Applies the options that the engine understands, all other options are only stored
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), UciError> {
+        let integer = || value.parse::<u32>().map_err(|_| UciError::InvalidIntegerArgument(value.to_string()));
+        let boolean = || value.parse::<bool>().map_err(|_| UciError::InvalidBooleanArgument(value.to_string()));
+
+        match name {
+            #[cfg(feature = "syzygy")]
+            "SyzygyPath" => self.tb.tablebase = Arc::new(crate::syzygy::fathom::FathomTablebase::init(value)),
+            #[cfg(not(feature = "syzygy"))]
+            "SyzygyPath" => return Err(UciError::Unsupported("SyzygyPath (built without the syzygy feature)")),
+            "SyzygyProbeDepth" => self.tb.probe_depth = integer()?.min(u8::MAX as u32) as u8,
+            "SyzygyProbeLimit" => self.tb.probe_limit = integer()?,
+            "UseNNUE" if !EMBEDDED_NET && boolean()? => return Err(UciError::Unsupported("UseNNUE (built without the nnue feature)")),
+            "UseNNUE" => self.use_nnue = boolean()?,
+            "MateChecksOnly" => self.mate_checks_only = boolean()?,
+            "Skill Level" => self.strength.level = integer()?.min(Skill::MAX_LEVEL as u32) as u8,
+            "SkillSeed" => self.strength.rng = PRNG::new(integer()?.max(1)),
+            "Contempt" => {
+                let value = value.parse::<i32>().map_err(|_| UciError::InvalidIntegerArgument(value.to_string()))?;
+                self.contempt.value = value.clamp(-Contempt::MAX, Contempt::MAX);
+            }
+            "DynamicContempt" => self.contempt.dynamic = boolean()?,
+            #[cfg(feature = "tune")]
+            name if Tunable::find(name).is_some() => {
+                let value = value.parse::<i32>().map_err(|_| UciError::InvalidIntegerArgument(value.to_string()))?;
+                Tunable::find(name).unwrap().set(value);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn identify() -> [&'static str; 4] {
         ["id name: papa", "id author: Tolumide", "id email: tolumideshopein@gmail.com", "uciok"]
     }
 
+    /// The options applied by set_option (GUIs only send the options that are advertised), SkillSeed is only meant for testing
+    pub(crate) fn options() -> Vec<String> {
+        let tb = SyzygyConfig::default();
+        let contempt = Contempt::default();
+        let mut options = Vec::new();
+
+        #[cfg(feature = "syzygy")]
+        options.push(String::from("option name SyzygyPath type string default <empty>"));
+        options.extend([
+            format!("option name SyzygyProbeDepth type spin default {} min 1 max 100", tb.probe_depth),
+            format!("option name SyzygyProbeLimit type spin default {} min 0 max 7", tb.probe_limit),
+            format!("option name UseNNUE type check default {EMBEDDED_NET}"),
+            String::from("option name MateChecksOnly type check default false"),
+            format!("option name Skill Level type spin default {max} min 0 max {max}", max = Skill::MAX_LEVEL),
+            format!("option name Contempt type spin default {} min {} max {}", contempt.value, -Contempt::MAX, Contempt::MAX),
+            format!("option name DynamicContempt type check default {}", contempt.dynamic),
+        ]);
+        options
+    }
+
 
     fn apply_moves_to_board(state: &mut Position, mut moves: SplitWhitespace) {
         // let mut p = state;
@@ -216,6 +411,8 @@ impl UCI {
             if let Some(b_move) = Self::parse_move(&state, mv) {
                 // b = b.make_move(b_move, MoveScope::AllMoves).unwrap();
                 state.make_move(b_move, MoveScope::AllMoves);
+                // the accumulator stack only has room for the search, not for the whole game
+                state.refresh_nnue();
             }
         }
     }
@@ -1,8 +1,20 @@
 #[cfg(test)]
 mod uci_tests {
-    use std::io::Cursor;
+    use std::{io::Cursor, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
 
-    use crate::{board::state::board::Board, constants::TRICKY_POSITION, uci::UCI};
+    use crate::{board::state::board::Board, constants::TRICKY_POSITION, move_logic::bitmove::Move, uci::{Output, UCI}};
+
+    /// A UCI whose search thread writes to the returned buffer
+    fn uci_with_output() -> (UCI, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut uci = UCI::default();
+        uci.output = Output(buffer.clone());
+        (uci, buffer)
+    }
+
+    fn lines(buffer: &Arc<Mutex<Vec<u8>>>) -> Vec<String> {
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap().lines().map(String::from).collect()
+    }
 
     #[test]
     fn should_return_author_identity() {
@@ -13,6 +25,7 @@ mod uci_tests {
         assert_eq!(identity[3], "uciok");
     }
 
+    #[cfg(not(feature = "tune"))]
     #[test]
     fn should_respond_to_the_uciok_cmd() {
         let mut cursor = Cursor::new(Vec::new());
@@ -25,7 +38,12 @@ mod uci_tests {
         assert_eq!(result[0], "id name: papa");
         assert_eq!(result[1], "id author: Tolumide");
         assert_eq!(result[2], "id email: tolumideshopein@gmail.com");
-        assert_eq!(result[3], "uciok");
+        assert!(result.contains(&"option name SyzygyProbeDepth type spin default 1 min 1 max 100"));
+        // the skill levels are not calibrated, so no Elo is offered
+        assert!(!result.iter().any(|l| l.contains("UCI_Elo") || l.contains("UCI_LimitStrength")));
+        assert!(result.contains(&"option name Skill Level type spin default 20 min 0 max 20"));
+        assert!(result.contains(&"option name Contempt type spin default 0 min -100 max 100"));
+        assert_eq!(result[3 + UCI::options().len()], "uciok");
     }
 
     #[test]
@@ -78,4 +96,274 @@ mod uci_tests {
 
         assert_eq!(expected.trim(), result.trim());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_print_the_evaluation_breakdown_with_the_eval_cmd() {
+        let mut uci = UCI::default();
+        let _ = uci.process_input(format!("position fen {TRICKY_POSITION}"), Cursor::new(Vec::new()));
+
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("eval"), &mut cursor);
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+
+        assert!(result.contains("NNUE evaluation:"));
+        assert!(result.contains("HCE evaluation:"));
+        assert!(result.contains("Material:"));
+        // 8 ranks, each with a row of pieces, a row of values, and a separator
+        assert_eq!(result.lines().filter(|l| l.starts_with('|')).count(), 16);
+    }
+
+    #[test]
+    fn should_apply_the_syzygy_probe_options() {
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("setoption name SyzygyProbeDepth value 4"), &mut cursor);
+        let _ = uci.process_input(String::from("setoption name SyzygyProbeLimit value 5"), &mut cursor);
+
+        assert!(cursor.get_ref().is_empty());
+        assert_eq!(uci.tb.probe_depth, 4);
+        assert_eq!(uci.tb.probe_limit, 5);
+
+        let _ = uci.process_input(String::from("setoption name SyzygyProbeLimit value many"), &mut cursor);
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert_eq!(result, "Expected integer but got: many\n");
+        assert_eq!(uci.tb.probe_limit, 5);
+    }
+
+    #[test]
+    fn should_apply_the_use_nnue_option() {
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(uci.use_nnue, crate::nnue::EMBEDDED_NET);
+
+        let _ = uci.process_input(String::from("setoption name UseNNUE value false"), &mut cursor);
+        assert!(cursor.get_ref().is_empty());
+        assert!(!uci.use_nnue);
+
+        let _ = uci.process_input(String::from("setoption name UseNNUE value maybe"), &mut cursor);
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert_eq!(result, "Expected true or false but got: maybe\n");
+        assert!(!uci.use_nnue);
+    }
+
+    #[test]
+    fn positions_should_use_the_handcrafted_evaluation_without_nnue() {
+        use crate::{board::position::Position, search::evaluation::Evaluation};
+
+        let board = Board::try_from(TRICKY_POSITION).unwrap();
+        let mut position = Position::with(board.clone());
+        position.use_nnue = false;
+        assert_eq!(position.evaluate(), Evaluation::evaluate(&board));
+    }
+
+    #[test]
+    fn should_apply_the_strength_options() {
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(uci.strength.skill().level(), 20);
+
+        let _ = uci.process_input(String::from("setoption name Skill Level value 5"), &mut cursor);
+        assert_eq!(uci.strength.skill().level(), 5);
+
+        let _ = uci.process_input(String::from("setoption name Skill Level value 99"), &mut cursor);
+        assert_eq!(uci.strength.skill().level(), 20);
+        assert!(cursor.get_ref().is_empty());
+
+        // the same seed gives the same skills
+        let lines = [(Move::from(1u16), 20), (Move::from(2u16), 10), (Move::from(3u16), 0)];
+        let _ = uci.process_input(String::from("setoption name SkillSeed value 42"), &mut cursor);
+        let first = [uci.strength.skill().pick(&lines), uci.strength.skill().pick(&lines), uci.strength.skill().pick(&lines)];
+        let _ = uci.process_input(String::from("setoption name SkillSeed value 42"), &mut cursor);
+        assert_eq!(first, [uci.strength.skill().pick(&lines), uci.strength.skill().pick(&lines), uci.strength.skill().pick(&lines)]);
+
+        let _ = uci.process_input(String::from("setoption name Skill Level value weak"), &mut cursor);
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert_eq!(result, "Expected integer but got: weak\n");
+    }
+
+
+    #[test]
+    fn should_apply_the_contempt_options() {
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+
+        let _ = uci.process_input(String::from("setoption name Contempt value -25"), &mut cursor);
+        let _ = uci.process_input(String::from("setoption name DynamicContempt value true"), &mut cursor);
+        assert!(cursor.get_ref().is_empty());
+        assert_eq!((uci.contempt.value, uci.contempt.dynamic), (-25, true));
+
+        let _ = uci.process_input(String::from("setoption name Contempt value 1000"), &mut cursor);
+        assert_eq!(uci.contempt.value, 100);
+    }
+
+    #[test]
+    fn positions_longer_than_the_accumulator_stack_should_be_searchable() {
+        let mut uci = UCI::default();
+        // 144 plies of knight moves
+        let moves = ["g1f3", "g8f6", "f3g1", "f6g8"].repeat(36).join(" ");
+        let _ = uci.process_input(format!("position startpos moves {moves}"), Cursor::new(Vec::new()));
+        assert!(uci.position.as_ref().unwrap().board.to_fen().starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq"));
+
+        let _ = uci.process_input(String::from("go depth 3"), Cursor::new(Vec::new()));
+        uci.wait();
+    }
+
+    #[test]
+    fn go_should_print_the_bestmove_of_the_last_iteration() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position startpos moves e2e4"), Cursor::new(Vec::new()));
+        let _ = uci.process_input(String::from("go depth 3"), Cursor::new(Vec::new()));
+        uci.wait();
+
+        let lines = lines(&buffer);
+        let depths = lines.iter().filter(|l| l.starts_with("info depth ")).map(|l| l.split(' ').nth(2).unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(depths, ["1", "2", "3"]);
+        // the bestmove (and the ponder move) is the start of the last PV
+        let pv = lines[2].split(" pv ").nth(1).unwrap().split(' ').collect::<Vec<_>>();
+        assert_eq!(lines[3], format!("bestmove {} ponder {}", pv[0], pv[1]));
+    }
+
+    #[test]
+    fn stop_should_end_an_infinite_search_while_the_loop_reads_commands() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position fen ") + TRICKY_POSITION, Cursor::new(Vec::new()));
+
+        let start = Instant::now();
+        assert_eq!(uci.process_input(String::from("go infinite"), Cursor::new(Vec::new())).unwrap(), true);
+        thread::sleep(Duration::from_millis(200));
+
+        // the search runs on its own thread, the loop still answers
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("isready"), &mut cursor);
+        assert_eq!(cursor.get_ref().as_slice(), b"readyok\n");
+        assert!(!lines(&buffer).iter().any(|l| l.starts_with("bestmove")));
+
+        // stop does not end the loop (unlike quit)
+        assert_eq!(uci.process_input(String::from("stop"), Cursor::new(Vec::new())).unwrap(), true);
+        uci.wait();
+        assert!(lines(&buffer).last().unwrap().starts_with("bestmove "));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn a_pondering_search_should_only_print_its_bestmove_after_ponderhit() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position startpos moves e2e4 e7e5"), Cursor::new(Vec::new()));
+        let _ = uci.process_input(String::from("go ponder time 10"), Cursor::new(Vec::new()));
+        thread::sleep(Duration::from_millis(300));
+        assert!(!lines(&buffer).iter().any(|l| l.starts_with("bestmove")));
+
+        let _ = uci.process_input(String::from("ponderhit"), Cursor::new(Vec::new()));
+        uci.wait();
+        assert!(lines(&buffer).last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn go_mate_should_report_a_proven_mate() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position fen 5r1k/6pp/8/3QN3/8/8/6PP/6K1 w - - 0 1"), Cursor::new(Vec::new()));
+        let _ = uci.process_input(String::from("setoption name MateChecksOnly value true"), Cursor::new(Vec::new()));
+
+        let _ = uci.process_input(String::from("go mate 5"), Cursor::new(Vec::new()));
+        uci.wait();
+        let result = lines(&buffer);
+
+        assert_eq!(result.len(), 2);
+        assert!(result[0].starts_with("info depth 7 nodes "));
+        assert!(result[0].ends_with(" score mate 4 pv e5f7 h8g8 f7h6 g8h8 d5g8 f8g8 h6f7"));
+        assert_eq!(result[1], "bestmove e5f7");
+    }
+
+    #[test]
+    fn a_stop_should_not_carry_over_to_the_next_search() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position fen 5r1k/6pp/8/3QN3/8/8/6PP/6K1 w - - 0 1"), Cursor::new(Vec::new()));
+        let _ = uci.process_input(String::from("stop"), Cursor::new(Vec::new()));
+
+        let _ = uci.process_input(String::from("go ponder mate 5"), Cursor::new(Vec::new()));
+        uci.wait();
+        assert_eq!(lines(&buffer).last().map(String::as_str), Some("bestmove e5f7"));
+    }
+
+    #[test]
+    fn go_mate_should_report_when_there_is_no_mate() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position startpos"), Cursor::new(Vec::new()));
+
+        let _ = uci.process_input(String::from("go mate 2"), Cursor::new(Vec::new()));
+        uci.wait();
+        assert_eq!(lines(&buffer), ["info string no mate in 2", "bestmove 0000"]);
+    }
+
+    #[test]
+    fn stop_should_end_a_long_mate_search() {
+        let (mut uci, buffer) = uci_with_output();
+        let _ = uci.process_input(String::from("position fen ") + TRICKY_POSITION, Cursor::new(Vec::new()));
+        let _ = uci.process_input(String::from("setoption name MateChecksOnly value false"), Cursor::new(Vec::new()));
+
+        let start = Instant::now();
+        let _ = uci.process_input(String::from("go mate 30"), Cursor::new(Vec::new()));
+        thread::sleep(Duration::from_millis(200));
+
+        // the mate search runs on the search thread, the loop still answers
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("isready"), &mut cursor);
+        assert_eq!(cursor.get_ref().as_slice(), b"readyok\n");
+        assert!(lines(&buffer).is_empty());
+
+        let _ = uci.process_input(String::from("stop"), Cursor::new(Vec::new()));
+        uci.wait();
+        assert_eq!(lines(&buffer), ["bestmove 0000"]);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(not(feature = "syzygy"))]
+    #[test]
+    fn syzygy_path_should_be_rejected_without_the_syzygy_feature() {
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = UCI::default().process_input(String::from("setoption name SyzygyPath value /tmp/syzygy"), &mut cursor);
+
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert_eq!(result, "SyzygyPath (built without the syzygy feature) is not supported\n");
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    #[test]
+    fn use_nnue_should_be_rejected_without_the_nnue_feature() {
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+        assert!(UCI::options().contains(&String::from("option name UseNNUE type check default false")));
+
+        let _ = uci.process_input(String::from("setoption name UseNNUE value true"), &mut cursor);
+        let result = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert_eq!(result, "UseNNUE (built without the nnue feature) is not supported\n");
+        assert!(!uci.use_nnue);
+    }
+
+    #[cfg(feature = "tune")]
+    #[test]
+    fn tune_builds_should_expose_the_search_parameters() {
+        use crate::search::tunables::{futility_improving, Tunable, TUNABLES};
+
+        let mut uci = UCI::default();
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("uci"), &mut cursor);
+        let output = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        let result = output.lines().collect::<Vec<_>>();
+        assert_eq!(result[0], "id name: papa");
+        let options = UCI::options().len();
+        assert_eq!(result[3 + options], "option name aspiration_delta type spin default 25 min 10 max 60");
+        assert_eq!(result.len(), 3 + options + TUNABLES.len() + 1);
+        assert_eq!(result.last(), Some(&"uciok"));
+
+        let mut cursor = Cursor::new(Vec::new());
+        let _ = uci.process_input(String::from("setoption name futility_improving value 60"), &mut cursor);
+        assert!(cursor.get_ref().is_empty());
+        assert_eq!(futility_improving(), 60);
+
+        let _ = uci.process_input(String::from("spsa"), &mut cursor);
+        let output = String::from_utf8(cursor.get_ref()[..].to_vec()).unwrap();
+        assert!(output.lines().any(|l| l == "futility_improving, int, 60, 20, 80, 5, 0.002"));
+        Tunable::find("futility_improving").unwrap().set(50);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{default, ops::Deref};
 
-use crate::{constants::REDUCTIONS, search::constants::NodeType};
+use crate::search::{constants::NodeType, tunables::lmr_divisor};
 
 /// LMR: Late Move Reduction (LMR) table
 #[derive(Debug)]
@@ -27,9 +27,7 @@ impl LmrTable {
         for impr in 0..2 {
             for depth in 1..64 {
                 for mv_n in 1..64 {
-                    // https://skemman.is/bitstream/1946/34940/1/Master_Project_Final.pdf   (1.7-Search Depth Reduction)
-                    let r = (depth as f64).log(2.0) * (mv_n as f64).log(2.0)/ 1.95;
-                    reductions[0][impr][depth][mv_n] = r as i16;
+                    let r = Self::formula(depth, mv_n);
                     reductions[0][impr][depth][mv_n] = (r as i16 - 1).max(1);
 
                     // Increase reduction for non-PV nodes when eval is not improving
@@ -42,6 +40,12 @@ impl LmrTable {
 
         Self(reductions)
     }
+
+    // https://skemman.is/bitstream/1946/34940/1/Master_Project_Final.pdf   (1.7-Search Depth Reduction)
+    // log2(depth) * log2(moves) / (lmr_divisor / 100)
+    fn formula(depth: usize, mv_n: usize) -> f64 {
+        (depth as f64).log(2.0) * (mv_n as f64).log(2.0) / (lmr_divisor() as f64 / 100.0)
+    }
 }
 
 impl Deref for LmrTable {
@@ -52,8 +56,17 @@ impl Deref for LmrTable {
 }
 
 
+#[cfg(not(feature = "tune"))]
 pub(crate) fn reduction<NT: NodeType>(improving: bool, depth: usize, moves_searched: usize) -> i16 {
-    REDUCTIONS[NT::PV as usize][improving as usize][depth.min(63)][moves_searched]
+    crate::constants::REDUCTIONS[NT::PV as usize][improving as usize][depth.min(63)][moves_searched.min(63)]
+}
+
+/// The table is built once, so tune builds compute the reductions on the fly (lmr_divisor can change between games)
+#[cfg(feature = "tune")]
+pub(crate) fn reduction<NT: NodeType>(_improving: bool, depth: usize, moves_searched: usize) -> i16 {
+    let (depth, mv_n) = (depth.min(63), moves_searched.min(63));
+    if NT::PV || depth == 0 || mv_n == 0 { return 0 }
+    (LmrTable::formula(depth, mv_n) as i16 - 1).max(1)
 }
 
 
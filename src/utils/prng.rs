@@ -1,6 +1,7 @@
 use std::ops::Deref;
 
 // Pseudo-Random Number Generator (PRNG)
+#[derive(Debug)]
 #[repr(transparent)]
 pub(crate) struct PRNG(u32);
 